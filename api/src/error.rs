@@ -0,0 +1,40 @@
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+use tracing::error;
+
+pub enum ApiError {
+    /// The requested resource does not exist (404)
+    NotFound(String),
+    /// A query parameter could not be parsed or is out of range (422)
+    InvalidFilter(String),
+    /// ClickHouse or any other internal failure (500)
+    Internal(anyhow::Error),
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError::Internal(e)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            ApiError::InvalidFilter(message) => (StatusCode::UNPROCESSABLE_ENTITY, message),
+            ApiError::Internal(e) => {
+                error!("Query error: {:?}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "internal query error".to_string(),
+                )
+            }
+        };
+
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
@@ -0,0 +1,59 @@
+mod error;
+mod params;
+mod routes;
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use clap::Parser;
+use indexer_core::config::Config;
+use processor::ClickhouseClient;
+use processor::query::QueryService;
+use tracing::info;
+
+#[derive(Parser)]
+#[command(name = "dex-api")]
+#[command(about = "Serve DEX transaction data over HTTP")]
+struct Cli {
+    /// TOML config file, environment variables override its values
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Port to listen on
+    #[arg(long, env = "API_PORT", default_value_t = 3000)]
+    port: u16,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .init();
+
+    dotenv::dotenv().ok();
+
+    let cli = Cli::parse();
+
+    let config = Config::load(cli.config)?;
+
+    let client = ClickhouseClient::builder()
+        .url(&config.clickhouse_url)
+        .user(&config.clickhouse_user)
+        .password(&config.clickhouse_password)
+        .database(&config.clickhouse_db)
+        .compression(config.clickhouse_compression)
+        .build()
+        .await?;
+
+    let qs = Arc::new(
+        QueryService::new(client).with_timeout(Duration::from_millis(config.query_timeout_ms)),
+    );
+    let app = routes::router(qs);
+
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", cli.port)).await?;
+    info!("API listening on {}", listener.local_addr()?);
+
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use processor::query::{TimeBucket, TimePeriod, TransactionFilters};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+
+use crate::error::ApiError;
+
+/// Parse a raw query string into `T`, rejecting malformed values with a 422
+pub fn parse_query<T: DeserializeOwned>(query: Option<String>) -> Result<T, ApiError> {
+    serde_qs::from_str(query.as_deref().unwrap_or_default())
+        .map_err(|e| ApiError::InvalidFilter(e.to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionsParams {
+    pub limit: Option<usize>,
+    pub period: Option<String>,
+    pub success: Option<bool>,
+    pub min_fee: Option<u64>,
+    pub max_fee: Option<u64>,
+    pub min_cu: Option<u64>,
+    pub max_cu: Option<u64>,
+    pub start_slot: Option<u64>,
+    pub end_slot: Option<u64>,
+}
+
+impl TransactionsParams {
+    pub fn filters(&self) -> Result<TransactionFilters, ApiError> {
+        let slot_range = match (self.start_slot, self.end_slot) {
+            (Some(start), Some(end)) => Some((start, end)),
+            (None, None) => None,
+            _ => {
+                return Err(ApiError::InvalidFilter(
+                    "start_slot and end_slot must be provided together".to_string(),
+                ));
+            }
+        };
+
+        let mut builder = TransactionFilters::builder();
+        if let Some(period) = parse_period(self.period.as_deref())? {
+            builder = builder.with_period(period);
+        }
+        if let Some(success) = self.success {
+            builder = builder.with_success(success);
+        }
+        if let Some(min_fee) = self.min_fee {
+            builder = builder.with_min_fee(min_fee);
+        }
+        if let Some(max_fee) = self.max_fee {
+            builder = builder.with_max_fee(max_fee);
+        }
+        if let Some(min_cu) = self.min_cu {
+            builder = builder.with_min_cu(min_cu);
+        }
+        if let Some(max_cu) = self.max_cu {
+            builder = builder.with_max_cu(max_cu);
+        }
+        if let Some((start, end)) = slot_range {
+            builder = builder.with_slot_range(start, end);
+        }
+
+        // Inverted ranges are rejected by the builder
+        builder
+            .build()
+            .map_err(|e| ApiError::InvalidFilter(e.to_string()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PeriodParams {
+    pub period: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimeseriesParams {
+    pub period: Option<String>,
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FailedParams {
+    pub period: Option<String>,
+    pub limit: Option<usize>,
+}
+
+pub fn parse_period(period: Option<&str>) -> Result<Option<TimePeriod>, ApiError> {
+    period
+        .map(str::parse::<TimePeriod>)
+        .transpose()
+        .map_err(|e| ApiError::InvalidFilter(e.to_string()))
+}
+
+pub fn parse_bucket(bucket: Option<&str>) -> Result<Option<TimeBucket>, ApiError> {
+    bucket
+        .map(str::parse::<TimeBucket>)
+        .transpose()
+        .map_err(|e| ApiError::InvalidFilter(e.to_string()))
+}
@@ -0,0 +1,147 @@
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+use axum::{
+    Json, Router,
+    extract::{Path, RawQuery, State},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+};
+use futures::{Stream, stream};
+use processor::query::{
+    FeeStats, QueryService, SlotStats, TimeBucket, TimePeriod, TpsDataPoint, TransactionResult,
+};
+use serde_json::{Value, json};
+use tokio::time::MissedTickBehavior;
+use tracing::warn;
+
+use crate::{
+    error::ApiError,
+    params::{
+        FailedParams, PeriodParams, TimeseriesParams, TransactionsParams, parse_bucket,
+        parse_period, parse_query,
+    },
+};
+
+type AppState = State<Arc<QueryService>>;
+
+pub fn router(qs: Arc<QueryService>) -> Router {
+    Router::new()
+        .route("/transactions", get(recent_transactions))
+        .route("/transactions/failed", get(failed_transactions))
+        .route("/transactions/{signature}", get(transaction))
+        .route("/tps", get(tps))
+        .route("/tps/timeseries", get(tps_timeseries))
+        .route("/fees/stats", get(fee_stats))
+        .route("/slots/stats", get(slot_stats))
+        .route("/stream/tps", get(tps_stream))
+        .with_state(qs)
+}
+
+fn period_or_default(period: Option<String>) -> Result<TimePeriod, ApiError> {
+    Ok(parse_period(period.as_deref())?.unwrap_or(TimePeriod::Last24Hours))
+}
+
+async fn recent_transactions(
+    State(qs): AppState,
+    RawQuery(query): RawQuery,
+) -> Result<Json<Vec<TransactionResult>>, ApiError> {
+    let params: TransactionsParams = parse_query(query)?;
+    let filters = params.filters()?;
+
+    let txs = qs
+        .get_recent_transactions(params.limit.unwrap_or(10), filters)
+        .await?;
+
+    Ok(Json(txs))
+}
+
+async fn transaction(
+    State(qs): AppState,
+    Path(signature): Path<String>,
+) -> Result<Json<TransactionResult>, ApiError> {
+    qs.get_transaction(&signature)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound(format!("transaction {} not found", signature)))
+}
+
+async fn failed_transactions(
+    State(qs): AppState,
+    RawQuery(query): RawQuery,
+) -> Result<Json<Vec<TransactionResult>>, ApiError> {
+    let params: FailedParams = parse_query(query)?;
+    let period = period_or_default(params.period)?;
+
+    Ok(Json(
+        qs.get_failed_transactions(period, params.limit).await?,
+    ))
+}
+
+async fn tps(State(qs): AppState, RawQuery(query): RawQuery) -> Result<Json<Value>, ApiError> {
+    let params: PeriodParams = parse_query(query)?;
+    let period = period_or_default(params.period)?;
+
+    let tps = qs.get_tps(period).await?;
+    Ok(Json(json!({ "tps": tps })))
+}
+
+async fn tps_timeseries(
+    State(qs): AppState,
+    RawQuery(query): RawQuery,
+) -> Result<Json<Vec<TpsDataPoint>>, ApiError> {
+    let params: TimeseriesParams = parse_query(query)?;
+    let period = period_or_default(params.period)?;
+    let bucket = parse_bucket(params.bucket.as_deref())?.unwrap_or(TimeBucket::Hour);
+
+    Ok(Json(qs.get_tps_timeseries(period, bucket).await?))
+}
+
+async fn fee_stats(
+    State(qs): AppState,
+    RawQuery(query): RawQuery,
+) -> Result<Json<FeeStats>, ApiError> {
+    let params: PeriodParams = parse_query(query)?;
+    let period = period_or_default(params.period)?;
+
+    Ok(Json(qs.get_fee_stats(period).await?))
+}
+
+async fn slot_stats(
+    State(qs): AppState,
+    RawQuery(query): RawQuery,
+) -> Result<Json<SlotStats>, ApiError> {
+    let params: PeriodParams = parse_query(query)?;
+    let period = period_or_default(params.period)?;
+
+    Ok(Json(qs.get_slot_stats(period).await?))
+}
+
+/// Push the last hour's TPS and success rate every second as server-sent events.
+/// `Sse` sets the `text/event-stream` and `no-cache` headers.
+async fn tps_stream(State(qs): AppState) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let events = stream::unfold((qs, ticker), |(qs, mut ticker)| async move {
+        ticker.tick().await;
+
+        let stats = tokio::try_join!(
+            qs.get_tps(TimePeriod::LastHour),
+            qs.get_success_rate(TimePeriod::LastHour)
+        );
+        let event = match stats {
+            Ok((tps, success_rate)) => Event::default()
+                .data(json!({ "tps": tps, "success_rate": success_rate }).to_string()),
+            Err(e) => {
+                warn!("Failed to query TPS for the stream: {}", e);
+                Event::default().event("error").data(e.to_string())
+            }
+        };
+
+        // Browsers reconnect after 3 seconds if the connection drops
+        let event = event.retry(Duration::from_secs(3));
+        Some((Ok(event), (qs, ticker)))
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
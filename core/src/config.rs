@@ -1,26 +1,223 @@
-use anyhow::Result;
-use std::env;
+use anyhow::{Context, Result};
+use processor::clickhouse::parse_compression;
+use serde::Deserialize;
+use std::{borrow::Cow, env, fmt, fs, path::Path};
+use validator::{Validate, ValidateUrl, ValidationError};
 
+/// `validate()` checks the values that would otherwise only fail once the indexer
+/// connects, with a message naming the setting to fix
+#[derive(Validate)]
 pub struct Config {
+    #[validate(custom(function = "validate_grpc_endpoint"))]
     pub yellowstone_grpc_endpoint: String,
     pub yellowstone_grpc_token: Option<String>,
+    #[validate(custom(function = "validate_clickhouse_url"))]
     pub clickhouse_url: String,
+    #[validate(length(min = 1, message = "set CLICKHOUSE_USER or [clickhouse] user"))]
     pub clickhouse_user: String,
+    #[validate(length(min = 1, message = "set CLICKHOUSE_PASSWORD or [clickhouse] password"))]
     pub clickhouse_password: String,
+    #[validate(length(min = 1, message = "set CLICKHOUSE_DB or [clickhouse] database"))]
     pub clickhouse_db: String,
+    pub metrics_port: u16,
+    pub checkpoint_file: String,
+    pub processor_channel_capacity: usize,
+    pub shutdown_timeout_secs: u64,
+    pub dead_letter_path: String,
+    pub query_timeout_ms: u64,
+    pub data_retention_days: Option<u32>,
+    pub clickhouse_compression: bool,
+}
+
+fn invalid(code: &'static str, message: String) -> ValidationError {
+    ValidationError::new(code).with_message(Cow::Owned(message))
+}
+
+fn validate_grpc_endpoint(endpoint: &str) -> Result<(), ValidationError> {
+    if endpoint.is_empty() {
+        return Err(invalid(
+            "required",
+            "set YELLOWSTONE_GRPC_ENDPOINT or [yellowstone] endpoint".to_string(),
+        ));
+    }
+    if !endpoint.validate_url() {
+        return Err(invalid(
+            "uri",
+            format!(
+                "YELLOWSTONE_GRPC_ENDPOINT must be a valid URI like https://host:443, got: '{}'",
+                endpoint
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_clickhouse_url(url: &str) -> Result<(), ValidationError> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(invalid(
+            "scheme",
+            format!(
+                "CLICKHOUSE_URL must start with http:// or https://, got: '{}'",
+                url
+            ),
+        ));
+    }
+    if !url.validate_url() {
+        return Err(invalid(
+            "url",
+            format!("CLICKHOUSE_URL must be a valid URL, got: '{}'", url),
+        ));
+    }
+    Ok(())
+}
+
+/// Layout of the TOML config file, every value can be overridden by its env var
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    #[serde(default)]
+    clickhouse: ClickhouseSection,
+    #[serde(default)]
+    yellowstone: YellowstoneSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClickhouseSection {
+    url: Option<String>,
+    user: Option<String>,
+    password: Option<String>,
+    database: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct YellowstoneSection {
+    endpoint: Option<String>,
+    token: Option<String>,
 }
 
 impl Config {
+    /// Load the config from environment variables only
     pub fn load_config() -> Result<Self> {
+        Self::from_sources(FileConfig::default())
+    }
+
+    /// Load the config from a TOML file, environment variables take precedence
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let file: FileConfig = toml::from_str(&contents)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+
+        Self::from_sources(file)
+    }
+
+    /// `from_file` if a path is given, `load_config` otherwise
+    pub fn load(path: Option<impl AsRef<Path>>) -> Result<Self> {
+        match path {
+            Some(path) => Self::from_file(path),
+            None => Self::load_config(),
+        }
+    }
+
+    fn from_sources(file: FileConfig) -> Result<Self> {
         Ok(Self {
             yellowstone_grpc_endpoint: env::var("YELLOWSTONE_GRPC_ENDPOINT")
+                .ok()
+                .or(file.yellowstone.endpoint)
                 .unwrap_or("".to_string()),
-            yellowstone_grpc_token: env::var("YELLOWSTONE_GRPC_TOKEN").ok(),
+            yellowstone_grpc_token: env::var("YELLOWSTONE_GRPC_TOKEN")
+                .ok()
+                .or(file.yellowstone.token),
             clickhouse_url: env::var("CLICKHOUSE_URL")
+                .ok()
+                .or(file.clickhouse.url)
                 .unwrap_or("http://localhost:8123".to_string()),
-            clickhouse_db: env::var("DATABASE_NAME").unwrap_or("indexer".to_string()),
-            clickhouse_user: env::var("CLICKHOUSE_USER").unwrap_or("default".to_string()),
-            clickhouse_password: env::var("CLICKHOUSE_PASSWORD").unwrap_or("pass123".to_string()),
+            clickhouse_db: env::var("CLICKHOUSE_DB")
+                .or_else(|_| env::var("DATABASE_NAME"))
+                .ok()
+                .or(file.clickhouse.database)
+                .unwrap_or("indexer".to_string()),
+            clickhouse_user: env::var("CLICKHOUSE_USER")
+                .ok()
+                .or(file.clickhouse.user)
+                .unwrap_or("default".to_string()),
+            clickhouse_password: env::var("CLICKHOUSE_PASSWORD")
+                .ok()
+                .or(file.clickhouse.password)
+                .unwrap_or("pass123".to_string()),
+            metrics_port: match env::var("METRICS_PORT") {
+                Ok(port) => port.parse().context("METRICS_PORT must be a valid port")?,
+                Err(_) => 9090,
+            },
+            processor_channel_capacity: match env::var("PROCESSOR_CHANNEL_CAPACITY") {
+                Ok(capacity) => capacity
+                    .parse()
+                    .ok()
+                    .filter(|capacity| *capacity > 0)
+                    .context("PROCESSOR_CHANNEL_CAPACITY must be a positive integer")?,
+                Err(_) => 10_000,
+            },
+            shutdown_timeout_secs: match env::var("SHUTDOWN_TIMEOUT_SECS") {
+                Ok(secs) => secs
+                    .parse()
+                    .context("SHUTDOWN_TIMEOUT_SECS must be a number of seconds")?,
+                Err(_) => 30,
+            },
+            dead_letter_path: env::var("DEAD_LETTER_PATH")
+                .unwrap_or("./dead_letter.jsonl".to_string()),
+            checkpoint_file: env::var("CHECKPOINT_FILE").unwrap_or("checkpoint.slot".to_string()),
+            query_timeout_ms: match env::var("QUERY_TIMEOUT_MS") {
+                Ok(ms) => ms
+                    .parse()
+                    .ok()
+                    .filter(|ms| *ms > 0)
+                    .context("QUERY_TIMEOUT_MS must be a positive integer")?,
+                Err(_) => 30_000,
+            },
+            data_retention_days: match env::var("DATA_RETENTION_DAYS") {
+                Ok(days) => Some(
+                    days.parse()
+                        .ok()
+                        .filter(|days| *days > 0)
+                        .context("DATA_RETENTION_DAYS must be a positive number of days")?,
+                ),
+                Err(_) => None,
+            },
+            clickhouse_compression: match env::var("CLICKHOUSE_COMPRESSION") {
+                Ok(compression) => parse_compression(&compression)?,
+                Err(_) => true,
+            },
         })
     }
 }
+
+// Manual impl so secrets never end up in logs
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("yellowstone_grpc_endpoint", &self.yellowstone_grpc_endpoint)
+            .field(
+                "yellowstone_grpc_token",
+                &self.yellowstone_grpc_token.as_ref().map(|_| "***"),
+            )
+            .field("clickhouse_url", &self.clickhouse_url)
+            .field("clickhouse_user", &self.clickhouse_user)
+            .field("clickhouse_password", &"***")
+            .field("clickhouse_db", &self.clickhouse_db)
+            .field("metrics_port", &self.metrics_port)
+            .field("checkpoint_file", &self.checkpoint_file)
+            .field(
+                "processor_channel_capacity",
+                &self.processor_channel_capacity,
+            )
+            .field("shutdown_timeout_secs", &self.shutdown_timeout_secs)
+            .field("dead_letter_path", &self.dead_letter_path)
+            .field("query_timeout_ms", &self.query_timeout_ms)
+            .field("data_retention_days", &self.data_retention_days)
+            .field("clickhouse_compression", &self.clickhouse_compression)
+            .finish()
+    }
+}
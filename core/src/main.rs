@@ -1,18 +1,83 @@
-mod config;
-use std::time::Duration;
-
-use crate::config::Config;
-use anyhow::Result;
-use ingest::{types::IndexEvent, yellowstone_client::YellowstoneClient};
+mod metrics_server;
+mod websocket;
+use std::{env, path::PathBuf, time::Duration};
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use indexer_core::config::Config;
+use ingest::{
+    checkpoint::CheckpointStore,
+    multi_stream::{MultiStreamConfig, MultiStreamIngest},
+    subscriptions::Subscriptions,
+    types::IndexEvent,
+};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
+
+use processor::{
+    clickhouse_types::ClickHouseTransaction,
+    worker::{Processor, ProcessorConfig},
+};
+use tokio::{
+    signal,
+    sync::{broadcast, mpsc::Receiver, watch},
+};
+use tracing::{error, info};
+use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
+use validator::Validate;
+
+#[derive(Parser)]
+#[command(name = "indexer")]
+#[command(about = "Stream DEX transactions from Yellowstone gRPC into ClickHouse")]
+struct Cli {
+    /// TOML config file, environment variables override its values
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// TOML file listing the programs to stream, defaults to the built-in DEX programs
+    #[arg(long)]
+    subscriptions: Option<PathBuf>,
+
+    /// TOML file splitting the programs over several gRPC connections, replaces the
+    /// Yellowstone endpoint of the config and `--subscriptions`
+    #[arg(long, conflicts_with = "subscriptions")]
+    streams: Option<PathBuf>,
+}
 
-use processor::worker::Processor;
-use tokio::sync::mpsc::{Receiver, Sender};
-use tracing::{error, info, warn};
+/// Log to stdout, filtered by `RUST_LOG` (default `info`). If `OTEL_EXPORTER_OTLP_ENDPOINT`
+/// is set, spans are also exported there over OTLP/HTTP. The returned provider has to be
+/// shut down before exiting to export the last spans.
+fn setup_logging() -> Result<Option<SdkTracerProvider>> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer());
+
+    let Ok(endpoint) = env::var("OTEL_EXPORTER_OTLP_ENDPOINT") else {
+        registry.init();
+        return Ok(None);
+    };
+
+    // The exporter reads the endpoint (and any other OTEL_EXPORTER_OTLP_* setting) itself
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()
+        .context("Failed to create the OTLP span exporter")?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name("solana-grpc-indexer")
+                .build(),
+        )
+        .build();
 
-fn setup_logging() {
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
+    registry
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("indexer")))
         .init();
+    info!("Exporting traces to {}", endpoint);
+
+    Ok(Some(provider))
 }
 
 fn setup_rustls() {
@@ -24,123 +89,157 @@ fn setup_rustls() {
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     setup_rustls();
-    setup_logging();
-
+    // Before the logging setup, which reads RUST_LOG and OTEL_EXPORTER_OTLP_ENDPOINT
     dotenv::dotenv().ok();
+    let tracer_provider = setup_logging()?;
+
+    let cli = Cli::parse();
+    let config = Config::load(cli.config)?;
+    config.validate().context("Invalid configuration")?;
+    info!("Loaded config: {:?}", config);
+
+    let ingest = match (&cli.streams, &cli.subscriptions) {
+        (Some(path), _) => {
+            let streams = MultiStreamConfig::from_file(path)?;
+            info!("Streaming over {} gRPC connections", streams.streams.len());
+            MultiStreamIngest::new(&streams)?
+        }
+        (None, Some(path)) => MultiStreamIngest::single(
+            config.yellowstone_grpc_endpoint,
+            config.yellowstone_grpc_token,
+            Subscriptions::from_config(path)?,
+        ),
+        (None, None) => MultiStreamIngest::single(
+            config.yellowstone_grpc_endpoint,
+            config.yellowstone_grpc_token,
+            Subscriptions::create_subscriptions()?,
+        ),
+    };
 
-    let config = Config::load_config()?;
-
-    let endpoint = config.yellowstone_grpc_endpoint;
-    let token = config.yellowstone_grpc_token;
     let clickhouse_url = config.clickhouse_url;
     let clickhouse_db = config.clickhouse_db;
     let clickhouse_user = config.clickhouse_user;
     let clickhouse_password = config.clickhouse_password;
+    let metrics_port = config.metrics_port;
+    let checkpoint = CheckpointStore::new(config.checkpoint_file);
+    let processor_checkpoint = checkpoint.clone();
+    let processor_config = ProcessorConfig {
+        dead_letter_path: Some(config.dead_letter_path.into()),
+        data_retention_days: config.data_retention_days,
+        compression: config.clickhouse_compression,
+        ..ProcessorConfig::from_env()?
+    };
+
+    // Programs added at runtime through the metrics server, see `/subscriptions/programs`
+    let (program_tx, program_rx) = tokio::sync::mpsc::channel::<String>(16);
+
+    // Inserted transactions, pushed to `/stream/transactions` WebSocket clients
+    let (tx_stream, _) = broadcast::channel(websocket::STREAM_CAPACITY);
+    let server_tx_stream = tx_stream.clone();
 
-    let (event_tx, event_rx) = tokio::sync::mpsc::channel::<IndexEvent>(10_000);
+    tokio::spawn(async move {
+        if let Err(e) = metrics_server::serve(metrics_port, program_tx, server_tx_stream).await {
+            error!("Metrics server error: {}", e);
+        }
+    });
+
+    // Bounded so a slow processor applies backpressure to the gRPC stream
+    let (event_tx, event_rx) =
+        tokio::sync::mpsc::channel::<IndexEvent>(config.processor_channel_capacity);
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
 
+    let ingest_shutdown = shutdown_rx.clone();
     tokio::spawn(async move {
-        if let Err(e) = run_yellowstone_with_reconnect(endpoint, token, event_tx).await {
+        if let Err(e) = ingest
+            .run(checkpoint, program_rx, event_tx, ingest_shutdown)
+            .await
+        {
             error!("stream error: {}", e);
         }
     });
 
-    tokio::spawn(async move {
-        if let Err(e) = run_processor(
+    let processor_handle = tokio::spawn(async move {
+        run_processor(
             event_rx,
             clickhouse_url,
             clickhouse_user,
             clickhouse_password,
             clickhouse_db,
+            processor_checkpoint,
+            processor_config,
+            tx_stream,
+            shutdown_rx,
         )
         .await
-        {
-            error!("Processor error: {}", e);
-        }
     });
 
-    futures::future::pending::<()>().await; // keeps main alive without using cpu
+    shutdown_signal().await?;
+    info!(
+        "Shutdown signal received, draining buffered events (timeout {}s)",
+        config.shutdown_timeout_secs
+    );
+
+    // The ingest task drops its sender, the processor drains the channel and flushes
+    shutdown_tx.send(true)?;
+
+    match tokio::time::timeout(
+        Duration::from_secs(config.shutdown_timeout_secs),
+        processor_handle,
+    )
+    .await
+    {
+        Ok(Ok(Ok(flushed))) => info!("Shutdown complete, {} transactions flushed", flushed),
+        Ok(Ok(Err(e))) => error!("Processor error during shutdown: {}", e),
+        Ok(Err(e)) => error!("Processor task failed: {}", e),
+        Err(_) => error!(
+            "Flushing did not finish within {}s, exiting with unflushed data",
+            config.shutdown_timeout_secs
+        ),
+    }
+
+    if let Some(provider) = tracer_provider
+        && let Err(e) = provider.shutdown()
+    {
+        error!("Failed to export the remaining spans: {}", e);
+    }
+
     Ok(())
 }
 
-async fn run_yellowstone_with_reconnect(
-    endpoint: String,
-    token: Option<String>,
-    event_tx: Sender<IndexEvent>,
-) -> Result<()> {
-    const MAX_BACKOFF: u64 = 60;
-    let mut backoff = 1u64;
-
-    loop {
-        match YellowstoneClient::connect_and_run(&endpoint, &token, &event_tx).await {
-            Ok(_) => {
-                warn!("Stream ended normally, reconnecting…");
-            }
-            Err(e) => {
-                error!("Stream error: {:?}", e);
-            }
-        }
+/// Resolves on Ctrl-C (SIGINT) or SIGTERM
+async fn shutdown_signal() -> Result<()> {
+    let mut sigterm = signal::unix::signal(signal::unix::SignalKind::terminate())?;
 
-        info!("Reconnecting in {}s", backoff);
-        tokio::time::sleep(Duration::from_secs(backoff)).await;
-        backoff = (backoff * 2).min(MAX_BACKOFF);
+    tokio::select! {
+        result = signal::ctrl_c() => result?,
+        _ = sigterm.recv() => {}
     }
+
+    Ok(())
 }
 
 pub async fn run_processor(
-    mut event_rx: Receiver<IndexEvent>,
+    event_rx: Receiver<IndexEvent>,
     clickhouse_url: String,
     clickhouse_user: String,
     clickhouse_password: String,
     clickhouse_db: String,
-) -> anyhow::Result<()> {
-    let mut processor = Processor::new(
-        &clickhouse_url,
-        &clickhouse_user,
-        &clickhouse_password,
-        &clickhouse_db,
-    )
-    .await
-    .expect("Clickhouse init failed");
-
-    // Create periodic flush interval
-    let flush_interval = processor.flush_interval;
-    let mut flush_timer = tokio::time::interval(flush_interval);
-    flush_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-
-    loop {
-        /*
-            Wait until either:
-            a new event arrives
-            OR the flush timer fires
-            then run the matching block.
-        */
-        tokio::select! {
-            // Process events
-            event = event_rx.recv() => {
-                match event {
-                    Some(event) => {
-                        if let Err(e) = processor.process_event(event).await {
-                            error!("Processing error: {}", e);
-                        }
-                    }
-                    None => {
-                        warn!("Event channel closed, flushing and exiting");
-                        break;
-                    }
-                }
-            }
-
-            // Periodic flush
-            _ = flush_timer.tick() => {
-                if let Err(e) = processor.flush_all().await {
-                    error!("Periodic flush error: {}", e);
-                }
-            }
-        }
-    }
-
-    processor.flush_all().await?;
+    checkpoint: CheckpointStore,
+    processor_config: ProcessorConfig,
+    tx_stream: broadcast::Sender<ClickHouseTransaction>,
+    shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<u64> {
+    let mut processor = Processor::with_config(processor_config)
+        .with_checkpoint(checkpoint)
+        .with_transaction_stream(tx_stream)
+        .build(
+            &clickhouse_url,
+            &clickhouse_user,
+            &clickhouse_password,
+            &clickhouse_db,
+        )
+        .await
+        .expect("Clickhouse init failed");
 
-    Ok(())
+    processor.run(event_rx, shutdown).await
 }
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::StatusCode,
+    http::header,
+    response::IntoResponse,
+    routing::{get, post},
+};
+use processor::clickhouse_types::ClickHouseTransaction;
+use tokio::sync::{broadcast, mpsc::Sender};
+use tracing::{error, info};
+
+use crate::websocket;
+
+/// Serve the processor's Prometheus metrics on `0.0.0.0:{port}/metrics`,
+/// `POST /subscriptions/programs/{program_id}` to start streaming another program
+/// and the `/stream/transactions` WebSocket
+pub async fn serve(
+    port: u16,
+    program_tx: Sender<String>,
+    tx_stream: broadcast::Sender<ClickHouseTransaction>,
+) -> Result<()> {
+    let app = Router::new()
+        .route("/metrics", get(metrics))
+        .route("/subscriptions/programs/{program_id}", post(add_program))
+        .with_state(program_tx)
+        .merge(websocket::router(tx_stream));
+
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", port)).await?;
+    info!("Metrics listening on {}", listener.local_addr()?);
+
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+async fn metrics() -> impl IntoResponse {
+    match processor::metrics::gather() {
+        Ok(body) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, processor::metrics::CONTENT_TYPE)],
+            body,
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to encode metrics: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Queue `program_id` to be added to the live subscription. Accepted even if the
+/// program is already subscribed, in which case the stream is left untouched.
+async fn add_program(
+    State(program_tx): State<Sender<String>>,
+    Path(program_id): Path<String>,
+) -> impl IntoResponse {
+    let is_pubkey = bs58::decode(&program_id)
+        .into_vec()
+        .is_ok_and(|bytes| bytes.len() == 32);
+    if !is_pubkey {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("invalid program id '{}'", program_id),
+        );
+    }
+
+    match program_tx.send(program_id.clone()).await {
+        Ok(_) => (
+            StatusCode::ACCEPTED,
+            format!("subscribing to {}", program_id),
+        ),
+        Err(_) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "ingest is shutting down".to_string(),
+        ),
+    }
+}
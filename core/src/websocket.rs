@@ -0,0 +1,91 @@
+use axum::{
+    Router,
+    extract::{
+        Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    response::IntoResponse,
+    routing::get,
+};
+use processor::{clickhouse_types::ClickHouseTransaction, query::TransactionResult};
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{error, warn};
+
+/// Transactions buffered per subscriber, slower clients skip the oldest ones
+pub const STREAM_CAPACITY: usize = 1000;
+
+/// Optional filters of `/stream/transactions`, e.g. `?dex=raydium&success=true`
+#[derive(Debug, Default, Deserialize)]
+struct StreamFilters {
+    dex: Option<String>,
+    success: Option<bool>,
+}
+
+impl StreamFilters {
+    fn matches(&self, tx: &ClickHouseTransaction) -> bool {
+        self.dex
+            .as_ref()
+            .is_none_or(|dex| dex.eq_ignore_ascii_case(&tx.dex))
+            && self.success.is_none_or(|success| success == tx.success)
+    }
+}
+
+/// `GET /stream/transactions` pushing every inserted transaction as a JSON `TransactionResult`
+pub fn router(tx_stream: broadcast::Sender<ClickHouseTransaction>) -> Router {
+    Router::new()
+        .route("/stream/transactions", get(stream_transactions))
+        .with_state(tx_stream)
+}
+
+async fn stream_transactions(
+    ws: WebSocketUpgrade,
+    State(tx_stream): State<broadcast::Sender<ClickHouseTransaction>>,
+    Query(filters): Query<StreamFilters>,
+) -> impl IntoResponse {
+    let transactions = tx_stream.subscribe();
+    ws.on_upgrade(move |socket| send_transactions(socket, transactions, filters))
+}
+
+async fn send_transactions(
+    mut socket: WebSocket,
+    mut transactions: broadcast::Receiver<ClickHouseTransaction>,
+    filters: StreamFilters,
+) {
+    loop {
+        tokio::select! {
+            received = transactions.recv() => {
+                let tx = match received {
+                    Ok(tx) => tx,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("WebSocket client lagging, skipped {} transactions", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+
+                if !filters.matches(&tx) {
+                    continue;
+                }
+
+                let json = match serde_json::to_string(&TransactionResult::from(&tx)) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        error!("Failed to serialize transaction {}: {}", tx.signature, e);
+                        continue;
+                    }
+                };
+
+                if socket.send(Message::Text(json.into())).await.is_err() {
+                    break;
+                }
+            }
+
+            // Nothing is expected from the client, only watch for it going away
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            }
+        }
+    }
+}
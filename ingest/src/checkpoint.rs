@@ -0,0 +1,50 @@
+use std::{fs, io::Write, path::PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Persists the last slot that was fully flushed to ClickHouse, so a restarted
+/// indexer can resume the stream from there instead of from "now"
+#[derive(Debug, Clone)]
+pub struct CheckpointStore {
+    path: PathBuf,
+}
+
+impl CheckpointStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Read the checkpointed slot, `None` if no checkpoint was written yet
+    pub fn load(&self) -> Result<Option<u64>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", self.path.display()));
+            }
+        };
+
+        let slot = contents
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid checkpoint in {}", self.path.display()))?;
+
+        Ok(Some(slot))
+    }
+
+    /// Write `slot` to a temporary file next to the checkpoint and rename it over
+    /// the checkpoint, so a crash mid-write never leaves a truncated file behind
+    pub fn save(&self, slot: u64) -> Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+
+        let mut file = fs::File::create(&tmp_path)
+            .with_context(|| format!("Failed to create {}", tmp_path.display()))?;
+        file.write_all(slot.to_string().as_bytes())?;
+        file.sync_all()?;
+
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to rename checkpoint to {}", self.path.display()))?;
+
+        Ok(())
+    }
+}
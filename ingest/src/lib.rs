@@ -1,3 +1,7 @@
+pub mod checkpoint;
+pub mod multi_stream;
 pub mod subscriptions;
+#[cfg(feature = "test-utils")]
+pub mod testing;
 pub mod types;
 pub mod yellowstone_client;
@@ -0,0 +1,237 @@
+use std::{fmt, fs, path::Path, time::Duration};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use tokio::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        watch,
+    },
+    task::JoinSet,
+};
+use tracing::{Instrument, error, info, info_span, warn};
+use yellowstone_grpc_proto::geyser::SubscribeRequest;
+
+use crate::{
+    checkpoint::CheckpointStore,
+    subscriptions::{SubscriptionConfig, Subscriptions},
+    types::IndexEvent,
+    yellowstone_client::YellowstoneClient,
+};
+
+/// Upper bound of the delay between reconnects of a stream
+const MAX_BACKOFF_SECS: u64 = 60;
+
+/// Independent gRPC connections, loadable from a TOML file:
+///
+/// ```toml
+/// [[streams]]
+/// endpoint = "https://grpc-1.example.com:443"
+/// token = "token-1"
+/// programs = ["JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4"]
+///
+/// [[streams]]
+/// endpoint = "https://grpc-2.example.com:443"
+/// programs = ["675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8"]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MultiStreamConfig {
+    pub streams: Vec<StreamConfig>,
+}
+
+/// One gRPC connection streaming the transactions and accounts of `programs`
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StreamConfig {
+    pub endpoint: String,
+    #[serde(default)]
+    pub token: Option<String>,
+    pub programs: Vec<String>,
+}
+
+impl fmt::Debug for StreamConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamConfig")
+            .field("endpoint", &self.endpoint)
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .field("programs", &self.programs)
+            .finish()
+    }
+}
+
+impl MultiStreamConfig {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read streams file {}", path.display()))?;
+        let config: Self = toml::from_str(&contents)
+            .with_context(|| format!("Invalid streams file {}", path.display()))?;
+
+        if config.streams.is_empty() {
+            anyhow::bail!("{} doesn't list any streams", path.display());
+        }
+        for (index, stream) in config.streams.iter().enumerate() {
+            if stream.endpoint.is_empty() {
+                anyhow::bail!("Stream {} in {} has no endpoint", index, path.display());
+            }
+            if stream.programs.is_empty() {
+                anyhow::bail!(
+                    "Stream {} in {} doesn't list any programs",
+                    index,
+                    path.display()
+                );
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+struct Connection {
+    endpoint: String,
+    token: Option<String>,
+    subscriptions: SubscribeRequest,
+}
+
+/// Runs several Yellowstone connections side by side, all feeding the same `IndexEvent`
+/// channel. Each connection reconnects on its own: a dead or stalled stream is restarted
+/// with backoff while the others keep streaming.
+pub struct MultiStreamIngest {
+    connections: Vec<Connection>,
+}
+
+impl MultiStreamIngest {
+    /// One connection per entry of `config`, subscribed to the transactions and accounts
+    /// of its programs. Slots and block metadata are the same on every stream, so only
+    /// the first one subscribes to them. Commitment and vote / failed filtering use the
+    /// defaults and the `INDEXER_INCLUDE_*` env vars.
+    pub fn new(config: &MultiStreamConfig) -> Result<Self> {
+        let defaults = SubscriptionConfig::default().with_env_overrides()?;
+        let connections = config
+            .streams
+            .iter()
+            .enumerate()
+            .map(|(index, stream)| {
+                let mut subscriptions = Subscriptions::from_programs(&SubscriptionConfig {
+                    account_programs: stream.programs.clone(),
+                    transaction_programs: stream.programs.clone(),
+                    ..defaults.clone()
+                });
+                if index > 0 {
+                    subscriptions.slots.clear();
+                    subscriptions.blocks_meta.clear();
+                }
+
+                Connection {
+                    endpoint: stream.endpoint.clone(),
+                    token: stream.token.clone(),
+                    subscriptions,
+                }
+            })
+            .collect();
+
+        Ok(Self { connections })
+    }
+
+    /// A single connection with a prebuilt subscription
+    pub fn single(
+        endpoint: String,
+        token: Option<String>,
+        subscriptions: SubscribeRequest,
+    ) -> Self {
+        Self {
+            connections: vec![Connection {
+                endpoint,
+                token,
+                subscriptions,
+            }],
+        }
+    }
+
+    /// Stream until `shutdown` fires. Programs received on `program_rx` are added to the
+    /// first stream. Every stream resumes from the same checkpoint: the processor skips
+    /// replayed transactions still in its in-memory signature cache, which is bounded and
+    /// empty after a restart, and the rest are merged by signature when ClickHouse merges
+    /// parts (until then, queries without `FINAL` count them twice).
+    pub async fn run(
+        self,
+        checkpoint: CheckpointStore,
+        program_rx: Receiver<String>,
+        event_tx: Sender<IndexEvent>,
+        shutdown: watch::Receiver<bool>,
+    ) -> Result<()> {
+        let mut program_rx = Some(program_rx);
+        let mut tasks = JoinSet::new();
+
+        for (index, connection) in self.connections.into_iter().enumerate() {
+            // The other streams get a closed channel and never see added programs
+            let program_rx = program_rx.take().unwrap_or_else(|| mpsc::channel(1).1);
+            let span = info_span!("stream", index, endpoint = %connection.endpoint);
+
+            tasks.spawn(
+                run_with_reconnect(
+                    connection,
+                    checkpoint.clone(),
+                    program_rx,
+                    event_tx.clone(),
+                    shutdown.clone(),
+                )
+                .instrument(span),
+            );
+        }
+
+        // Only the streams hold senders, the processor sees the channel close once all stop
+        drop(event_tx);
+
+        while let Some(result) = tasks.join_next().await {
+            if let Err(e) = result {
+                error!("Stream task failed: {}", e);
+            }
+        }
+
+        info!("Stopped streaming");
+        Ok(())
+    }
+}
+
+async fn run_with_reconnect(
+    mut connection: Connection,
+    checkpoint: CheckpointStore,
+    mut program_rx: Receiver<String>,
+    event_tx: Sender<IndexEvent>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut backoff = 1u64;
+
+    loop {
+        let stream = YellowstoneClient::connect_and_run(
+            &connection.endpoint,
+            &connection.token,
+            &mut connection.subscriptions,
+            &checkpoint,
+            &mut program_rx,
+            &event_tx,
+        );
+
+        tokio::select! {
+            result = stream => {
+                match result {
+                    Ok(_) => {
+                        warn!("Stream ended normally, reconnecting…");
+                    }
+                    Err(e) => {
+                        error!("Stream error: {:?}", e);
+                    }
+                }
+            }
+            _ = shutdown.changed() => break,
+        }
+
+        info!("Reconnecting in {}s", backoff);
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(backoff)) => {}
+            _ = shutdown.changed() => break,
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF_SECS);
+    }
+}
@@ -1,24 +1,172 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, env, fs, path::Path};
 
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer};
 use yellowstone_grpc_proto::geyser::{
     CommitmentLevel, SubscribeRequest, SubscribeRequestFilterAccounts,
+    SubscribeRequestFilterBlocksMeta, SubscribeRequestFilterSlots,
     SubscribeRequestFilterTransactions,
 };
 
+const ACCOUNTS_FILTER: &str = "dexs_accounts";
+const TRANSACTIONS_FILTER: &str = "dexs_transactions";
+const SLOTS_FILTER: &str = "all_slots";
+const BLOCKS_META_FILTER: &str = "all_blocks_meta";
+
+/// Programs to stream, loadable from a TOML file:
+///
+/// ```toml
+/// account_programs = ["JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4"]
+/// transaction_programs = ["JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4"]
+/// commitment = "finalized"
+/// include_votes = false
+/// include_failed = true
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SubscriptionConfig {
+    /// Accounts whose updates are streamed
+    pub account_programs: Vec<String>,
+    /// Programs whose transactions are streamed
+    pub transaction_programs: Vec<String>,
+    /// `processed`, `confirmed` (default) or `finalized`. Processed data is the freshest
+    /// but may be rolled back on a fork, confirmed has been voted on by 2/3 of the stake
+    /// and finalized can't be rolled back but trails by about 32 slots.
+    #[serde(
+        default = "default_commitment",
+        deserialize_with = "deserialize_commitment"
+    )]
+    pub commitment: CommitmentLevel,
+    /// Also stream vote transactions, overridden by `INDEXER_INCLUDE_VOTES`
+    #[serde(default)]
+    pub include_votes: bool,
+    /// Also stream failed transactions, overridden by `INDEXER_INCLUDE_FAILED`
+    #[serde(default)]
+    pub include_failed: bool,
+}
+
+fn default_commitment() -> CommitmentLevel {
+    CommitmentLevel::Confirmed
+}
+
+fn deserialize_commitment<'de, D>(deserializer: D) -> Result<CommitmentLevel, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    match value.to_ascii_lowercase().as_str() {
+        "processed" => Ok(CommitmentLevel::Processed),
+        "confirmed" => Ok(CommitmentLevel::Confirmed),
+        "finalized" => Ok(CommitmentLevel::Finalized),
+        _ => Err(serde::de::Error::invalid_value(
+            serde::de::Unexpected::Str(&value),
+            &"processed, confirmed or finalized",
+        )),
+    }
+}
+
+impl Default for SubscriptionConfig {
+    fn default() -> Self {
+        Self {
+            account_programs: vec![
+                "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4".to_string(), // Jupiter v6
+                "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8".to_string(), // Raydium v5
+                "cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG".to_string(), // Meteora DAMM v2
+                "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc".to_string(), // Orca
+            ],
+            transaction_programs: vec![
+                "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc".to_string(), // Orca
+                "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8".to_string(), // Raydium v5
+                "cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG".to_string(), // Meteora DAMM v2
+                "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4".to_string(), // Jupiter v6
+            ],
+            commitment: default_commitment(),
+            include_votes: false,
+            include_failed: false,
+        }
+    }
+}
+
+impl SubscriptionConfig {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read subscriptions file {}", path.display()))?;
+        let config: Self = toml::from_str(&contents)
+            .with_context(|| format!("Invalid subscriptions file {}", path.display()))?;
+
+        if config.account_programs.is_empty() && config.transaction_programs.is_empty() {
+            anyhow::bail!("{} doesn't list any programs", path.display());
+        }
+
+        Ok(config)
+    }
+
+    /// Apply `INDEXER_INCLUDE_VOTES` and `INDEXER_INCLUDE_FAILED` when they are set
+    pub fn with_env_overrides(mut self) -> Result<Self> {
+        if let Some(include_votes) = env_flag("INDEXER_INCLUDE_VOTES")? {
+            self.include_votes = include_votes;
+        }
+        if let Some(include_failed) = env_flag("INDEXER_INCLUDE_FAILED")? {
+            self.include_failed = include_failed;
+        }
+
+        Ok(self)
+    }
+}
+
+fn env_flag(name: &str) -> Result<Option<bool>> {
+    match env::var(name) {
+        Ok(value) => match value.to_ascii_lowercase().as_str() {
+            "true" | "1" => Ok(Some(true)),
+            "false" | "0" => Ok(Some(false)),
+            _ => anyhow::bail!("{} must be true or false", name),
+        },
+        Err(_) => Ok(None),
+    }
+}
+
 pub struct Subscriptions;
 
 impl Subscriptions {
-    pub fn create_subscriptions() -> SubscribeRequest {
+    pub fn create_subscriptions() -> Result<SubscribeRequest> {
+        let config = SubscriptionConfig::default().with_env_overrides()?;
+        Ok(Self::from_programs(&config))
+    }
+
+    /// Build the subscription from a TOML file, see `SubscriptionConfig`
+    pub fn from_config(path: &Path) -> Result<SubscribeRequest> {
+        let config = SubscriptionConfig::from_file(path)?.with_env_overrides()?;
+        Ok(Self::from_programs(&config))
+    }
+
+    /// Add `program_id` to the account and transaction filters of `request`.
+    /// Returns false if both filters already contained it.
+    pub fn add_program(request: &mut SubscribeRequest, program_id: &str) -> bool {
+        let mut added = false;
+
+        if let Some(filter) = request.accounts.get_mut(ACCOUNTS_FILTER)
+            && !filter.account.iter().any(|id| id == program_id)
+        {
+            filter.account.push(program_id.to_string());
+            added = true;
+        }
+
+        if let Some(filter) = request.transactions.get_mut(TRANSACTIONS_FILTER)
+            && !filter.account_include.iter().any(|id| id == program_id)
+        {
+            filter.account_include.push(program_id.to_string());
+            added = true;
+        }
+
+        added
+    }
+
+    pub fn from_programs(config: &SubscriptionConfig) -> SubscribeRequest {
         let mut accounts = HashMap::new();
         accounts.insert(
-            "dexs_accounts".to_string(),
+            ACCOUNTS_FILTER.to_string(),
             SubscribeRequestFilterAccounts {
-                account: vec![
-                    "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4".to_string(), // Jupiter v6
-                    "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8".to_string(), // Raydium v5
-                    "cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG".to_string(), // Meteora DAMM v2
-                    "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc".to_string(), // Orca
-                ],
+                account: config.account_programs.clone(),
                 nonempty_txn_signature: None,
                 owner: vec![],
                 filters: vec![],
@@ -27,31 +175,44 @@ impl Subscriptions {
 
         let mut transactions = HashMap::new();
         transactions.insert(
-            "dexs_transactions".to_string(),
+            TRANSACTIONS_FILTER.to_string(),
             SubscribeRequestFilterTransactions {
-                account_include: vec![
-                    "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc".to_string(), // Orca
-                    "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8".to_string(), // Raydium v5
-                    "cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG".to_string(), // Meteora DAMM v2
-                    "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4".to_string(), // Jupiter v6
-                ],
+                account_include: config.transaction_programs.clone(),
                 account_exclude: vec![],
                 account_required: vec![],
-                vote: Some(false),
-                failed: Some(false),
+                // None streams both kinds, Some(false) only non-vote / successful ones
+                vote: (!config.include_votes).then_some(false),
+                failed: (!config.include_failed).then_some(false),
                 signature: None,
             },
         );
 
+        let mut slots = HashMap::new();
+        slots.insert(
+            SLOTS_FILTER.to_string(),
+            // One update per slot, at the subscription's commitment level
+            SubscribeRequestFilterSlots {
+                filter_by_commitment: Some(true),
+                ..Default::default()
+            },
+        );
+
+        // Block metadata only, full blocks would repeat every transaction
+        let mut blocks_meta = HashMap::new();
+        blocks_meta.insert(
+            BLOCKS_META_FILTER.to_string(),
+            SubscribeRequestFilterBlocksMeta::default(),
+        );
+
         SubscribeRequest {
             accounts,
             transactions,
-            slots: HashMap::new(),
+            slots,
             blocks: HashMap::new(),
-            blocks_meta: HashMap::new(),
+            blocks_meta,
             transactions_status: HashMap::new(),
             entry: HashMap::new(),
-            commitment: Some(CommitmentLevel::Confirmed as i32),
+            commitment: Some(config.commitment as i32),
             accounts_data_slice: vec![],
             ping: None,
             from_slot: None,
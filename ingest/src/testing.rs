@@ -0,0 +1,187 @@
+//! Builders for the proto messages the Yellowstone stream sends, to drive the
+//! ingest pipeline in tests without a gRPC endpoint. Enabled by the `test-utils` feature.
+
+use yellowstone_grpc_proto::prelude::{
+    CompiledInstruction, Message, SubscribeUpdate, SubscribeUpdateSlot, SubscribeUpdateTransaction,
+    SubscribeUpdateTransactionInfo, Transaction, TransactionError, TransactionStatusMeta,
+    subscribe_update::UpdateOneof,
+};
+
+/// Builds a transaction `SubscribeUpdate`. Accounts are given as base58 strings and
+/// instructions reference them by key, the builder takes care of the account indices.
+#[derive(Debug, Clone)]
+pub struct MockSubscribeUpdate {
+    signature: Vec<u8>,
+    slot: u64,
+    index: u64,
+    is_vote: bool,
+    fee: u64,
+    err: Option<Vec<u8>>,
+    account_keys: Vec<Vec<u8>>,
+    pre_balances: Vec<u64>,
+    post_balances: Vec<u64>,
+    instructions: Vec<CompiledInstruction>,
+    log_messages: Vec<String>,
+    compute_units_consumed: Option<u64>,
+}
+
+impl Default for MockSubscribeUpdate {
+    fn default() -> Self {
+        Self {
+            signature: vec![1; 64],
+            slot: 1,
+            index: 0,
+            is_vote: false,
+            fee: 5000,
+            err: None,
+            account_keys: Vec::new(),
+            pre_balances: Vec::new(),
+            post_balances: Vec::new(),
+            instructions: Vec::new(),
+            log_messages: Vec::new(),
+            compute_units_consumed: None,
+        }
+    }
+}
+
+impl MockSubscribeUpdate {
+    pub fn transaction() -> Self {
+        Self::default()
+    }
+
+    pub fn signature(mut self, signature: [u8; 64]) -> Self {
+        self.signature = signature.to_vec();
+        self
+    }
+
+    pub fn slot(mut self, slot: u64) -> Self {
+        self.slot = slot;
+        self
+    }
+
+    pub fn index(mut self, index: u64) -> Self {
+        self.index = index;
+        self
+    }
+
+    pub fn vote(mut self) -> Self {
+        self.is_vote = true;
+        self
+    }
+
+    pub fn fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Mark the transaction as failed with a bincode encoded `TransactionError`
+    pub fn failed(mut self, err: Vec<u8>) -> Self {
+        self.err = Some(err);
+        self
+    }
+
+    pub fn compute_units(mut self, compute_units_consumed: u64) -> Self {
+        self.compute_units_consumed = Some(compute_units_consumed);
+        self
+    }
+
+    pub fn log(mut self, message: &str) -> Self {
+        self.log_messages.push(message.to_string());
+        self
+    }
+
+    /// Add an account with its lamports before and after the transaction. The first
+    /// account added is the fee payer.
+    pub fn account(mut self, pubkey: &str, pre_balance: u64, post_balance: u64) -> Self {
+        let index = self.account_index(pubkey);
+        self.pre_balances[index] = pre_balance;
+        self.post_balances[index] = post_balance;
+        self
+    }
+
+    /// Add a top level instruction, accounts not added yet are appended with zero balances
+    pub fn instruction(mut self, program_id: &str, accounts: &[&str], data: &[u8]) -> Self {
+        let program_id_index = self.account_index(program_id) as u32;
+        let accounts = accounts
+            .iter()
+            .map(|account| self.account_index(account) as u8)
+            .collect();
+
+        self.instructions.push(CompiledInstruction {
+            program_id_index,
+            accounts,
+            data: data.to_vec(),
+        });
+        self
+    }
+
+    fn account_index(&mut self, pubkey: &str) -> usize {
+        let key = bs58::decode(pubkey)
+            .into_vec()
+            .expect("account keys must be base58");
+
+        match self
+            .account_keys
+            .iter()
+            .position(|existing| *existing == key)
+        {
+            Some(index) => index,
+            None => {
+                self.account_keys.push(key);
+                self.pre_balances.push(0);
+                self.post_balances.push(0);
+                self.account_keys.len() - 1
+            }
+        }
+    }
+
+    pub fn build_transaction(self) -> SubscribeUpdateTransaction {
+        let message = Message {
+            account_keys: self.account_keys,
+            instructions: self.instructions,
+            ..Default::default()
+        };
+
+        let meta = TransactionStatusMeta {
+            err: self.err.map(|err| TransactionError { err }),
+            fee: self.fee,
+            pre_balances: self.pre_balances,
+            post_balances: self.post_balances,
+            log_messages: self.log_messages,
+            compute_units_consumed: self.compute_units_consumed,
+            ..Default::default()
+        };
+
+        SubscribeUpdateTransaction {
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                signature: self.signature.clone(),
+                is_vote: self.is_vote,
+                transaction: Some(Transaction {
+                    signatures: vec![self.signature],
+                    message: Some(message),
+                }),
+                meta: Some(meta),
+                index: self.index,
+            }),
+            slot: self.slot,
+        }
+    }
+
+    pub fn build(self) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Transaction(self.build_transaction())),
+            ..Default::default()
+        }
+    }
+}
+
+/// A slot `SubscribeUpdate`
+pub fn mock_slot_update(slot: u64) -> SubscribeUpdate {
+    SubscribeUpdate {
+        update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+            slot,
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+}
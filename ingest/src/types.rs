@@ -1,5 +1,24 @@
+use anyhow::{Error, Result, anyhow};
+use base64::{Engine as _, engine::general_purpose};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use yellowstone_grpc_proto::{
+    convert_from,
+    geyser::{SubscribeUpdateAccount, SubscribeUpdateTransaction},
+    prelude::{TokenBalance, TransactionError},
+};
+
+/// Accounts of the Jito tip payment program, bundles transfer a tip to one of them
+pub const JITO_TIP_ACCOUNTS: [&str; 8] = [
+    "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+    "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+    "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
+    "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+    "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+    "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+    "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+    "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SolanaAccount {
@@ -12,6 +31,22 @@ pub struct SolanaAccount {
     pub write_version: u64,
     pub txn_signature: Option<String>, // base58 encoded if present
     pub timestamp: DateTime<Utc>,
+    pub slot: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolanaSlot {
+    pub slot: u64,
+    pub timestamp: DateTime<Utc>, // when the update was created, receive time if not sent
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolanaBlock {
+    pub slot: u64,
+    pub blockhash: String,
+    pub parent_slot: u64,
+    pub block_time: Option<i64>, // unix seconds
+    pub block_height: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,13 +56,29 @@ pub struct SolanaTransaction {
     pub is_vote: bool,
     pub index: u64,
     pub success: bool,
+    pub error_message: Option<String>, // JSON encoded TransactionError if failed
     pub fee: Option<u64>,
     pub pre_balances: Vec<u64>,
     pub post_balances: Vec<u64>,
     pub compute_units_consumed: Option<u64>,
     pub instructions: Vec<TransactionInstruction>,
+    pub inner_instructions: Vec<TransactionInstruction>,
     pub log_messages: Vec<String>,
-    pub account_keys: Vec<String>,
+    pub account_keys: Vec<String>, // static keys, then keys loaded from lookup tables
+    pub fee_payer: String,         // first account key, empty if the message is missing
+    pub token_balance_changes: Vec<TokenBalanceChange>,
+    pub is_jito_bundle: bool, // tips one of the `JITO_TIP_ACCOUNTS`
+    pub is_versioned: bool,   // v0 message, may load accounts from lookup tables
+}
+
+/// Change of an SPL token account balance, amounts are in UI units (adjusted for decimals)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenBalanceChange {
+    pub mint: String,
+    pub owner: String,
+    pub pre_amount: f64,
+    pub post_amount: f64,
+    pub delta: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,12 +86,272 @@ pub struct TransactionInstruction {
     pub program_id: String,
     pub accounts: Vec<String>,
     pub data: String,
+    pub depth: u8, // 0 for top level instructions, CPI depth otherwise
+    /// Top level instruction whose CPI chain this belongs to, `None` for top level ones
+    pub parent_instruction_index: Option<u16>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum IndexEvent {
     Transaction(SolanaTransaction),
     Account(SolanaAccount),
-    Slot(u64),
-    Block(u64),
+    Slot(SolanaSlot),
+    Block(SolanaBlock),
+}
+
+/// Fails if the update has no account. The timestamp is the receive time.
+impl TryFrom<SubscribeUpdateAccount> for SolanaAccount {
+    type Error = Error;
+
+    fn try_from(account_update: SubscribeUpdateAccount) -> Result<Self> {
+        let account_info = account_update.account.ok_or_else(|| {
+            anyhow!(
+                "Account update for slot {} has no account",
+                account_update.slot
+            )
+        })?;
+
+        Ok(SolanaAccount {
+            pubkey: bs58::encode(account_info.pubkey).into_string(),
+            lamports: account_info.lamports,
+            owner: bs58::encode(account_info.owner).into_string(),
+            executable: account_info.executable,
+            rent_epoch: account_info.rent_epoch,
+            data: general_purpose::STANDARD.encode(&account_info.data),
+            write_version: account_info.write_version,
+            txn_signature: account_info
+                .txn_signature
+                .map(|sig| bs58::encode(sig).into_string()),
+            timestamp: Utc::now(),
+            slot: account_update.slot,
+        })
+    }
+}
+
+/// Fails if the update has no transaction. A transaction without its message or status
+/// meta still converts, as a failed transaction with no accounts or instructions.
+impl TryFrom<SubscribeUpdateTransaction> for SolanaTransaction {
+    type Error = Error;
+
+    fn try_from(transaction_update: SubscribeUpdateTransaction) -> Result<Self> {
+        let transaction_info = transaction_update.transaction.ok_or_else(|| {
+            anyhow!(
+                "Transaction update for slot {} has no transaction",
+                transaction_update.slot
+            )
+        })?;
+
+        let signature = bs58::encode(transaction_info.signature).into_string();
+        let slot = transaction_update.slot;
+        let is_vote = transaction_info.is_vote;
+        let index = transaction_info.index;
+
+        let (
+            success,
+            error_message,
+            fee,
+            pre_balances,
+            post_balances,
+            compute_units_consumed,
+            log_messages,
+            instructions,
+            inner_instructions,
+            account_keys,
+            token_balance_changes,
+            is_versioned,
+        ) = if let (Some(transaction), Some(meta)) =
+            (transaction_info.transaction, transaction_info.meta)
+        {
+            let success = meta.err.is_none();
+            let error_message = meta.err.as_ref().map(error_to_json);
+            let fee = Some(meta.fee);
+            let pre_balances = meta.pre_balances;
+            let post_balances = meta.post_balances;
+            let compute_units_consumed = meta.compute_units_consumed;
+            let log_messages = meta.log_messages;
+            let token_balance_changes =
+                token_balance_changes(&meta.pre_token_balances, &meta.post_token_balances);
+
+            // v0 messages can load accounts from address lookup tables, they come
+            // after the static keys in the account index space: writable first, then
+            // readonly. Pre and post balances cover the same accounts in this order.
+            let account_keys: Vec<String> = match transaction.message.as_ref() {
+                Some(message) => message
+                    .account_keys
+                    .iter()
+                    .chain(meta.loaded_writable_addresses.iter())
+                    .chain(meta.loaded_readonly_addresses.iter())
+                    .map(|key| bs58::encode(key).into_string())
+                    .collect(),
+                None => Vec::new(),
+            };
+            let is_versioned = transaction
+                .message
+                .as_ref()
+                .is_some_and(|message| message.versioned);
+            let encode_key = |id: u32| account_keys.get(id as usize).cloned();
+
+            let mut instructions = Vec::new();
+
+            if let Some(message) = transaction.message.as_ref() {
+                for instruction in &message.instructions {
+                    instructions.push(TransactionInstruction {
+                        program_id: encode_key(instruction.program_id_index).unwrap_or_default(),
+                        accounts: instruction
+                            .accounts
+                            .iter()
+                            .filter_map(|&id| encode_key(id as u32))
+                            .collect(),
+                        data: general_purpose::STANDARD.encode(&instruction.data),
+                        depth: 0,
+                        parent_instruction_index: None,
+                    });
+                }
+            }
+
+            let mut inner_instructions = Vec::new();
+
+            if transaction.message.is_some() {
+                for inner in &meta.inner_instructions {
+                    for instruction in &inner.instructions {
+                        inner_instructions.push(TransactionInstruction {
+                            program_id: encode_key(instruction.program_id_index)
+                                .unwrap_or_default(),
+                            accounts: instruction
+                                .accounts
+                                .iter()
+                                .filter_map(|&id| encode_key(id as u32))
+                                .collect(),
+                            data: general_purpose::STANDARD.encode(&instruction.data),
+                            // stack height is 1 for top level instructions,
+                            // missing on old slots
+                            depth: instruction
+                                .stack_height
+                                .map(|height| height.saturating_sub(1) as u8)
+                                .unwrap_or(1),
+                            parent_instruction_index: Some(inner.index as u16),
+                        });
+                    }
+                }
+            }
+
+            (
+                success,
+                error_message,
+                fee,
+                pre_balances,
+                post_balances,
+                compute_units_consumed,
+                log_messages,
+                instructions,
+                inner_instructions,
+                account_keys,
+                token_balance_changes,
+                is_versioned,
+            )
+        } else {
+            (
+                false,
+                None,
+                None,
+                Vec::new(),
+                Vec::new(),
+                None,
+                Vec::new(),
+                Vec::new(),
+                Vec::new(),
+                Vec::new(),
+                Vec::new(),
+                false,
+            )
+        };
+
+        // The fee payer is always the first account of the message
+        let fee_payer = account_keys.first().cloned().unwrap_or_default();
+        let is_jito_bundle = account_keys
+            .iter()
+            .any(|key| JITO_TIP_ACCOUNTS.contains(&key.as_str()));
+
+        Ok(SolanaTransaction {
+            signature,
+            slot,
+            is_vote,
+            index,
+            success,
+            error_message,
+            fee,
+            pre_balances,
+            post_balances,
+            compute_units_consumed,
+            instructions,
+            inner_instructions,
+            log_messages,
+            account_keys,
+            fee_payer,
+            token_balance_changes,
+            is_jito_bundle,
+            is_versioned,
+        })
+    }
+}
+
+/// Pair up the pre and post balances of each token account and keep the ones that changed.
+/// An account missing on either side was created or closed in the transaction.
+fn token_balance_changes(pre: &[TokenBalance], post: &[TokenBalance]) -> Vec<TokenBalanceChange> {
+    let ui_amount = |balance: &TokenBalance| {
+        balance
+            .ui_token_amount
+            .as_ref()
+            .map_or(0.0, |amount| amount.ui_amount)
+    };
+
+    let mut changes: Vec<TokenBalanceChange> = post
+        .iter()
+        .map(|post_balance| {
+            let pre_amount = pre
+                .iter()
+                .find(|balance| balance.account_index == post_balance.account_index)
+                .map_or(0.0, ui_amount);
+            let post_amount = ui_amount(post_balance);
+
+            TokenBalanceChange {
+                mint: post_balance.mint.clone(),
+                owner: post_balance.owner.clone(),
+                pre_amount,
+                post_amount,
+                delta: post_amount - pre_amount,
+            }
+        })
+        .collect();
+
+    // Closed accounts only show up in the pre balances
+    changes.extend(
+        pre.iter()
+            .filter(|pre_balance| {
+                !post
+                    .iter()
+                    .any(|balance| balance.account_index == pre_balance.account_index)
+            })
+            .map(|pre_balance| TokenBalanceChange {
+                mint: pre_balance.mint.clone(),
+                owner: pre_balance.owner.clone(),
+                pre_amount: ui_amount(pre_balance),
+                post_amount: 0.0,
+                delta: -ui_amount(pre_balance),
+            }),
+    );
+
+    changes.retain(|change| change.delta != 0.0);
+    changes
+}
+
+/// Decode the bincode encoded transaction error and serialize it as JSON,
+/// e.g. `{"InstructionError":[2,{"Custom":6001}]}`.
+/// Falls back to the raw bytes in base64 if the error can't be decoded.
+fn error_to_json(err: &TransactionError) -> String {
+    match convert_from::create_tx_error(Some(err)) {
+        Ok(Some(tx_error)) => serde_json::to_string(&tx_error)
+            .unwrap_or_else(|_| general_purpose::STANDARD.encode(&err.err)),
+        _ => general_purpose::STANDARD.encode(&err.err),
+    }
 }
@@ -1,10 +1,14 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
 use anyhow::Result;
-use base64::{Engine as _, engine::general_purpose};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use futures::{Sink, SinkExt, Stream, StreamExt, channel::mpsc};
-use tokio::sync::mpsc::Sender;
-use tonic::Status;
-use tracing::{error, info, warn};
+use tokio::sync::mpsc::{Receiver, Sender};
+use tonic::{Code, Status};
+use tracing::{Span, debug, error, field, info, instrument, warn};
 use yellowstone_grpc_client::{
     ClientTlsConfig, GeyserGrpcBuilderError, GeyserGrpcClient, GeyserGrpcClientResult, Interceptor,
 };
@@ -14,12 +18,33 @@ use yellowstone_grpc_proto::geyser::{
 };
 
 use crate::{
+    checkpoint::CheckpointStore,
     subscriptions::Subscriptions,
-    types::{IndexEvent, SolanaAccount, SolanaTransaction, TransactionInstruction},
+    types::{IndexEvent, SolanaAccount, SolanaBlock, SolanaSlot, SolanaTransaction},
 };
 
+/// Channel usage (in percent) above which a backpressure warning is logged
+const CHANNEL_WARN_PERCENT: usize = 80;
+
+/// A stream without any update for this long is considered dead and reconnected. Slot
+/// updates arrive several times a second, and the server pings streams without slot
+/// updates every few seconds, so a healthy stream is never this quiet.
+const STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+static CHANNEL_NEARLY_FULL: AtomicBool = AtomicBool::new(false);
+
 pub struct YellowstoneClient {}
 
+/// How the server answered the first request of a stream, see `start_stream`
+pub enum StreamStart {
+    /// The request was accepted. Holds the first message, `None` if the stream closed
+    /// without one.
+    Accepted(Option<Result<SubscribeUpdate, Status>>),
+    /// The server can't replay from the requested `from_slot`, usually because the slot is
+    /// older than the updates it keeps
+    FromSlotRejected(Status),
+}
+
 impl YellowstoneClient {
     pub async fn new(
         endpoint: &str,
@@ -43,11 +68,23 @@ impl YellowstoneClient {
         client.subscribe().await
     }
 
+    /// Convert every update of `stream` into an `IndexEvent` and send it to the processor
+    /// on `event_tx`. Returns when the server closes the stream, and fails on a stream
+    /// error, a stall (see `STREAM_IDLE_TIMEOUT`) or once the processor is gone.
     pub async fn handle_grpc_stream(
         mut stream: impl Stream<Item = Result<SubscribeUpdate, Status>> + Unpin,
         event_tx: &Sender<IndexEvent>,
     ) -> Result<()> {
-        while let Some(message) = stream.next().await {
+        loop {
+            let message = match tokio::time::timeout(STREAM_IDLE_TIMEOUT, stream.next()).await {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(_) => anyhow::bail!(
+                    "No update received for {}s, treating the stream as dead",
+                    STREAM_IDLE_TIMEOUT.as_secs()
+                ),
+            };
+
             match message {
                 Ok(update) => {
                     Self::process_update(update, &event_tx).await?;
@@ -66,21 +103,121 @@ impl YellowstoneClient {
     pub async fn connect_and_run(
         endpoint: &str,
         token: &Option<String>,
+        subscriptions: &mut SubscribeRequest,
+        checkpoint: &CheckpointStore,
+        program_rx: &mut Receiver<String>,
         event_tx: &Sender<IndexEvent>,
     ) -> anyhow::Result<()> {
         let mut yellowstone_client = Self::new(endpoint, token).await?;
 
-        let (mut yellowstone_tx, yellowstone_rx) = Self::subscribe(&mut yellowstone_client).await?;
+        let mut initial_request = subscriptions.clone();
 
-        let subscriptions = Subscriptions::create_subscriptions();
+        // Resume from the last flushed slot. The processor skips replayed transactions it
+        // still remembers, after a restart the copies are only merged by ClickHouse.
+        initial_request.from_slot = checkpoint.load()?;
+        if let Some(slot) = initial_request.from_slot {
+            info!("Resuming stream from checkpointed slot {}", slot);
+        }
 
-        yellowstone_tx.send(subscriptions).await?;
+        let mut missed_from = None;
+        let (mut yellowstone_tx, yellowstone_rx, first) = loop {
+            let (mut tx, mut rx) = Self::subscribe(&mut yellowstone_client).await?;
+            match Self::start_stream(&mut tx, &mut rx, initial_request.clone()).await? {
+                StreamStart::Accepted(first) => break (tx, rx, first),
+                StreamStart::FromSlotRejected(status) => {
+                    // A rejected stream is closed, subscribe again from the current slot
+                    let from_slot = initial_request.from_slot.take().unwrap_or_default();
+                    warn!(
+                        "Can't resume from checkpointed slot {} ({}), streaming from the tip",
+                        from_slot,
+                        status.message()
+                    );
+                    missed_from = Some(from_slot);
+                }
+            }
+        };
+
+        if let Some(from) = missed_from {
+            let resumed_at = first.as_ref().and_then(|first| first.as_ref().ok());
+            match resumed_at.and_then(update_slot) {
+                Some(slot) => warn!("Slots {} to {} are missed", from, slot.saturating_sub(1)),
+                None => warn!("Slots from {} until the stream resumed are missed", from),
+            }
+        }
 
         info!("Connected!...");
         info!("Subscribed to Dexs. Starting data stream...");
 
-        Self::handle_grpc_stream(yellowstone_rx, &event_tx).await?;
+        // The first message was already read, put it back in front of the stream
+        let stream =
+            Self::handle_grpc_stream(futures::stream::iter(first).chain(yellowstone_rx), event_tx);
+        tokio::pin!(stream);
+
+        loop {
+            tokio::select! {
+                result = &mut stream => return result,
+                Some(program_id) = program_rx.recv() => {
+                    Self::add_program_subscription(&mut yellowstone_tx, subscriptions, &program_id)
+                        .await?;
+                }
+            }
+        }
+    }
+
+    /// Send the first `request` of a new stream and wait for the server's first message.
+    /// A `from_slot` the server can't replay from fails the stream right away with
+    /// `InvalidArgument` or `OutOfRange`, any other first message is left to the caller.
+    pub async fn start_stream(
+        tx: &mut (impl Sink<SubscribeRequest, Error = mpsc::SendError> + Unpin),
+        rx: &mut (impl Stream<Item = Result<SubscribeUpdate, Status>> + Unpin),
+        request: SubscribeRequest,
+    ) -> Result<StreamStart> {
+        let replaying = request.from_slot.is_some();
+        tx.send(request).await?;
+
+        let first = tokio::time::timeout(STREAM_IDLE_TIMEOUT, rx.next())
+            .await
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "No update received for {}s after subscribing",
+                    STREAM_IDLE_TIMEOUT.as_secs()
+                )
+            })?;
+
+        match first {
+            Some(Err(status))
+                if replaying
+                    && matches!(status.code(), Code::InvalidArgument | Code::OutOfRange) =>
+            {
+                Ok(StreamStart::FromSlotRejected(status))
+            }
+            first => Ok(StreamStart::Accepted(first)),
+        }
+    }
+
+    /// Start streaming `program_id` on an open stream, without reconnecting.
+    ///
+    /// A subscribe request replaces the previous filters of the stream, so the
+    /// program is added to `subscriptions` and the whole request is resent.
+    /// Adding a program that is already subscribed is a no-op and sends nothing.
+    pub async fn add_program_subscription(
+        tx: &mut (impl Sink<SubscribeRequest, Error = mpsc::SendError> + Unpin),
+        subscriptions: &mut SubscribeRequest,
+        program_id: &str,
+    ) -> Result<()> {
+        if !Subscriptions::add_program(subscriptions, program_id) {
+            info!("Program {} is already subscribed", program_id);
+            return Ok(());
+        }
+
+        // Resending from_slot would replay the stream
+        let request = SubscribeRequest {
+            from_slot: None,
+            ..subscriptions.clone()
+        };
+        tx.send(request).await?;
 
+        info!("Subscribed to program {}", program_id);
         Ok(())
     }
 
@@ -95,8 +232,31 @@ impl YellowstoneClient {
             Some(subscribe_update::UpdateOneof::Transaction(transaction_update)) => {
                 Self::handle_transaction_update(transaction_update, &event_tx).await?;
             }
+            Some(subscribe_update::UpdateOneof::BlockMeta(block_meta)) => {
+                let block = SolanaBlock {
+                    slot: block_meta.slot,
+                    blockhash: block_meta.blockhash,
+                    parent_slot: block_meta.parent_slot,
+                    block_time: block_meta.block_time.map(|time| time.timestamp),
+                    block_height: block_meta.block_height.map(|height| height.block_height),
+                };
+                Self::handle_block(block, &event_tx).await?;
+            }
+            Some(subscribe_update::UpdateOneof::Block(block)) => {
+                let block = SolanaBlock {
+                    slot: block.slot,
+                    blockhash: block.blockhash,
+                    parent_slot: block.parent_slot,
+                    block_time: block.block_time.map(|time| time.timestamp),
+                    block_height: block.block_height.map(|height| height.block_height),
+                };
+                Self::handle_block(block, &event_tx).await?;
+            }
             Some(subscribe_update::UpdateOneof::Slot(slot_update)) => {
-                Self::handle_slot_update(slot_update, &event_tx).await?;
+                let created_at = update
+                    .created_at
+                    .and_then(|ts| DateTime::from_timestamp(ts.seconds, ts.nanos as u32));
+                Self::handle_slot_update(slot_update, created_at, &event_tx).await?;
             }
             _ => {}
         }
@@ -107,176 +267,98 @@ impl YellowstoneClient {
         account_update: SubscribeUpdateAccount,
         event_tx: &Sender<IndexEvent>,
     ) -> Result<()> {
-        if let Some(account) = Self::into_solana_account(account_update) {
-            info!(
-                "Account: pubkey={}, lamports={}, owner={}, executable={}",
-                account.pubkey, account.lamports, account.owner, account.executable
-            );
+        let account = match SolanaAccount::try_from(account_update) {
+            Ok(account) => account,
+            Err(e) => {
+                debug!("Skipping account update: {}", e);
+                return Ok(());
+            }
+        };
+
+        debug!(
+            "Account: pubkey={}, lamports={}, owner={}, executable={}",
+            account.pubkey, account.lamports, account.owner, account.executable
+        );
+        Self::send_event(event_tx, IndexEvent::Account(account)).await?;
 
-            event_tx.send(IndexEvent::Account(account)).await?;
-        }
         Ok(())
     }
 
+    #[instrument(skip_all, fields(slot = transaction_update.slot, signature = field::Empty))]
     pub async fn handle_transaction_update(
         transaction_update: SubscribeUpdateTransaction,
         event_tx: &Sender<IndexEvent>,
     ) -> Result<()> {
-        if let Some(transaction) = Self::into_solana_transaction(transaction_update) {
-            info!(
-                "Transaction: signature={}, slot={}, success={}",
-                transaction.signature, transaction.slot, transaction.success
-            );
+        let transaction = match SolanaTransaction::try_from(transaction_update) {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                debug!("Skipping transaction update: {}", e);
+                return Ok(());
+            }
+        };
+
+        Span::current().record("signature", transaction.signature.as_str());
+        debug!(success = transaction.success, "Transaction received");
+        Self::send_event(event_tx, IndexEvent::Transaction(transaction)).await?;
 
-            event_tx.send(IndexEvent::Transaction(transaction)).await?;
-        }
         Ok(())
     }
 
     pub async fn handle_slot_update(
         slot_update: SubscribeUpdateSlot,
+        created_at: Option<DateTime<Utc>>,
         event_tx: &Sender<IndexEvent>,
     ) -> Result<()> {
-        info!("Slot: {:?}", slot_update.slot);
+        debug!("Slot: {:?}", slot_update.slot);
 
-        event_tx.send(IndexEvent::Slot(slot_update.slot)).await?;
+        let slot = SolanaSlot {
+            slot: slot_update.slot,
+            timestamp: created_at.unwrap_or_else(Utc::now),
+        };
+        Self::send_event(event_tx, IndexEvent::Slot(slot)).await?;
 
         Ok(())
     }
 
-    fn into_solana_account(account_data: SubscribeUpdateAccount) -> Option<SolanaAccount> {
-        if let Some(account_info) = account_data.account {
-            let pubkey = bs58::encode(account_info.pubkey).into_string();
-            let owner = bs58::encode(account_info.owner).into_string();
-            let data = general_purpose::STANDARD.encode(&account_info.data);
-            let tx_sig = account_info
-                .txn_signature
-                .map(|sig| bs58::encode(sig).into_string());
-
-            Some(SolanaAccount {
-                pubkey: pubkey,
-                lamports: account_info.lamports,
-                owner: owner,
-                executable: account_info.executable,
-                rent_epoch: account_info.rent_epoch,
-                data: data,
-                write_version: account_info.write_version,
-                txn_signature: tx_sig,
-                timestamp: Utc::now(),
-            })
-        } else {
-            None
-        }
-    }
+    pub async fn handle_block(block: SolanaBlock, event_tx: &Sender<IndexEvent>) -> Result<()> {
+        debug!("Block: slot={}, blockhash={}", block.slot, block.blockhash);
 
-    fn into_solana_transaction(
-        transaction_update: SubscribeUpdateTransaction,
-    ) -> Option<SolanaTransaction> {
-        if let Some(transaction_info) = transaction_update.transaction {
-            let signature = bs58::encode(transaction_info.signature).into_string();
-            let slot = transaction_update.slot;
-            let is_vote = transaction_info.is_vote;
-            let index = transaction_info.index;
-
-            let (
-                success,
-                fee,
-                pre_balances,
-                post_balances,
-                compute_units_consumed,
-                log_messages,
-                instructions,
-                account_keys,
-            ) = if let (Some(transaction), Some(meta)) =
-                (transaction_info.transaction, transaction_info.meta)
-            {
-                let success = meta.err.is_none();
-                let fee = Some(meta.fee);
-                let pre_balances = meta.pre_balances;
-                let post_balances = meta.post_balances;
-                let compute_units_consumed = meta.compute_units_consumed;
-                let log_messages = meta.log_messages;
-
-                let mut instructions = Vec::new();
-
-                if let Some(message) = transaction.message.as_ref() {
-                    for instruction in &message.instructions {
-                        let program_id_index = instruction.program_id_index as usize;
-                        let program_id = if program_id_index < message.account_keys.len() {
-                            bs58::encode(&message.account_keys[program_id_index]).into_string()
-                        } else {
-                            String::new()
-                        };
-
-                        let accounts: Vec<String> = instruction
-                            .accounts
-                            .iter()
-                            .filter_map(|&id| {
-                                message
-                                    .account_keys
-                                    .get(id as usize)
-                                    .map(|key| bs58::encode(key).into_string())
-                            })
-                            .collect();
-
-                        instructions.push(TransactionInstruction {
-                            program_id,
-                            accounts,
-                            data: general_purpose::STANDARD.encode(&instruction.data),
-                        });
-                    }
-                }
-
-                let account_keys: Vec<String> = if let Some(message) = transaction.message.as_ref()
-                {
-                    message
-                        .account_keys
-                        .iter()
-                        .map(|key| bs58::encode(key).into_string())
-                        .collect()
-                } else {
-                    Vec::new()
-                };
+        Self::send_event(event_tx, IndexEvent::Block(block)).await?;
 
-                (
-                    success,
-                    fee,
-                    pre_balances,
-                    post_balances,
-                    compute_units_consumed,
-                    log_messages,
-                    instructions,
-                    account_keys,
-                )
-            } else {
-                (
-                    false,
-                    None,
-                    Vec::new(),
-                    Vec::new(),
-                    None,
-                    Vec::new(),
-                    Vec::new(),
-                    Vec::new(),
-                )
-            };
+        Ok(())
+    }
 
-            Some(SolanaTransaction {
-                signature,
-                slot,
-                is_vote,
-                index,
-                success,
-                fee,
-                pre_balances,
-                post_balances,
-                compute_units_consumed,
-                instructions,
-                log_messages,
-                account_keys,
-            })
+    /// Send an event to the processor, waiting for room when the channel is full so
+    /// that a slow processor slows down the stream instead of dropping events
+    pub async fn send_event(event_tx: &Sender<IndexEvent>, event: IndexEvent) -> Result<()> {
+        let max_capacity = event_tx.max_capacity();
+        let used = max_capacity - event_tx.capacity();
+
+        // Only warn when crossing the threshold, not for every event while above it
+        if used * 100 >= max_capacity * CHANNEL_WARN_PERCENT {
+            if !CHANNEL_NEARLY_FULL.swap(true, Ordering::Relaxed) {
+                warn!(
+                    "Processor channel is {}/{} full, processor is falling behind",
+                    used, max_capacity
+                );
+            }
         } else {
-            None
+            CHANNEL_NEARLY_FULL.store(false, Ordering::Relaxed);
         }
+
+        event_tx.send(event).await?;
+        Ok(())
+    }
+}
+
+/// Slot an update belongs to, `None` for pings and other updates without one
+fn update_slot(update: &SubscribeUpdate) -> Option<u64> {
+    match update.update_oneof.as_ref()? {
+        subscribe_update::UpdateOneof::Account(account) => Some(account.slot),
+        subscribe_update::UpdateOneof::Slot(slot) => Some(slot.slot),
+        subscribe_update::UpdateOneof::Transaction(transaction) => Some(transaction.slot),
+        subscribe_update::UpdateOneof::Block(block) => Some(block.slot),
+        subscribe_update::UpdateOneof::BlockMeta(block_meta) => Some(block_meta.slot),
+        _ => None,
     }
 }
@@ -0,0 +1,115 @@
+use ingest::{
+    testing::MockSubscribeUpdate,
+    types::{SolanaAccount, SolanaTransaction},
+};
+use yellowstone_grpc_proto::prelude::{
+    SubscribeUpdateAccount, SubscribeUpdateAccountInfo, SubscribeUpdateTransaction,
+};
+
+const FEE_PAYER: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+const JITO_TIP_PROGRAM: &str = "T1pyyaTNZsKv2WcRAB8oVnk93mLJw2XzjtVYqCsaHqt";
+const JITO_TIP_ACCOUNT: &str = "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5";
+const SYSTEM_PROGRAM: &str = "11111111111111111111111111111111";
+const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+#[test]
+fn transaction_update_converts() {
+    let update = MockSubscribeUpdate::transaction()
+        .signature([3; 64])
+        .slot(280_000_000)
+        .fee(10_000)
+        .account(FEE_PAYER, 5_000_000, 4_990_000)
+        .build_transaction();
+
+    let tx = SolanaTransaction::try_from(update).unwrap();
+
+    assert_eq!(tx.signature, bs58::encode([3u8; 64]).into_string());
+    assert_eq!(tx.slot, 280_000_000);
+    assert_eq!(tx.fee, Some(10_000));
+    assert_eq!(tx.fee_payer, FEE_PAYER);
+    assert_eq!(tx.pre_balances, vec![5_000_000]);
+    assert_eq!(tx.post_balances, vec![4_990_000]);
+    assert!(tx.success);
+    assert!(!tx.is_jito_bundle);
+}
+
+#[test]
+fn transaction_tipping_jito_is_a_bundle() {
+    // A system transfer to a tip account
+    let update = MockSubscribeUpdate::transaction()
+        .account(FEE_PAYER, 5_000_000, 3_990_000)
+        .account(JITO_TIP_ACCOUNT, 100_000_000, 101_000_000)
+        .instruction(
+            SYSTEM_PROGRAM,
+            &[FEE_PAYER, JITO_TIP_ACCOUNT],
+            &[2, 0, 0, 0],
+        )
+        .build_transaction();
+
+    let tx = SolanaTransaction::try_from(update).unwrap();
+
+    assert!(tx.is_jito_bundle);
+}
+
+#[test]
+fn transaction_calling_the_tip_program_is_not_a_bundle() {
+    let update = MockSubscribeUpdate::transaction()
+        .account(FEE_PAYER, 5_000_000, 4_990_000)
+        .instruction(JITO_TIP_PROGRAM, &[FEE_PAYER], &[])
+        .build_transaction();
+
+    let tx = SolanaTransaction::try_from(update).unwrap();
+
+    assert!(!tx.is_jito_bundle);
+}
+
+#[test]
+fn transaction_update_without_transaction_fails() {
+    let update = SubscribeUpdateTransaction {
+        transaction: None,
+        slot: 42,
+    };
+
+    assert!(SolanaTransaction::try_from(update).is_err());
+}
+
+#[test]
+fn account_update_converts() {
+    let update = SubscribeUpdateAccount {
+        account: Some(SubscribeUpdateAccountInfo {
+            pubkey: bs58::decode(FEE_PAYER).into_vec().unwrap(),
+            lamports: 2_039_280,
+            owner: bs58::decode(TOKEN_PROGRAM).into_vec().unwrap(),
+            data: vec![1, 2, 3],
+            write_version: 7,
+            txn_signature: Some(vec![5; 64]),
+            ..Default::default()
+        }),
+        slot: 300,
+        ..Default::default()
+    };
+
+    let account = SolanaAccount::try_from(update).unwrap();
+
+    assert_eq!(account.pubkey, FEE_PAYER);
+    assert_eq!(account.owner, TOKEN_PROGRAM);
+    assert_eq!(account.lamports, 2_039_280);
+    assert_eq!(account.data, "AQID");
+    assert_eq!(account.write_version, 7);
+    assert_eq!(
+        account.txn_signature,
+        Some(bs58::encode([5u8; 64]).into_string())
+    );
+    assert_eq!(account.slot, 300);
+}
+
+#[test]
+fn account_update_without_account_fails() {
+    let update = SubscribeUpdateAccount {
+        account: None,
+        slot: 300,
+        ..Default::default()
+    };
+
+    assert!(SolanaAccount::try_from(update).is_err());
+}
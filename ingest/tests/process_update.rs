@@ -0,0 +1,79 @@
+use ingest::{
+    testing::{MockSubscribeUpdate, mock_slot_update},
+    types::IndexEvent,
+    yellowstone_client::YellowstoneClient,
+};
+use tokio::sync::mpsc;
+
+const FEE_PAYER: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+const POOL: &str = "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2";
+const JUPITER_V6: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+
+async fn process(update: yellowstone_grpc_proto::prelude::SubscribeUpdate) -> IndexEvent {
+    let (event_tx, mut event_rx) = mpsc::channel(1);
+    YellowstoneClient::process_update(update, &event_tx)
+        .await
+        .unwrap();
+
+    event_rx.try_recv().expect("an event should have been sent")
+}
+
+#[tokio::test]
+async fn transaction_update_becomes_solana_transaction() {
+    let update = MockSubscribeUpdate::transaction()
+        .signature([7; 64])
+        .slot(250_000_000)
+        .index(3)
+        .fee(5000)
+        .compute_units(42_000)
+        .account(FEE_PAYER, 1_000_000, 994_000)
+        .instruction(JUPITER_V6, &[FEE_PAYER, POOL], &[1, 2, 3])
+        .log("Program log: success")
+        .build();
+
+    let IndexEvent::Transaction(tx) = process(update).await else {
+        panic!("expected a transaction event");
+    };
+
+    assert_eq!(tx.signature, bs58::encode([7u8; 64]).into_string());
+    assert_eq!(tx.slot, 250_000_000);
+    assert_eq!(tx.index, 3);
+    assert!(tx.success);
+    assert_eq!(tx.error_message, None);
+    assert_eq!(tx.fee, Some(5000));
+    assert_eq!(tx.compute_units_consumed, Some(42_000));
+    assert_eq!(tx.account_keys, vec![FEE_PAYER, JUPITER_V6, POOL]);
+    assert_eq!(tx.fee_payer, FEE_PAYER);
+    assert_eq!(tx.pre_balances, vec![1_000_000, 0, 0]);
+    assert_eq!(tx.post_balances, vec![994_000, 0, 0]);
+    assert_eq!(tx.log_messages, vec!["Program log: success"]);
+
+    assert_eq!(tx.instructions.len(), 1);
+    assert_eq!(tx.instructions[0].program_id, JUPITER_V6);
+    assert_eq!(tx.instructions[0].accounts, vec![FEE_PAYER, POOL]);
+    assert_eq!(tx.instructions[0].data, "AQID");
+}
+
+#[tokio::test]
+async fn failed_transaction_has_error_message() {
+    let update = MockSubscribeUpdate::transaction()
+        .account(FEE_PAYER, 1_000_000, 995_000)
+        .failed(vec![0, 0, 0, 0])
+        .build();
+
+    let IndexEvent::Transaction(tx) = process(update).await else {
+        panic!("expected a transaction event");
+    };
+
+    assert!(!tx.success);
+    assert!(tx.error_message.is_some());
+}
+
+#[tokio::test]
+async fn slot_update_becomes_slot_event() {
+    let IndexEvent::Slot(slot) = process(mock_slot_update(123)).await else {
+        panic!("expected a slot event");
+    };
+
+    assert_eq!(slot.slot, 123);
+}
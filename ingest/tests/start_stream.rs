@@ -0,0 +1,67 @@
+//! Falling back to a fresh stream when the checkpointed slot can't be replayed
+
+use futures::{StreamExt, channel::mpsc, stream};
+use ingest::{
+    testing::mock_slot_update,
+    yellowstone_client::{StreamStart, YellowstoneClient},
+};
+use tonic::Status;
+use yellowstone_grpc_proto::geyser::SubscribeRequest;
+
+fn resume_from(slot: u64) -> SubscribeRequest {
+    SubscribeRequest {
+        from_slot: Some(slot),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn rejected_from_slot_is_reported() {
+    let (mut tx, mut sent) = mpsc::channel(1);
+    let mut rx = stream::iter([Err(Status::invalid_argument(
+        "broadcast from 100 is not available",
+    ))]);
+
+    let start = YellowstoneClient::start_stream(&mut tx, &mut rx, resume_from(100))
+        .await
+        .unwrap();
+
+    assert!(matches!(start, StreamStart::FromSlotRejected(_)));
+    assert_eq!(sent.next().await.unwrap().from_slot, Some(100));
+}
+
+#[tokio::test]
+async fn out_of_range_from_slot_is_reported() {
+    let (mut tx, _sent) = mpsc::channel(1);
+    let mut rx = stream::iter([Err(Status::out_of_range("slot 100 was pruned"))]);
+
+    let start = YellowstoneClient::start_stream(&mut tx, &mut rx, resume_from(100))
+        .await
+        .unwrap();
+
+    assert!(matches!(start, StreamStart::FromSlotRejected(_)));
+}
+
+#[tokio::test]
+async fn accepted_stream_keeps_its_first_update() {
+    let (mut tx, _sent) = mpsc::channel(1);
+    let mut rx = stream::iter([Ok(mock_slot_update(150))]);
+
+    let start = YellowstoneClient::start_stream(&mut tx, &mut rx, resume_from(100))
+        .await
+        .unwrap();
+
+    assert!(matches!(start, StreamStart::Accepted(Some(Ok(_)))));
+}
+
+#[tokio::test]
+async fn errors_without_from_slot_are_left_to_the_stream() {
+    let (mut tx, _sent) = mpsc::channel(1);
+    let mut rx = stream::iter([Err(Status::invalid_argument("invalid filter"))]);
+
+    let start = YellowstoneClient::start_stream(&mut tx, &mut rx, SubscribeRequest::default())
+        .await
+        .unwrap();
+
+    assert!(matches!(start, StreamStart::Accepted(Some(Err(_)))));
+}
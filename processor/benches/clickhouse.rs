@@ -0,0 +1,85 @@
+//! Insert throughput and query latency against a throwaway ClickHouse container.
+//!
+//! Run with `cargo bench -p processor`, Docker has to be available.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use processor::{
+    query::{QueryService, TimePeriod, TransactionFilters},
+    testing::{connect, start_clickhouse, swap_transactions, transactions},
+};
+use tokio::runtime::Runtime;
+
+const BATCH_SIZES: &[usize] = &[100, 500, 1000, 5000];
+/// Rows inserted before the query benchmarks run
+const QUERY_ROWS: usize = 100_000;
+/// Rows per insert of the compression benchmark
+const COMPRESSION_BATCH_SIZE: usize = 1000;
+
+fn benchmarks(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let (_container, url) = runtime.block_on(start_clickhouse());
+    let client = runtime.block_on(connect(&url, true));
+
+    let mut group = c.benchmark_group("batch_insert_transactions");
+    group.sample_size(10);
+    for &batch_size in BATCH_SIZES {
+        let batch = transactions(batch_size, 0);
+        group.throughput(Throughput::Elements(batch_size as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(batch_size),
+            &batch,
+            |b, batch| {
+                b.to_async(&runtime)
+                    .iter(|| async { client.batch_insert_transactions(batch).await.unwrap() });
+            },
+        );
+    }
+    group.finish();
+
+    runtime.block_on(async {
+        for chunk in transactions(QUERY_ROWS, 1_000_000).chunks(10_000) {
+            client.batch_insert_transactions(chunk).await.unwrap();
+        }
+    });
+    let qs = QueryService::new(runtime.block_on(connect(&url, true)));
+
+    let mut group = c.benchmark_group("count_transactions");
+    group.bench_function("no_filter", |b| {
+        b.to_async(&runtime).iter(|| async {
+            qs.count_transactions(TransactionFilters::default())
+                .await
+                .unwrap()
+        });
+    });
+    group.bench_function("last_24_hours", |b| {
+        b.to_async(&runtime).iter(|| async {
+            qs.count_transactions(TimePeriod::Last24Hours)
+                .await
+                .unwrap()
+        });
+    });
+    group.bench_function("jito_bundles_last_24_hours", |b| {
+        b.to_async(&runtime).iter(|| async {
+            qs.count_jito_bundles(TimePeriod::Last24Hours)
+                .await
+                .unwrap()
+        });
+    });
+    group.finish();
+
+    let batch = swap_transactions(COMPRESSION_BATCH_SIZE, 2_000_000);
+    let mut group = c.benchmark_group("insert_compression");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(COMPRESSION_BATCH_SIZE as u64));
+    for (name, compression) in [("none", false), ("lz4", true)] {
+        let client = runtime.block_on(connect(&url, compression));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &batch, |b, batch| {
+            b.to_async(&runtime)
+                .iter(|| async { client.batch_insert_transactions(batch).await.unwrap() });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, benchmarks);
+criterion_main!(benches);
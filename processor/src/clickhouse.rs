@@ -1,40 +1,321 @@
-use anyhow::{Ok, Result};
-use clickhouse::{Client, RowOwned, RowRead};
-use serde::Deserialize;
-use tracing::info;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Ok, Result, anyhow, bail};
+use chrono::Utc;
+use clickhouse::{Client, Compression, Row, RowOwned, RowRead, inserter::Inserter, query::Query};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    clickhouse_types::{
+        ClickHouseAccount, ClickHouseBlock, ClickHouseSlot, ClickHouseSwapEvent,
+        ClickHouseTokenMint, ClickHouseTransaction,
+    },
+    metrics,
+};
+
+/// ClickHouse error codes where a cheaper query is worth trying:
+/// TIMEOUT_EXCEEDED, TOO_SLOW, TOO_MANY_SIMULTANEOUS_QUERIES, MEMORY_LIMIT_EXCEEDED
+const RECOVERABLE_ERROR_CODES: &[u32] = &[159, 160, 202, 241];
+
+/// Tables with a `timestamp` column that a retention TTL applies to
+pub const RETENTION_TABLES: &[&str] =
+    &["transactions", "accounts", "slots", "blocks", "swap_events"];
+
+/// Parse a `CLICKHOUSE_COMPRESSION` value, true if LZ4 compression is enabled.
+/// `lz4hc` is accepted as an alias of `lz4`: the client only compresses with plain LZ4.
+pub fn parse_compression(value: &str) -> Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "none" => Ok(false),
+        "lz4" | "lz4hc" => Ok(true),
+        _ => bail!(
+            "CLICKHOUSE_COMPRESSION must be none, lz4 or lz4hc, got: '{}'",
+            value
+        ),
+    }
+}
+
+/// Builds a `ClickhouseClient`, see `ClickhouseClient::builder`
+pub struct ClickhouseClientBuilder {
+    url: String,
+    user: String,
+    password: String,
+    database: String,
+    compression: bool,
+    timeout: Option<Duration>,
+    max_query_retries: u32,
+}
+
+impl Default for ClickhouseClientBuilder {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            user: "default".to_string(),
+            password: String::new(),
+            database: String::new(),
+            compression: true,
+            timeout: None,
+            max_query_retries: 0,
+        }
+    }
+}
+
+impl ClickhouseClientBuilder {
+    /// Server HTTP endpoint, e.g. `http://localhost:8123`
+    pub fn url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    /// Defaults to `default`
+    pub fn user(mut self, user: &str) -> Self {
+        self.user = user.to_string();
+        self
+    }
+
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = password.to_string();
+        self
+    }
+
+    pub fn database(mut self, database: &str) -> Self {
+        self.database = database.to_string();
+        self
+    }
+
+    /// LZ4 compress inserts and query results, on by default
+    pub fn compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
 
-use crate::clickhouse_types::{ClickHouseAccount, ClickHouseSlot, ClickHouseTransaction};
+    /// Have the server cancel queries running longer than `timeout` (whole seconds)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Retry single row queries failing with a recoverable server error up to this
+    /// many times, see `ClickhouseClient::query_single`. Defaults to 0.
+    pub fn max_query_retries(mut self, max_query_retries: u32) -> Self {
+        self.max_query_retries = max_query_retries;
+        self
+    }
+
+    /// Connect, then create the tables and run pending migrations
+    pub async fn build(self) -> Result<ClickhouseClient> {
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            bail!(
+                "ClickHouse URL must start with http:// or https://, got: '{}'",
+                self.url
+            );
+        }
+        if self.user.is_empty() {
+            bail!("ClickHouse user must not be empty");
+        }
+        if self.database.is_empty() {
+            bail!("ClickHouse database must not be empty");
+        }
+
+        let mut client = Client::default()
+            .with_url(&self.url)
+            .with_database(&self.database)
+            .with_user(&self.user)
+            .with_password(&self.password)
+            .with_compression(if self.compression {
+                Compression::Lz4
+            } else {
+                Compression::None
+            });
+
+        if let Some(timeout) = self.timeout {
+            client = client.with_option("max_execution_time", timeout.as_secs().max(1).to_string());
+        }
+
+        let clickhouse_client = ClickhouseClient {
+            client,
+            max_query_retries: self.max_query_retries,
+        };
+
+        clickhouse_client
+            .ping()
+            .await
+            .with_context(|| format!("ClickHouse unreachable at {}", self.url))?;
+        clickhouse_client.init_tables().await?;
+        clickhouse_client.run_migrations().await?;
+        clickhouse_client.create_views().await?;
+        clickhouse_client.check_transactions_engine().await?;
+
+        Ok(clickhouse_client)
+    }
+}
 
 pub struct ClickhouseClient {
     pub client: Client,
+    max_query_retries: u32,
 }
 
 impl ClickhouseClient {
+    pub fn builder() -> ClickhouseClientBuilder {
+        ClickhouseClientBuilder::default()
+    }
+
+    /// Wrap `client` as is, without checking the connection or creating tables, e.g. a
+    /// client of a `clickhouse::test::Mock` server in tests
+    pub fn from_client(client: Client) -> Self {
+        Self {
+            client,
+            max_query_retries: 0,
+        }
+    }
+
     pub async fn new(
         clickhouse_url: &str,
         clickhouse_user: &str,
         clickhouse_password: &str,
         clickhouse_db: &str,
     ) -> Result<Self> {
-        let client = Client::default()
-            .with_url(clickhouse_url)
-            .with_database(clickhouse_db)
-            .with_user(clickhouse_user)
-            .with_password(clickhouse_password);
-
-        let clichouse_client = Self { client };
+        Self::builder()
+            .url(clickhouse_url)
+            .user(clickhouse_user)
+            .password(clickhouse_password)
+            .database(clickhouse_db)
+            .build()
+            .await
+    }
 
-        clichouse_client.init_tables().await?;
+    /// Run `SELECT 1` and return the round-trip time
+    pub async fn ping(&self) -> Result<Duration> {
+        let start_time = Instant::now();
+        self.client.query("SELECT 1").fetch_one::<u8>().await?;
 
-        Ok(clichouse_client)
+        Ok(start_time.elapsed())
     }
 
     async fn init_tables(&self) -> Result<()> {
-        // Transactions table
+        self.create_transactions_table("transactions").await?;
+
+        // Accounts table
+        self.client
+            .query(
+                r#"
+                CREATE TABLE IF NOT EXISTS accounts (
+                    pubkey String,
+                    lamports UInt64,
+                    owner String,
+                    executable UInt8,
+                    rent_epoch UInt64,
+                    data String,
+                    write_version UInt64,
+                    txn_signature Nullable(String),
+                    timestamp DateTime64(3),
+                    slot UInt64,
+                    decoded_data Nullable(String)
+                ) ENGINE = MergeTree()
+                ORDER BY (pubkey, write_version)
+                PARTITION BY toYYYYMM(toDateTime(timestamp))
+            "#,
+            )
+            .execute()
+            .await?;
+
+        // Slots table
+        self.client
+            .query(
+                r#"
+                CREATE TABLE IF NOT EXISTS slots (
+                    slot UInt64,
+                    timestamp DateTime64(3)
+                ) ENGINE = MergeTree()
+                ORDER BY slot
+            "#,
+            )
+            .execute()
+            .await?;
+
+        // Blocks table, metadata only
+        self.client
+            .query(
+                r#"
+                CREATE TABLE IF NOT EXISTS blocks (
+                    slot UInt64,
+                    blockhash String,
+                    parent_slot UInt64,
+                    block_time Nullable(Int64),
+                    block_height Nullable(UInt64),
+                    timestamp DateTime64(3)
+                ) ENGINE = ReplacingMergeTree()
+                ORDER BY slot
+            "#,
+            )
+            .execute()
+            .await?;
+
+        // Swap events table
+        self.client
+            .query(
+                r#"
+                CREATE TABLE IF NOT EXISTS swap_events (
+                    signature String,
+                    slot UInt64,
+                    timestamp DateTime64(3),
+                    dex LowCardinality(String),
+                    token_in String,
+                    token_out String,
+                    amount_in UInt64,
+                    amount_out UInt64,
+                    trader String,
+                    price_usd Nullable(Float64)
+                ) ENGINE = MergeTree()
+                PARTITION BY toYYYYMM(toDateTime(timestamp))
+                ORDER BY (slot, signature)
+            "#,
+            )
+            .execute()
+            .await?;
+
+        // Token mint metadata, seeded with well-known mints by a migration and extended
+        // with `upsert_token_mints`
+        self.client
+            .query(
+                r#"
+                CREATE TABLE IF NOT EXISTS token_mints (
+                    mint String,
+                    display_name Nullable(String),
+                    decimals UInt8
+                ) ENGINE = ReplacingMergeTree()
+                ORDER BY mint
+            "#,
+            )
+            .execute()
+            .await?;
+
+        // First invocation of each top level program, kept up to date by
+        // `program_first_seen_mv`. Each insert adds a row per program and merges keep
+        // the minimum of every column (a `ReplacingMergeTree` would keep the latest row).
         self.client
             .query(
                 r#"
-                CREATE TABLE IF NOT EXISTS transactions (
+                CREATE TABLE IF NOT EXISTS program_first_seen (
+                    program_id String,
+                    first_slot SimpleAggregateFunction(min, UInt64),
+                    first_timestamp SimpleAggregateFunction(min, DateTime64(3))
+                ) ENGINE = AggregatingMergeTree()
+                ORDER BY program_id
+            "#,
+            )
+            .execute()
+            .await?;
+
+        info!("ClickHouse tables initialized");
+        Ok(())
+    }
+
+    /// Create the transactions table under `name`, with the latest schema
+    async fn create_transactions_table(&self, name: &str) -> Result<()> {
+        let query = format!(
+            r#"
+                CREATE TABLE IF NOT EXISTS {} (
                 signature String,
                 slot UInt64,
                 is_vote UInt8,
@@ -47,53 +328,226 @@ impl ClickhouseClient {
                 post_balances String,
                 log_messages String,
                 account_keys String,
-                instructions String
+                instructions String,
+                dex LowCardinality(String),
+                error_message Nullable(String),
+                inner_instructions String,
+                priority_fee_microlamports Nullable(UInt64),
+                write_version UInt64,
+                program_ids Array(String),
+                token_balance_changes String DEFAULT '[]',
+                account_keys_array Array(String),
+                fee_payer String,
+                net_balance_change Int64,
+                is_jito_bundle UInt8,
+                parsed_logs String DEFAULT '[]',
+                balance_deltas Array(Int64),
+                is_versioned UInt8,
+                is_arbitrage UInt8,
+                max_cpi_depth UInt8,
+                instruction_discriminator String MATERIALIZED
+                    hex(substring(tryBase64Decode(tupleElement(arrayFirst(
+                        ix -> tupleElement(ix, 1) != 'ComputeBudget111111111111111111111111111111',
+                        JSONExtract(instructions, 'Array(Tuple(program_id String, data String))')
+                    ), 2)), 1, 8))
                 )
-                ENGINE = MergeTree()
+                ENGINE = ReplacingMergeTree(write_version)
                 PARTITION BY toYYYYMM(toDateTime(timestamp))
-                ORDER BY (slot, tx_index);
+                ORDER BY signature;
+            "#,
+            name
+        );
+        self.client.query(&query).execute().await?;
+
+        Ok(())
+    }
+
+    /// Create the materialized views over `transactions`. Runs after the migrations,
+    /// the views read columns that older tables only get from a migration.
+    async fn create_views(&self) -> Result<()> {
+        self.create_tps_materialized_view().await?;
+        self.create_program_first_seen_view().await?;
+
+        Ok(())
+    }
+
+    /// Warn if `transactions` predates the switch to `ReplacingMergeTree`, see
+    /// `rebuild_transactions_table`
+    async fn check_transactions_engine(&self) -> Result<()> {
+        let engine = self
+            .client
+            .query(
+                "SELECT engine FROM system.tables \
+                 WHERE database = currentDatabase() AND name = 'transactions'",
+            )
+            .fetch_one::<String>()
+            .await?;
+
+        if engine != "ReplacingMergeTree" {
+            warn!(
+                "transactions is a {} table, replayed transactions are never merged. \
+                 Stop the indexer and run `query rebuild-transactions`",
+                engine
+            );
+        }
+        Ok(())
+    }
+
+    /// Copy `transactions` into a new table with the latest schema and swap it in,
+    /// returning the number of rows copied. Converts tables created before transactions
+    /// were deduplicated by signature, the indexer must be stopped while it runs.
+    ///
+    /// The views over `transactions` stay attached to the table they were created on, so
+    /// they are dropped first and created again on the new table.
+    pub async fn rebuild_transactions_table(&self) -> Result<u64> {
+        for query in [
+            "DROP VIEW IF EXISTS tps_minutely_mv",
+            "DROP VIEW IF EXISTS program_first_seen_mv",
+            // Left over by an interrupted rebuild, either a partial copy or the old table
+            "DROP TABLE IF EXISTS transactions_v2",
+        ] {
+            self.client.query(query).execute().await?;
+        }
+        self.create_transactions_table("transactions_v2").await?;
+
+        // Columns missing on the old table, e.g. `write_version`, get their default
+        let columns = self
+            .client
+            .query(
+                r#"
+                SELECT name FROM system.columns
+                WHERE database = currentDatabase() AND table = 'transactions'
+                    AND default_kind NOT IN ('MATERIALIZED', 'ALIAS')
+                    AND name IN (
+                        SELECT name FROM system.columns
+                        WHERE database = currentDatabase() AND table = 'transactions_v2'
+                    )
+                ORDER BY position
             "#,
             )
+            .fetch_all::<String>()
+            .await?
+            .join(", ");
+
+        info!("Copying transactions into transactions_v2");
+        self.client
+            .query(&format!(
+                "INSERT INTO transactions_v2 ({0}) SELECT {0} FROM transactions",
+                columns
+            ))
             .execute()
             .await?;
+        let rows = self
+            .client
+            .query("SELECT count() FROM transactions_v2")
+            .fetch_one::<u64>()
+            .await?;
 
-        // Accounts table
+        self.client
+            .query("EXCHANGE TABLES transactions AND transactions_v2")
+            .execute()
+            .await?;
+        self.client
+            .query("DROP TABLE transactions_v2")
+            .execute()
+            .await?;
+        self.create_views().await?;
+
+        info!("Rebuilt transactions, {} rows", rows);
+        Ok(rows)
+    }
+
+    /// Delete rows of `table` once their `timestamp` is older than `days` days,
+    /// `days = 0` removes the TTL. ClickHouse drops expired rows during merges.
+    ///
+    /// Changing the TTL rewrites every part of the table, so nothing is done when the
+    /// table already has this retention.
+    pub async fn set_ttl(&self, table: &str, days: u32) -> Result<()> {
+        anyhow::ensure!(
+            RETENTION_TABLES.contains(&table),
+            "unknown table '{}', expected one of {}",
+            table,
+            RETENTION_TABLES.join(", ")
+        );
+
+        // The stored DDL has the TTL in its normalized form
+        let create_query = self
+            .client
+            .query(
+                "SELECT create_table_query FROM system.tables \
+                 WHERE database = currentDatabase() AND name = ?",
+            )
+            .bind(table)
+            .fetch_one::<String>()
+            .await?;
+        let unchanged = if days == 0 {
+            !create_query.contains(" TTL ")
+        } else {
+            create_query.contains(&format!(
+                "TTL toDateTime(timestamp) + toIntervalDay({})",
+                days
+            ))
+        };
+        if unchanged {
+            info!("Retention of {} is already {} days", table, days);
+            return Ok(());
+        }
+
+        let query = if days == 0 {
+            format!("ALTER TABLE {} REMOVE TTL", table)
+        } else {
+            format!(
+                "ALTER TABLE {} MODIFY TTL toDateTime(timestamp) + INTERVAL {} DAY",
+                table, days
+            )
+        };
+        self.client.query(&query).execute().await?;
+
+        info!("Set retention of {} to {} days", table, days);
+        Ok(())
+    }
+
+    /// Create `tps_minutely_mv`, which adds the per-minute transaction counts of every
+    /// insert to `tps_minutely`, so TPS queries read one row per minute instead of every
+    /// transaction. The table is created and backfilled by a migration.
+    pub async fn create_tps_materialized_view(&self) -> Result<()> {
         self.client
             .query(
                 r#"
-                CREATE TABLE IF NOT EXISTS accounts (
-                    pubkey String,
-                    lamports UInt64,
-                    owner String,
-                    executable UInt8,
-                    rent_epoch UInt64,
-                    data String,
-                    write_version UInt64,
-                    txn_signature Nullable(String),
-                    timestamp DateTime64(3)
-                ) ENGINE = MergeTree()
-                ORDER BY (pubkey, write_version)
-                PARTITION BY toYYYYMM(toDateTime(timestamp))
+                CREATE MATERIALIZED VIEW IF NOT EXISTS tps_minutely_mv
+                TO tps_minutely
+                AS SELECT
+                    toStartOfMinute(toDateTime(timestamp)) AS minute,
+                    count() AS tx_count
+                FROM transactions
+                GROUP BY minute
             "#,
             )
             .execute()
             .await?;
 
-        // Slots table
+        Ok(())
+    }
+
+    /// Create `program_first_seen_mv`, which records the first slot and time each top
+    /// level program was invoked in `program_first_seen` on insert
+    pub async fn create_program_first_seen_view(&self) -> Result<()> {
         self.client
             .query(
                 r#"
-                CREATE TABLE IF NOT EXISTS slots (
-                    slot UInt64,
-                    timestamp DateTime64(3)
-                ) ENGINE = MergeTree()
-                ORDER BY slot
+                CREATE MATERIALIZED VIEW IF NOT EXISTS program_first_seen_mv
+                TO program_first_seen
+                AS SELECT
+                    arrayJoin(program_ids) AS program_id,
+                    min(slot) AS first_slot,
+                    min(timestamp) AS first_timestamp
+                FROM transactions
+                GROUP BY program_id
             "#,
             )
             .execute()
             .await?;
 
-        info!("ClickHouse tables initialized");
         Ok(())
     }
 
@@ -109,6 +563,16 @@ impl ClickhouseClient {
         Ok(())
     }
 
+    /// Long lived inserter for the `transactions` table, rows are streamed to ClickHouse
+    /// in chunks as they are written and committed when the caller commits.
+    ///
+    /// Unlike `batch_insert_transactions` this does not set `write_version`, callers do.
+    pub fn create_transaction_inserter(&self) -> Result<Inserter<ClickHouseTransaction>> {
+        Ok(self
+            .client
+            .inserter::<ClickHouseTransaction>("transactions"))
+    }
+
     pub async fn batch_insert_transactions(&self, txs: &[ClickHouseTransaction]) -> Result<()> {
         if txs.is_empty() {
             return Ok(());
@@ -119,9 +583,16 @@ impl ClickhouseClient {
             .insert::<ClickHouseTransaction>("transactions")
             .await?;
 
+        // Replayed transactions are merged by signature, keeping the latest write
+        let write_version = Utc::now().timestamp_millis() as u64;
+
         // Write all items to the inserter
         for tx in txs {
-            inserter.write(tx).await?;
+            let row = ClickHouseTransaction {
+                write_version,
+                ..tx.clone()
+            };
+            inserter.write(&row).await?;
         }
 
         // Finalize the batch
@@ -180,6 +651,61 @@ impl ClickhouseClient {
         Ok(())
     }
 
+    pub async fn batch_insert_blocks(&self, blocks: &[ClickHouseBlock]) -> Result<()> {
+        if blocks.is_empty() {
+            return Ok(());
+        }
+
+        let mut inserter = self.client.insert::<ClickHouseBlock>("blocks").await?;
+
+        for block in blocks {
+            inserter.write(block).await?;
+        }
+
+        inserter.end().await?;
+
+        Ok(())
+    }
+
+    pub async fn batch_insert_swap_events(&self, swaps: &[ClickHouseSwapEvent]) -> Result<()> {
+        if swaps.is_empty() {
+            return Ok(());
+        }
+
+        let mut inserter = self
+            .client
+            .insert::<ClickHouseSwapEvent>("swap_events")
+            .await?;
+
+        for swap in swaps {
+            inserter.write(swap).await?;
+        }
+
+        inserter.end().await?;
+
+        Ok(())
+    }
+
+    /// Add or replace the metadata of `mints`, the latest row of a mint wins
+    pub async fn upsert_token_mints(&self, mints: &[ClickHouseTokenMint]) -> Result<()> {
+        if mints.is_empty() {
+            return Ok(());
+        }
+
+        let mut inserter = self
+            .client
+            .insert::<ClickHouseTokenMint>("token_mints")
+            .await?;
+
+        for mint in mints {
+            inserter.write(mint).await?;
+        }
+
+        inserter.end().await?;
+
+        Ok(())
+    }
+
     /// Execute a SELECT query and return results as typed JSON
     pub async fn query_all_typed<T>(&self, query: &str) -> Result<serde_json::Value>
     where
@@ -195,12 +721,176 @@ impl ClickhouseClient {
         Ok(chunks.join(""))
     }
 
-    /// Execute a query that returns a single value
+    /// Execute a query that returns a single value, retried up to `max_query_retries`
+    /// times if it fails with a recoverable server error
     pub async fn query_single<T>(&self, query: &str) -> Result<Option<T>>
     where
         T: RowOwned + for<'a> Deserialize<'a>,
     {
-        let mut cursor = self.client.query(query).fetch::<T>()?;
-        Ok(cursor.next().await?)
+        let mut attempt = 0;
+
+        loop {
+            let result = async {
+                let mut cursor = self.client.query(query).fetch::<T>()?;
+                cursor.next().await
+            }
+            .await;
+
+            match result {
+                Err(error) if attempt < self.max_query_retries && is_recoverable(&error) => {
+                    attempt += 1;
+                    warn!(
+                        "Query failed, retry {}/{}: {}",
+                        attempt, self.max_query_retries, error
+                    );
+                    tokio::time::sleep(Duration::from_millis(100 * attempt as u64)).await;
+                }
+                result => return Ok(result?),
+            }
+        }
+    }
+
+    /// `query_single` that gives up after `timeout`
+    pub async fn query_with_timeout<T>(&self, query: &str, timeout: Duration) -> Result<Option<T>>
+    where
+        T: RowOwned + for<'a> Deserialize<'a>,
+    {
+        tokio::time::timeout(timeout, self.query_single::<T>(query))
+            .await
+            .map_err(|_| anyhow!("Query timed out after {}ms", timeout.as_millis()))?
+    }
+
+    /// `query_with_timeout` for a query that already has its parameters bound
+    pub async fn fetch_optional_with_timeout<T>(
+        &self,
+        query: Query,
+        timeout: Duration,
+    ) -> Result<Option<T>>
+    where
+        T: RowOwned + RowRead,
+    {
+        let fetch = async move {
+            let mut cursor = query.fetch::<T>()?;
+            Ok(cursor.next().await?)
+        };
+
+        tokio::time::timeout(timeout, fetch)
+            .await
+            .map_err(|_| anyhow!("Query timed out after {}ms", timeout.as_millis()))?
     }
+
+    /// Fetch every row of a query that already has its parameters bound, giving up after
+    /// `timeout`
+    pub async fn fetch_all_with_timeout<T>(&self, query: Query, timeout: Duration) -> Result<Vec<T>>
+    where
+        T: RowOwned + RowRead,
+    {
+        tokio::time::timeout(timeout, query.fetch_all::<T>())
+            .await
+            .map_err(|_| anyhow!("Query timed out after {}ms", timeout.as_millis()))?
+            .map_err(Into::into)
+    }
+
+    /// Execute `primary_query`, retrying with `fallback_query` if the primary
+    /// times out or fails with a recoverable server error
+    pub async fn query_with_failover<T>(
+        &self,
+        primary_query: &str,
+        fallback_query: &str,
+    ) -> Result<Vec<T>>
+    where
+        T: RowOwned + RowRead,
+    {
+        let error = match self.client.query(primary_query).fetch_all::<T>().await {
+            Err(error) if is_recoverable(&error) => error,
+            result => return Ok(result?),
+        };
+
+        warn!("Primary query failed, running fallback query: {}", error);
+        metrics::FALLBACK_QUERIES_TOTAL.inc();
+
+        let timer = metrics::FALLBACK_QUERY_LATENCY_SECONDS.start_timer();
+        let rows = self.client.query(fallback_query).fetch_all::<T>().await?;
+        timer.observe_duration();
+
+        Ok(rows)
+    }
+
+    /// Row counts and disk usage of the main tables, from the active parts
+    pub async fn table_stats(&self) -> Result<Vec<TableStats>> {
+        let rows = self
+            .client
+            .query(
+                r#"
+                SELECT
+                    t.name AS name,
+                    sum(p.rows) AS row_count,
+                    sum(p.bytes_on_disk) AS disk_bytes,
+                    sum(p.data_compressed_bytes) AS compressed_bytes
+                FROM system.tables AS t
+                LEFT JOIN system.parts AS p
+                    ON p.database = t.database AND p.table = t.name AND p.active
+                WHERE t.database = currentDatabase()
+                    AND t.name IN ('transactions', 'accounts', 'slots')
+                GROUP BY t.name
+                ORDER BY t.name
+                "#,
+            )
+            .fetch_all::<TableStats>()
+            .await?;
+
+        Ok(rows)
+    }
+
+    /// Run an ad-hoc query and return its rows as JSON objects.
+    ///
+    /// The query runs with `readonly = 1`, so ClickHouse itself rejects anything that
+    /// writes data, changes the schema or changes settings.
+    pub async fn execute_raw_query(&self, sql: &str) -> Result<Vec<serde_json::Value>> {
+        let sql = sql.trim().trim_end_matches(';');
+
+        // Appends `FORMAT JSONEachRow`, one JSON object per line
+        let bytes = self
+            .client
+            .clone()
+            .with_option("readonly", "1")
+            .query(sql)
+            .fetch_bytes("JSONEachRow")?
+            .collect()
+            .await?;
+
+        bytes
+            .split(|&byte| byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).context("Invalid JSON row"))
+            .collect()
+    }
+}
+
+#[derive(Row, Debug, Serialize, Deserialize)]
+pub struct TableStats {
+    pub name: String,
+    pub row_count: u64,
+    /// Including primary index and marks
+    pub disk_bytes: u64,
+    pub compressed_bytes: u64,
+}
+
+fn is_recoverable(error: &clickhouse::error::Error) -> bool {
+    match error {
+        clickhouse::error::Error::TimedOut => true,
+        clickhouse::error::Error::BadResponse(message) => {
+            server_error_code(message).is_some_and(|code| RECOVERABLE_ERROR_CODES.contains(&code))
+        }
+        _ => false,
+    }
+}
+
+/// Extract the numeric code from a "Code: 159. DB::Exception: ..." message
+fn server_error_code(message: &str) -> Option<u32> {
+    let code = message.split("Code: ").nth(1)?;
+    code.split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()
 }
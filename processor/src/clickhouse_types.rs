@@ -8,15 +8,31 @@ pub struct ClickHouseTransaction {
     pub is_vote: bool,
     pub tx_index: u64,
     pub success: bool,
+    pub error_message: Option<String>,
     pub fee: Option<u64>,
     pub compute_units_consumed: Option<u64>,
     pub timestamp: i64,
     // JSON fields for complex data
-    pub pre_balances: String,  // JSON array
-    pub post_balances: String, // JSON array
-    pub log_messages: String,  // JSON array
-    pub account_keys: String,  // JSON array
-    pub instructions: String,  // JSON array
+    pub pre_balances: String,       // JSON array
+    pub post_balances: String,      // JSON array
+    pub log_messages: String,       // JSON array
+    pub account_keys: String,       // JSON array
+    pub instructions: String,       // JSON array
+    pub inner_instructions: String, // JSON array
+    pub dex: String,                // empty if not a DEX transaction
+    pub priority_fee_microlamports: Option<u64>,
+    pub write_version: u64, // set on insert, highest version wins on merge
+    pub program_ids: Vec<String>,
+    pub token_balance_changes: String,   // JSON array
+    pub account_keys_array: Vec<String>, // same keys as `account_keys`, for indexed lookups
+    pub fee_payer: String,
+    pub net_balance_change: i64, // lamports, post minus pre balance of the fee payer
+    pub is_jito_bundle: bool,
+    pub parsed_logs: String,      // JSON array of `ParsedLogEvent`
+    pub balance_deltas: Vec<i64>, // lamports, post minus pre balance of each account
+    pub is_versioned: bool,
+    pub is_arbitrage: bool, // see `Transformer::detect_arbitrage`
+    pub max_cpi_depth: u8,  // deepest inner instruction, 0 without CPIs
 }
 
 #[derive(Row, Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +46,8 @@ pub struct ClickHouseAccount {
     pub write_version: u64,
     pub txn_signature: Option<String>,
     pub timestamp: i64,
+    pub slot: u64,
+    pub decoded_data: Option<String>, // JSON, if the owner program has a decoder
 }
 
 #[derive(Row, Debug, Clone, Serialize, Deserialize)]
@@ -37,3 +55,35 @@ pub struct ClickHouseSlot {
     pub slot: u64,
     pub timestamp: i64,
 }
+
+#[derive(Row, Debug, Clone, Serialize, Deserialize)]
+pub struct ClickHouseBlock {
+    pub slot: u64,
+    pub blockhash: String,
+    pub parent_slot: u64,
+    pub block_time: Option<i64>, // unix seconds, as reported by the validator
+    pub block_height: Option<u64>,
+    pub timestamp: i64, // when the block was received
+}
+
+#[derive(Row, Debug, Clone, Serialize, Deserialize)]
+pub struct ClickHouseSwapEvent {
+    pub signature: String,
+    pub slot: u64,
+    pub timestamp: i64,
+    pub dex: String,
+    pub token_in: String,
+    pub token_out: String,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub trader: String,
+    pub price_usd: Option<f64>,
+}
+
+/// Name shown for a mint by the token queries, see `ClickhouseClient::upsert_token_mints`
+#[derive(Row, Debug, Clone, Serialize, Deserialize)]
+pub struct ClickHouseTokenMint {
+    pub mint: String,
+    pub display_name: Option<String>,
+    pub decimals: u8,
+}
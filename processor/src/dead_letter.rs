@@ -0,0 +1,168 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::clickhouse_types::ClickHouseTransaction;
+
+/// A batch of transactions that could not be inserted into ClickHouse
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeadLetterBatch {
+    pub timestamp: i64,
+    pub error: String,
+    pub transactions: Vec<ClickHouseTransaction>,
+    /// Number of replays that failed for these transactions
+    #[serde(default)]
+    pub retry_count: u32,
+}
+
+/// Append-only JSONL file of failed transaction batches, one batch per line.
+///
+/// The indexer appends while `query replay-dead-letter` reads and rewrites the file, both
+/// hold an exclusive lock on a `.lock` file next to it while they change the file.
+#[derive(Debug, Clone)]
+pub struct DeadLetterQueue {
+    path: PathBuf,
+}
+
+impl DeadLetterQueue {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn append(&self, transactions: &[ClickHouseTransaction], error: &str) -> Result<()> {
+        let batch = DeadLetterBatch {
+            timestamp: Utc::now().timestamp_millis(),
+            error: error.to_string(),
+            transactions: transactions.to_vec(),
+            retry_count: 0,
+        };
+
+        let _lock = self.lock()?;
+        append_batch(&self.path, &batch)
+    }
+
+    /// File that successfully replayed batches are moved to, e.g. `dead_letter.done.jsonl`
+    pub fn done_path(&self) -> PathBuf {
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.path.with_file_name(format!("{stem}.done.jsonl"))
+    }
+
+    /// Append a successfully replayed batch to the done file
+    pub fn mark_done(&self, batch: &DeadLetterBatch) -> Result<()> {
+        append_batch(&self.done_path(), batch)
+    }
+
+    /// Read all batches and the length of the file they were read from, see `rewrite`.
+    /// An empty list if the file does not exist.
+    pub fn read_all(&self) -> Result<(Vec<DeadLetterBatch>, u64)> {
+        let contents = {
+            let _lock = self.lock()?;
+            match fs::read_to_string(&self.path) {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to read {}", self.path.display()));
+                }
+            }
+        };
+
+        let mut batches = Vec::new();
+        for (line_number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let batch = serde_json::from_str(&line).with_context(|| {
+                format!(
+                    "Invalid batch on line {} of {}",
+                    line_number + 1,
+                    self.path.display()
+                )
+            })?;
+            batches.push(batch);
+        }
+
+        Ok((batches, contents.len() as u64))
+    }
+
+    /// Replace the first `read_len` bytes of the file, the batches returned by `read_all`,
+    /// with `batches`. Batches appended since are kept. The new contents are written to a
+    /// temporary file first.
+    pub fn rewrite(&self, batches: &[DeadLetterBatch], read_len: u64) -> Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        let _lock = self.lock()?;
+
+        let mut appended = Vec::new();
+        match fs::File::open(&self.path) {
+            Ok(mut file) => {
+                file.seek(SeekFrom::Start(read_len))?;
+                file.read_to_end(&mut appended)?;
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to open {}", self.path.display()));
+            }
+        }
+
+        let mut file = fs::File::create(&tmp_path)
+            .with_context(|| format!("Failed to create {}", tmp_path.display()))?;
+        for batch in batches {
+            let mut line = serde_json::to_string(batch)?;
+            line.push('\n');
+            file.write_all(line.as_bytes())?;
+        }
+        file.write_all(&appended)?;
+        file.sync_all()?;
+
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to replace {}", self.path.display()))?;
+
+        Ok(())
+    }
+
+    /// Exclusive lock on the `.lock` file next to the queue, released when dropped. The
+    /// queue file itself can't be locked, `rewrite` replaces it.
+    fn lock(&self) -> Result<fs::File> {
+        let lock_path = self.path.with_extension("lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+        file.lock()
+            .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+
+        Ok(file)
+    }
+}
+
+fn append_batch(path: &Path, batch: &DeadLetterBatch) -> Result<()> {
+    let mut line = serde_json::to_string(batch)?;
+    line.push('\n');
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(line.as_bytes())?;
+    file.sync_all()?;
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use serde_json::{Value, json};
+
+const SPL_TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+/// Size of an SPL Token account, mints (82 bytes) and multisigs (355 bytes) differ
+const SPL_TOKEN_ACCOUNT_LEN: usize = 165;
+
+/// Decodes the data of the accounts owned by one program into JSON
+pub trait AccountDecoder: Send + Sync {
+    /// `None` if `data` doesn't have a layout this decoder knows
+    fn decode(&self, data: &[u8]) -> Option<Value>;
+}
+
+/// Decoders by owner program id, see `decode_account`
+pub static ACCOUNT_DECODERS: LazyLock<HashMap<String, Box<dyn AccountDecoder>>> =
+    LazyLock::new(|| {
+        let mut decoders: HashMap<String, Box<dyn AccountDecoder>> = HashMap::new();
+        decoders.insert(
+            SPL_TOKEN_PROGRAM.to_string(),
+            Box::new(SplTokenAccountDecoder),
+        );
+        decoders
+    });
+
+/// Decode the raw `data` of an account owned by `owner`, if a decoder is registered for it
+pub fn decode_account(owner: &str, data: &[u8]) -> Option<Value> {
+    ACCOUNT_DECODERS.get(owner)?.decode(data)
+}
+
+/// SPL Token account: mint 32, owner 32, amount 8, delegate 36, state 1, is_native 12,
+/// delegated_amount 8, close_authority 36. Optional fields are a 4 byte tag followed by
+/// the value, which is zeroed when the tag is 0.
+pub struct SplTokenAccountDecoder;
+
+impl AccountDecoder for SplTokenAccountDecoder {
+    fn decode(&self, data: &[u8]) -> Option<Value> {
+        if data.len() != SPL_TOKEN_ACCOUNT_LEN {
+            return None;
+        }
+
+        let state = match data[108] {
+            0 => "uninitialized",
+            1 => "initialized",
+            2 => "frozen",
+            _ => return None,
+        };
+
+        Some(json!({
+            "mint": pubkey(&data[0..32]),
+            "owner": pubkey(&data[32..64]),
+            "amount": u64_le(&data[64..72]),
+            "delegate": optional(&data[72..108]).map(pubkey),
+            "state": state,
+            // Rent exempt reserve of wrapped SOL accounts
+            "is_native": optional(&data[109..121]).map(u64_le),
+            "delegated_amount": u64_le(&data[121..129]),
+            "close_authority": optional(&data[129..165]).map(pubkey),
+        }))
+    }
+}
+
+fn pubkey(bytes: &[u8]) -> String {
+    bs58::encode(bytes).into_string()
+}
+
+fn u64_le(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes.try_into().expect("8 bytes"))
+}
+
+/// Value of a `COption`, a little-endian u32 tag (1 if set) followed by the value
+fn optional(bytes: &[u8]) -> Option<&[u8]> {
+    let (tag, value) = bytes.split_at(4);
+    (tag == [1, 0, 0, 0]).then_some(value)
+}
@@ -0,0 +1,43 @@
+use std::collections::{HashSet, VecDeque};
+
+/// Bounded set of recently seen transaction signatures.
+///
+/// Once full, the oldest signatures are evicted first so replays from a
+/// recent `from_slot` are still caught while memory stays capped.
+pub struct SignatureCache {
+    capacity: usize,
+    seen: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl SignatureCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seen: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn contains(&self, signature: &str) -> bool {
+        self.seen.contains(signature)
+    }
+
+    /// Record `signature`, evicting the oldest signatures beyond the capacity.
+    /// Returns `false` if it has already been seen.
+    pub fn insert(&mut self, signature: &str) -> bool {
+        if self.seen.contains(signature) {
+            return false;
+        }
+
+        self.seen.insert(signature.to_string());
+        self.order.push_back(signature.to_string());
+
+        while self.order.len() > self.capacity {
+            if let Some(signature) = self.order.pop_front() {
+                self.seen.remove(&signature);
+            }
+        }
+        true
+    }
+}
@@ -1,6 +1,14 @@
 pub mod clickhouse;
 pub mod clickhouse_types;
+pub mod dead_letter;
+pub mod decoders;
+pub mod dedup;
+pub mod metrics;
+pub mod migrations;
 pub mod query;
+pub mod swaps;
+#[cfg(feature = "test-utils")]
+pub mod testing;
 pub mod transformer;
 pub mod worker;
 
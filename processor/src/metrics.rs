@@ -0,0 +1,125 @@
+use std::sync::LazyLock;
+
+use anyhow::Result;
+use prometheus::{
+    Encoder, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, TextEncoder,
+    register_histogram, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge_vec,
+};
+
+/// Content type of the Prometheus text exposition format
+pub const CONTENT_TYPE: &str = prometheus::TEXT_FORMAT;
+
+/// Number of queries that were answered by their fallback query
+pub static FALLBACK_QUERIES_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "fallback_queries_total",
+        "Number of queries answered by their fallback query"
+    )
+    .expect("Failed to register fallback_queries_total")
+});
+
+/// Latency of fallback queries
+pub static FALLBACK_QUERY_LATENCY_SECONDS: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "fallback_query_latency_seconds",
+        "Latency of fallback queries in seconds"
+    )
+    .expect("Failed to register fallback_query_latency_seconds")
+});
+
+/// Number of transactions skipped because their signature was already buffered
+pub static DUPLICATE_TX_SKIPPED: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "duplicate_tx_skipped_total",
+        "Number of duplicate transactions skipped before buffering"
+    )
+    .expect("Failed to register duplicate_tx_skipped_total")
+});
+
+pub static TRANSACTIONS_RECEIVED_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "transactions_received_total",
+        "Number of transaction events received by the processor"
+    )
+    .expect("Failed to register transactions_received_total")
+});
+
+pub static ACCOUNTS_RECEIVED_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "accounts_received_total",
+        "Number of account events received by the processor"
+    )
+    .expect("Failed to register accounts_received_total")
+});
+
+pub static BLOCKS_RECEIVED_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "blocks_received_total",
+        "Number of block events received by the processor"
+    )
+    .expect("Failed to register blocks_received_total")
+});
+
+pub static SLOTS_RECEIVED_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "slots_received_total",
+        "Number of slot events received by the processor"
+    )
+    .expect("Failed to register slots_received_total")
+});
+
+/// Transactions dropped by `Transformer::validate_transaction`
+pub static MALFORMED_TRANSACTIONS_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "malformed_transactions_total",
+        "Number of structurally invalid transactions dropped before buffering"
+    )
+    .expect("Failed to register malformed_transactions_total")
+});
+
+/// Transactions kept despite a fee above `Transformer::exceeds_fee_bound`
+pub static HIGH_FEE_TRANSACTIONS_TOTAL: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "high_fee_transactions_total",
+        "Number of transactions with an unusually high fee"
+    )
+    .expect("Failed to register high_fee_transactions_total")
+});
+
+/// Time spent inserting a buffer into ClickHouse, labelled by table
+pub static FLUSH_DURATION_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "flush_duration_seconds",
+        "Duration of buffer flushes to ClickHouse in seconds",
+        &["table"]
+    )
+    .expect("Failed to register flush_duration_seconds")
+});
+
+/// Failed flushes, labelled by table
+pub static FLUSH_ERRORS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "flush_errors_total",
+        "Number of failed buffer flushes to ClickHouse",
+        &["table"]
+    )
+    .expect("Failed to register flush_errors_total")
+});
+
+/// Rows currently waiting in each buffer
+pub static BUFFER_SIZE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "buffer_size",
+        "Number of rows waiting in each processor buffer",
+        &["buffer"]
+    )
+    .expect("Failed to register buffer_size")
+});
+
+/// Render all registered metrics in the Prometheus text format
+pub fn gather() -> Result<String> {
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer)?)
+}
@@ -0,0 +1,233 @@
+use anyhow::Result;
+use clickhouse::Row;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::ClickhouseClient;
+
+/// Schema changes to tables created by `init_tables`, sorted by version.
+///
+/// `init_tables` always creates the latest schema, so every migration must be
+/// a no-op on a fresh table (e.g. `ADD COLUMN IF NOT EXISTS`). Never edit or
+/// reorder an applied migration, append a new version instead.
+pub const MIGRATIONS: &[(i32, &str)] = &[
+    (
+        1,
+        "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS dex LowCardinality(String) DEFAULT ''",
+    ),
+    (
+        2,
+        "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS error_message Nullable(String)",
+    ),
+    (
+        3,
+        "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS inner_instructions String DEFAULT '[]'",
+    ),
+    (
+        4,
+        "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS priority_fee_microlamports Nullable(UInt64)",
+    ),
+    (
+        5,
+        "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS program_ids Array(String)",
+    ),
+    (
+        6,
+        "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS token_balance_changes String DEFAULT '[]'",
+    ),
+    // The default fills the column for rows inserted before it existed
+    (
+        7,
+        r#"
+        ALTER TABLE transactions ADD COLUMN IF NOT EXISTS account_keys_array Array(String)
+            DEFAULT JSONExtract(account_keys, 'Array(String)')
+        "#,
+    ),
+    (
+        8,
+        r#"
+        ALTER TABLE transactions ADD COLUMN IF NOT EXISTS fee_payer String
+            DEFAULT JSONExtractString(account_keys, 1)
+        "#,
+    ),
+    (
+        9,
+        r#"
+        ALTER TABLE transactions ADD COLUMN IF NOT EXISTS net_balance_change Int64
+            DEFAULT toInt64(JSONExtractUInt(post_balances, 1))
+                - toInt64(JSONExtractUInt(pre_balances, 1))
+        "#,
+    ),
+    // Same tip accounts as `ingest::types::JITO_TIP_ACCOUNTS`
+    (
+        10,
+        r#"
+        ALTER TABLE transactions ADD COLUMN IF NOT EXISTS is_jito_bundle UInt8
+            DEFAULT hasAny(account_keys_array, [
+                '96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5',
+                'HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe',
+                'Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY',
+                'ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49',
+                'DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh',
+                'ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt',
+                'DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL',
+                '3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT'
+            ])
+        "#,
+    ),
+    (
+        11,
+        "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS parsed_logs String DEFAULT '[]'",
+    ),
+    // Hex of the first 8 data bytes (the Anchor discriminator) of the first instruction
+    // that isn't a compute budget instruction
+    (
+        12,
+        r#"
+        ALTER TABLE transactions ADD COLUMN IF NOT EXISTS instruction_discriminator String
+            MATERIALIZED hex(substring(tryBase64Decode(tupleElement(arrayFirst(
+                ix -> tupleElement(ix, 1) != 'ComputeBudget111111111111111111111111111111',
+                JSONExtract(instructions, 'Array(Tuple(program_id String, data String))')
+            ), 2)), 1, 8))
+        "#,
+    ),
+    // Accounts written before this have slot 0
+    (
+        13,
+        "ALTER TABLE accounts ADD COLUMN IF NOT EXISTS slot UInt64",
+    ),
+    (
+        14,
+        r#"
+        ALTER TABLE transactions ADD COLUMN IF NOT EXISTS balance_deltas Array(Int64)
+            DEFAULT arrayMap(
+                (post, pre) -> toInt64(post) - toInt64(pre),
+                JSONExtract(post_balances, 'Array(UInt64)'),
+                JSONExtract(pre_balances, 'Array(UInt64)')
+            )
+        "#,
+    ),
+    // Transactions indexed before this count as legacy, and their account keys
+    // miss the accounts loaded from lookup tables
+    (
+        15,
+        "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS is_versioned UInt8",
+    ),
+    // Not backfilled, transactions indexed before this are never flagged
+    (
+        16,
+        "ALTER TABLE transactions ADD COLUMN IF NOT EXISTS is_arbitrage UInt8",
+    ),
+    // Accounts written before this are not decoded
+    (
+        17,
+        "ALTER TABLE accounts ADD COLUMN IF NOT EXISTS decoded_data Nullable(String)",
+    ),
+    // The view only sees new inserts, backfill the programs indexed before it existed
+    (18, PROGRAM_FIRST_SEEN_BACKFILL),
+    (
+        19,
+        r#"
+        ALTER TABLE transactions ADD COLUMN IF NOT EXISTS max_cpi_depth UInt8
+            DEFAULT arrayMax(arrayMap(
+                ix -> tupleElement(ix, 1),
+                JSONExtract(inner_instructions, 'Array(Tuple(depth UInt8))')
+            ))
+        "#,
+    ),
+    // Fed by `tps_minutely_mv`, which is created after the migrations and only sees new
+    // inserts, so the existing transactions are counted first
+    (
+        20,
+        r#"
+        CREATE TABLE IF NOT EXISTS tps_minutely (
+            minute DateTime,
+            tx_count UInt64
+        ) ENGINE = SummingMergeTree()
+        ORDER BY minute
+        "#,
+    ),
+    (
+        21,
+        r#"
+        INSERT INTO tps_minutely
+        SELECT toStartOfMinute(toDateTime(timestamp)) AS minute, count() AS tx_count
+        FROM transactions FINAL
+        GROUP BY minute
+        "#,
+    ),
+    // Nothing wrote `token_mints` before, name the mints most swaps go through
+    (
+        22,
+        r#"
+        INSERT INTO token_mints (mint, display_name, decimals) VALUES
+            ('So11111111111111111111111111111111111111112', 'SOL', 9),
+            ('EPjFWdd5AufqSSqeM2qNsxDiBAZmYFEvNCHqrDtkMEaC', 'USDC', 6),
+            ('Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB', 'USDT', 6),
+            ('JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN', 'JUP', 6),
+            ('DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263', 'Bonk', 5),
+            ('mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So', 'mSOL', 9),
+            ('J1toso1uCk3RLmjorhTtrVwY9HJ7X8V9yYac6Y7kGCPn', 'JitoSOL', 9),
+            ('4k3Dyjzvzp8eMZWUXbBCjEvwSkkk59S5iCNLY3QrkX6R', 'RAY', 6),
+            ('EKpQGSJtjMFqKZ9KQanSqYXRcF8fBopzLHYxdM65zcjm', 'WIF', 6),
+            ('HZ1JovNiVvGrGNiiYvEozEVgZ58xaU3RKwX8eACQBCt3', 'PYTH', 6)
+        "#,
+    ),
+];
+
+/// Merges keep the minimum of each program, so running it again is harmless
+const PROGRAM_FIRST_SEEN_BACKFILL: &str = r#"
+    INSERT INTO program_first_seen
+    SELECT arrayJoin(program_ids) AS program_id, min(slot), min(timestamp)
+    FROM transactions
+    GROUP BY program_id
+"#;
+
+impl ClickhouseClient {
+    /// Apply the migrations that are not yet recorded in `schema_versions`, in order
+    pub async fn run_migrations(&self) -> Result<()> {
+        self.client
+            .query(
+                r#"
+                CREATE TABLE IF NOT EXISTS schema_versions (
+                    version Int32,
+                    applied_at DateTime
+                ) ENGINE = MergeTree()
+                ORDER BY version
+            "#,
+            )
+            .execute()
+            .await?;
+
+        #[derive(Row, Deserialize)]
+        struct AppliedVersion {
+            version: i32,
+        }
+
+        let applied: Vec<i32> = self
+            .client
+            .query("SELECT version FROM schema_versions")
+            .fetch_all::<AppliedVersion>()
+            .await?
+            .into_iter()
+            .map(|row| row.version)
+            .collect();
+
+        for (version, sql) in MIGRATIONS {
+            if applied.contains(version) {
+                continue;
+            }
+
+            info!("Applying schema migration {}", version);
+            self.client.query(sql).execute().await?;
+
+            self.client
+                .query("INSERT INTO schema_versions (version, applied_at) VALUES (?, now())")
+                .bind(version)
+                .execute()
+                .await?;
+        }
+
+        Ok(())
+    }
+}
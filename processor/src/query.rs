@@ -1,28 +1,110 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, str::FromStr};
 
-use anyhow::Result;
-use chrono::{DateTime, Utc};
-use clickhouse::Row;
+use anyhow::{Context, Result, bail};
+use base64::{Engine as _, engine::general_purpose};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
+use clickhouse::{Row, query::Query};
 use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWrite;
 
-use crate::ClickhouseClient;
+use crate::{ClickhouseClient, clickhouse_types::ClickHouseTransaction};
+
+/// `DateTime64(3)` literal so comparisons against the timestamp columns are type-correct
+fn datetime_to_sql(datetime: &DateTime<Utc>) -> String {
+    format!(
+        "toDateTime64('{}', 3, 'UTC')",
+        datetime.format("%Y-%m-%d %H:%M:%S%.3f")
+    )
+}
+
+/// Lamports moved by the fee payer (first account) in a transaction,
+/// i.e. the absolute difference between its pre and post balance
+const VOLUME_SQL: &str = "toUInt64(abs(net_balance_change))";
+
+/// Numeraire of `QueryService::get_trader_pnl_estimates`, 6 decimals
+const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qNsxDiBAZmYFEvNCHqrDtkMEaC";
+
+/// A `WHERE` condition with `?` placeholders for user supplied values, which are
+/// bound in order by `bind` so they never end up in the SQL text
+struct WhereClause {
+    sql: String,
+    binds: Vec<String>,
+}
+
+impl WhereClause {
+    fn bind(&self, mut query: Query) -> Query {
+        for value in &self.binds {
+            query = query.bind(value.as_str());
+        }
+        query
+    }
+}
+
+/// Number of buckets of `QueryService::get_slot_density_distribution`
+pub const SLOT_DENSITY_BUCKETS: u64 = 10;
+
+/// Rows between two progress callbacks of `export_transactions_csv_with_progress`
+pub const EXPORT_PROGRESS_INTERVAL: u64 = 10_000;
+
+/// Largest address list accepted by `QueryService::get_transactions_by_fee_payer_batch`
+pub const MAX_BATCH_ADDRESSES: usize = 1000;
+
+/// Default for `QueryService::with_timeout`
+pub const DEFAULT_QUERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
 
 pub struct QueryService {
     client: ClickhouseClient,
+    timeout: std::time::Duration,
 }
 
 impl QueryService {
     pub fn new(client: ClickhouseClient) -> Self {
-        Self { client }
+        Self {
+            client,
+            timeout: DEFAULT_QUERY_TIMEOUT,
+        }
+    }
+
+    /// Give up on queries running longer than `timeout`. Queries are cancelled client side,
+    /// ClickHouse also stops any query after `max_execution_time`.
+    pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+        let seconds = timeout.as_secs().max(1);
+        self.client.client = self
+            .client
+            .client
+            .with_option("max_execution_time", seconds.to_string());
+        self.timeout = timeout;
+        self
     }
 
-    fn build_where_clause(&self, filters: &TransactionFilters) -> String {
+    fn build_where_clause(&self, filters: &TransactionFilters) -> WhereClause {
         let mut conditions = Vec::new();
+        let mut binds = Vec::new();
 
         if let Some(period) = &filters.period {
             conditions.push(self.period_to_sql(period));
         }
 
+        if let Some(dex) = &filters.dex {
+            conditions.push("dex = ?".to_string());
+            binds.push(dex.clone());
+        }
+
+        if let Some(program_id) = &filters.program_id {
+            conditions.push("has(program_ids, ?)".to_string());
+            binds.push(program_id.clone());
+        }
+
+        if let Some(fee_payer) = &filters.fee_payer {
+            conditions.push("fee_payer = ?".to_string());
+            binds.push(fee_payer.clone());
+        }
+
+        if let Some(account_key) = &filters.account_key {
+            conditions.push("has(account_keys_array, ?)".to_string());
+            binds.push(account_key.clone());
+        }
+
         if let Some(success) = filters.success {
             conditions.push(format!("success = {}", if success { 1 } else { 0 }));
         }
@@ -35,42 +117,88 @@ impl QueryService {
             conditions.push(format!("fee <= {}", max_fee));
         }
 
+        if let Some(min_cu) = filters.min_cu {
+            conditions.push(format!("compute_units_consumed >= {}", min_cu));
+        }
+
+        if let Some(max_cu) = filters.max_cu {
+            conditions.push(format!("compute_units_consumed <= {}", max_cu));
+        }
+
         if let Some((start_slot, end_slot)) = filters.slot_range {
             conditions.push(format!("slot >= {} AND slot <= {}", start_slot, end_slot));
         }
 
-        if conditions.is_empty() {
+        let sql = if conditions.is_empty() {
             "1=1".to_string()
         } else {
             conditions.join(" AND ")
-        }
+        };
+
+        WhereClause { sql, binds }
     }
 
     fn period_to_sql(&self, period: &TimePeriod) -> String {
+        self.period_to_sql_on("timestamp", period)
+    }
+
+    fn period_to_sql_on(&self, column: &str, period: &TimePeriod) -> String {
         match period {
-            TimePeriod::LastHour => "timestamp >= now() - INTERVAL 1 HOUR".to_string(),
-            TimePeriod::Last24Hours => "timestamp >= now() - INTERVAL 24 HOUR".to_string(),
-            TimePeriod::Last7Days => "timestamp >= now() - INTERVAL 7 DAY".to_string(),
-            TimePeriod::Last30Days => "timestamp >= now() - INTERVAL 30 DAY".to_string(),
+            TimePeriod::LastHour => format!("{} >= now() - INTERVAL 1 HOUR", column),
+            TimePeriod::Last24Hours => format!("{} >= now() - INTERVAL 24 HOUR", column),
+            TimePeriod::Last7Days => format!("{} >= now() - INTERVAL 7 DAY", column),
+            TimePeriod::Last30Days => format!("{} >= now() - INTERVAL 30 DAY", column),
             TimePeriod::Custom { start, end } => {
                 format!(
-                    "timestamp >= {} AND timestamp <= {}",
-                    start.timestamp_millis(),
-                    end.timestamp_millis()
+                    "{} >= {} AND {} <= {}",
+                    column,
+                    datetime_to_sql(start),
+                    column,
+                    datetime_to_sql(end)
                 )
             }
         }
     }
 
+    fn bucket_to_sql(&self, bucket: &TimeBucket) -> String {
+        self.bucket_to_sql_on("timestamp", bucket)
+    }
+
+    fn bucket_to_sql_on(&self, column: &str, bucket: &TimeBucket) -> String {
+        match bucket {
+            TimeBucket::Minute => format!("toStartOfMinute(toDateTime({}))", column),
+            TimeBucket::FiveMinutes => format!("toStartOfFiveMinutes(toDateTime({}))", column),
+            TimeBucket::FifteenMinutes => {
+                format!("toStartOfFifteenMinutes(toDateTime({}))", column)
+            }
+            TimeBucket::Minutes(minutes) => format!(
+                "toStartOfInterval(toDateTime({}), INTERVAL {} MINUTE)",
+                column, minutes
+            ),
+            TimeBucket::Hour => format!("toStartOfHour(toDateTime({}))", column),
+            TimeBucket::Day => format!("toStartOfDay(toDateTime({}))", column),
+            TimeBucket::Week => format!("toStartOfWeek(toDateTime({}))", column),
+        }
+    }
+
+    fn volume_where_clause(&self, filters: &VolumeFilters) -> WhereClause {
+        let tx_filters = TransactionFilters {
+            period: Some(filters.period),
+            ..filters.tx_filters.clone()
+        };
+
+        self.build_where_clause(&tx_filters)
+    }
+
     // ========== Transaction Queries ==========
 
     /// Get transaction count with optional filters
-    pub async fn count_transactions(&self, filters: TransactionFilters) -> Result<u64> {
-        let where_clause = self.build_where_clause(&filters);
+    pub async fn count_transactions(&self, filters: impl Into<TransactionFilters>) -> Result<u64> {
+        let where_clause = self.build_where_clause(&filters.into());
 
         let query = format!(
-            "SELECT count(*) as total FROM transactions WHERE {}",
-            where_clause
+            "SELECT count(*) as total FROM transactions FINAL WHERE {}",
+            where_clause.sql
         );
 
         #[derive(Row, Deserialize)]
@@ -78,7 +206,13 @@ impl QueryService {
             total: u64,
         }
 
-        let result = self.client.query_single::<CountResult>(&query).await?;
+        let result = self
+            .client
+            .fetch_optional_with_timeout::<CountResult>(
+                where_clause.bind(self.client.client.query(&query)),
+                self.timeout,
+            )
+            .await?;
         Ok(result.map(|r| r.total).unwrap_or(0))
     }
 
@@ -91,7 +225,7 @@ impl QueryService {
         SELECT
             count(*) as total,
             sum(success) as successful
-        FROM transactions
+        FROM transactions FINAL
         WHERE {}
         "#,
             period_clause
@@ -105,7 +239,7 @@ impl QueryService {
 
         let result = self
             .client
-            .query_single::<SuccessRateResult>(&query)
+            .query_with_timeout::<SuccessRateResult>(&query, self.timeout)
             .await?;
 
         match result {
@@ -118,7 +252,53 @@ impl QueryService {
     /// Get fee statistics (min, max, avg, median)
     pub async fn get_fee_stats(&self, period: TimePeriod) -> Result<FeeStats> {
         let period_clause = self.period_to_sql(&period);
+        self.fetch_fee_stats(&period_clause).await
+    }
+
+    /// Fee statistics of vote and non-vote transactions. Vote transactions pay a fixed
+    /// fee and skew the averages of `get_fee_stats` when the subscription includes them.
+    pub async fn get_fee_stats_by_type(&self, period: TimePeriod) -> Result<FeeStatsByType> {
+        let period_clause = self.period_to_sql(&period);
+        let vote = self
+            .fetch_fee_stats(&format!("{} AND is_vote = 1", period_clause))
+            .await?;
+        let non_vote = self
+            .fetch_fee_stats(&format!("{} AND is_vote = 0", period_clause))
+            .await?;
+
+        Ok(FeeStatsByType { vote, non_vote })
+    }
+
+    /// Get fee statistics of Jito bundle transactions
+    pub async fn get_jito_bundle_fee_stats(&self, period: TimePeriod) -> Result<FeeStats> {
+        let period_clause = self.period_to_sql(&period);
+        self.fetch_fee_stats(&format!("{} AND is_jito_bundle = 1", period_clause))
+            .await
+    }
+
+    /// Count transactions that tip one of the Jito tip accounts
+    pub async fn count_jito_bundles(&self, period: TimePeriod) -> Result<u64> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            "SELECT count(*) as count FROM transactions FINAL WHERE {} AND is_jito_bundle = 1",
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct CountResult {
+            count: u64,
+        }
+
+        let result = self
+            .client
+            .query_with_timeout::<CountResult>(&query, self.timeout)
+            .await?;
+        Ok(result.map(|r| r.count).unwrap_or(0))
+    }
 
+    /// Fee statistics of the transactions matching `where_clause`
+    async fn fetch_fee_stats(&self, where_clause: &str) -> Result<FeeStats> {
         let query = format!(
             r#"
         SELECT 
@@ -128,10 +308,10 @@ impl QueryService {
             quantile(0.5)(fee) as median_fee,
             sum(fee) as total_fees,
             count(*) as tx_count
-        FROM transactions
+        FROM transactions FINAL
         WHERE {} AND fee IS NOT NULL
         "#,
-            period_clause
+            where_clause
         );
 
         #[derive(Row, Deserialize)]
@@ -144,7 +324,10 @@ impl QueryService {
             tx_count: u64,
         }
 
-        let result = self.client.query_single::<FeeStatsResult>(&query).await?;
+        let result = self
+            .client
+            .query_with_timeout::<FeeStatsResult>(&query, self.timeout)
+            .await?;
 
         match result {
             Some(r) => Ok(FeeStats {
@@ -159,277 +342,640 @@ impl QueryService {
         }
     }
 
-    /// Get total fees collected
-    pub async fn get_total_fees(&self, period: TimePeriod) -> Result<u64> {
+    /// Get the share of successful transactions over time
+    pub async fn get_success_rate_timeseries(
+        &self,
+        period: TimePeriod,
+        bucket: TimeBucket,
+    ) -> Result<Vec<SuccessRateDataPoint>> {
         let period_clause = self.period_to_sql(&period);
+        let bucket_format = self.bucket_to_sql(&bucket);
 
         let query = format!(
-            "SELECT sum(fee) as total FROM transactions WHERE {} AND fee IS NOT NULL",
-            period_clause
+            r#"
+            SELECT
+                {} as time_bucket,
+                count(*) as total,
+                sum(success) as ok
+            FROM transactions FINAL
+            WHERE {}
+            GROUP BY time_bucket
+            ORDER BY time_bucket
+            "#,
+            bucket_format, period_clause
         );
 
         #[derive(Row, Deserialize)]
-        struct TotalResult {
+        struct SuccessRateRow {
+            time_bucket: i64,
             total: u64,
+            ok: u64,
         }
 
-        let result = self.client.query_single::<TotalResult>(&query).await?;
-        Ok(result.map(|r| r.total).unwrap_or(0))
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<SuccessRateRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(SuccessRateDataPoint {
+                timestamp: row.time_bucket,
+                success_rate: row.ok as f64 / row.total as f64 * 100.0,
+                total_count: row.total,
+                failed_count: row.total - row.ok,
+            });
+        }
+
+        Ok(results)
     }
 
-    /// Get transactions per second
-    pub async fn get_tps(&self, period: TimePeriod) -> Result<f64> {
+    /// Estimate retry storms by comparing the failures of each bucket to the number of
+    /// transactions in the following bucket
+    ///
+    /// Retries get a new signature, so they can't be matched to the failure they retry.
+    /// `estimated_retry_ratio` is the failures of bucket N divided by the transactions of
+    /// bucket N + 1, an upper bound of the share of bucket N + 1 that could be retries.
+    /// The last bucket has no successor yet and is left out.
+    pub async fn get_retry_transaction_rate(
+        &self,
+        period: TimePeriod,
+        bucket: TimeBucket,
+    ) -> Result<Vec<RetryPattern>> {
         let period_clause = self.period_to_sql(&period);
+        let bucket_format = self.bucket_to_sql(&bucket);
 
         let query = format!(
             r#"
-        SELECT 
-            count(*) as tx_count,
-            (max(timestamp) - min(timestamp)) / 1000.0 as duration_seconds
-        FROM transactions
-        WHERE {}
-        "#,
-            period_clause
+            SELECT
+                time_bucket,
+                failure_count,
+                next_bucket_tx_count
+            FROM (
+                SELECT
+                    time_bucket,
+                    failure_count,
+                    leadInFrame(tx_count, 1) OVER (
+                        ORDER BY time_bucket ROWS BETWEEN CURRENT ROW AND 1 FOLLOWING
+                    ) as next_bucket_tx_count
+                FROM (
+                    SELECT
+                        {} as time_bucket,
+                        countIf(success = 0) as failure_count,
+                        count(*) as tx_count
+                    FROM transactions FINAL
+                    WHERE {}
+                    GROUP BY time_bucket
+                )
+            )
+            WHERE next_bucket_tx_count > 0
+            ORDER BY time_bucket
+            "#,
+            bucket_format, period_clause
         );
 
         #[derive(Row, Deserialize)]
-        struct TpsResult {
-            tx_count: u64,
-            duration_seconds: f64,
+        struct RetryRow {
+            time_bucket: i64,
+            failure_count: u64,
+            next_bucket_tx_count: u64,
         }
 
-        let result = self.client.query_single::<TpsResult>(&query).await?;
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<RetryRow>(self.client.client.query(&query), self.timeout)
+            .await?;
+        let mut results = Vec::new();
 
-        match result {
-            Some(r) if r.duration_seconds > 0.0 => Ok(r.tx_count as f64 / r.duration_seconds),
-            _ => Ok(0.0),
+        for row in rows {
+            results.push(RetryPattern {
+                time_bucket: row.time_bucket,
+                failure_count: row.failure_count,
+                next_bucket_tx_count: row.next_bucket_tx_count,
+                estimated_retry_ratio: row.failure_count as f64 / row.next_bucket_tx_count as f64,
+            });
         }
+
+        Ok(results)
     }
 
-    /// Get transaction rate over time
-    pub async fn get_tps_timeseries(
+    /// Get time buckets whose average fee is more than `threshold_multiplier` times the
+    /// average of the previous 5 buckets
+    pub async fn get_fee_spike_events(
         &self,
         period: TimePeriod,
         bucket: TimeBucket,
-    ) -> Result<Vec<TpsDataPoint>> {
+        threshold_multiplier: f64,
+    ) -> Result<Vec<FeeSpikeEvent>> {
         let period_clause = self.period_to_sql(&period);
-        let bucket_format = match bucket {
-            TimeBucket::Minute => "toStartOfMinute(toDateTime(timestamp))",
-            TimeBucket::Hour => "toStartOfHour(toDateTime(timestamp))",
-            TimeBucket::Day => "toStartOfDay(toDateTime(timestamp))",
-            TimeBucket::Week => "toStartOfWeek(toDateTime(timestamp))",
-        };
+        let bucket_format = self.bucket_to_sql(&bucket);
 
         let query = format!(
             r#"
-        SELECT 
-            {} as time_bucket,
-            count(*) as tx_count
-        FROM transactions
-        WHERE {}
-        GROUP BY time_bucket
-        ORDER BY time_bucket
-        "#,
-            bucket_format, period_clause
+            SELECT
+                time_bucket,
+                avg_fee,
+                baseline_avg_fee,
+                avg_fee / baseline_avg_fee as spike_ratio,
+                tx_count
+            FROM (
+                SELECT
+                    time_bucket,
+                    avg_fee,
+                    avg(avg_fee) OVER (
+                        ORDER BY time_bucket ROWS BETWEEN 5 PRECEDING AND 1 PRECEDING
+                    ) as baseline_avg_fee,
+                    tx_count
+                FROM (
+                    SELECT
+                        {} as time_bucket,
+                        -- avg of the Nullable column would be Nullable(Float64)
+                        avg(assumeNotNull(fee)) as avg_fee,
+                        count(*) as tx_count
+                    FROM transactions FINAL
+                    WHERE {} AND fee IS NOT NULL
+                    GROUP BY time_bucket
+                )
+            )
+            WHERE baseline_avg_fee > 0 AND avg_fee / baseline_avg_fee > {}
+            ORDER BY time_bucket
+            "#,
+            bucket_format, period_clause, threshold_multiplier
         );
 
-        #[derive(Row, Deserialize, Serialize)]
-        struct TpsSeriesResult {
+        #[derive(Row, Deserialize)]
+        struct FeeSpikeRow {
             time_bucket: i64,
+            avg_fee: f64,
+            baseline_avg_fee: f64,
+            spike_ratio: f64,
             tx_count: u64,
         }
 
-        let mut cursor = self
-            .client
+        let rows = self
             .client
-            .query(&query)
-            .fetch::<TpsSeriesResult>()?;
+            .fetch_all_with_timeout::<FeeSpikeRow>(self.client.client.query(&query), self.timeout)
+            .await?;
         let mut results = Vec::new();
 
-        while let Some(row) = cursor.next().await? {
-            results.push(TpsDataPoint {
-                timestamp: row.time_bucket,
-                tps: row.tx_count as f64,
-                transaction_count: row.tx_count,
+        for row in rows {
+            results.push(FeeSpikeEvent {
+                bucket_start: row.time_bucket,
+                avg_fee: row.avg_fee,
+                baseline_avg_fee: row.baseline_avg_fee,
+                spike_ratio: row.spike_ratio,
+                tx_count: row.tx_count,
             });
         }
 
         Ok(results)
     }
 
-    /// Get transactions in slot range
-    pub async fn get_transactions_by_slot_range(
+    /// Success rate of transactions grouped by compute unit price, in `bucket_count`
+    /// equal width buckets from 0 to the highest price of the period. Transactions
+    /// without a price are left out, empty buckets are not returned.
+    pub async fn get_cu_price_success_correlation(
         &self,
-        start: u64,
-        end: u64,
-    ) -> Result<Vec<TransactionResult>> {
+        period: TimePeriod,
+        bucket_count: u8,
+    ) -> Result<Vec<CuPriceSuccessBucket>> {
+        if bucket_count == 0 {
+            bail!("bucket_count must be at least 1");
+        }
+
+        let period_clause = self.period_to_sql(&period);
+
+        // The upper bound is exclusive, +1 keeps the highest price in the last bucket
         let query = format!(
             r#"
-            SELECT 
-                signature,
-                slot,
-                timestamp,
-                success,
-                fee
-            FROM transactions
-            WHERE slot >= {} AND slot <= {}
-            ORDER BY slot, tx_index
-            LIMIT 10000
+            WITH (
+                SELECT ifNull(max(priority_fee_microlamports), 0)
+                FROM transactions FINAL
+                WHERE {period}
+            ) AS max_price
+            SELECT
+                toUInt64(widthBucket(
+                    assumeNotNull(priority_fee_microlamports), 0, max_price + 1, {buckets}
+                )) AS bucket,
+                any(max_price) AS max_price,
+                count(*) AS tx_count,
+                avg(success) * 100 AS success_rate
+            FROM transactions FINAL
+            WHERE {period} AND priority_fee_microlamports IS NOT NULL
+            GROUP BY bucket
+            ORDER BY bucket
             "#,
-            start, end
+            period = period_clause,
+            buckets = bucket_count
         );
 
-        #[derive(Serialize, Deserialize, Row)]
-        struct TransactionResultRow {
-            signature: String,
-            slot: u64,
-            timestamp: i64,
-            success: u8,
-            fee: Option<u64>,
+        #[derive(Row, Deserialize)]
+        struct CuPriceBucketRow {
+            bucket: u64,
+            max_price: u64,
+            tx_count: u64,
+            success_rate: f64,
         }
 
-        let mut cursor = self
-            .client
+        let rows = self
             .client
-            .query(&query)
-            .fetch::<TransactionResultRow>()?;
+            .fetch_all_with_timeout::<CuPriceBucketRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
         let mut results = Vec::new();
 
-        while let Some(row) = cursor.next().await? {
-            results.push(TransactionResult {
-                signature: row.signature,
-                slot: row.slot,
-                timestamp: DateTime::from_timestamp_millis(row.timestamp).unwrap_or_else(Utc::now),
-                success: row.success == 1,
-                fee: row.fee,
+        for row in rows {
+            // Bucket n (from 1) holds prices in [(n - 1) * width, n * width)
+            let width = (row.max_price + 1) as f64 / bucket_count as f64;
+            results.push(CuPriceSuccessBucket {
+                cu_price_bucket_start: ((row.bucket - 1) as f64 * width).ceil() as u64,
+                cu_price_bucket_end: (row.bucket as f64 * width).ceil() as u64 - 1,
+                tx_count: row.tx_count,
+                success_rate: row.success_rate,
             });
         }
 
         Ok(results)
     }
 
-    /// Get slot statistics
-    pub async fn get_slot_stats(&self, period: TimePeriod) -> Result<SlotStats> {
+    /// Get fee percentiles (p25/p50/p75/p95/p99)
+    pub async fn get_fee_percentiles(&self, period: TimePeriod) -> Result<FeePercentiles> {
         let period_clause = self.period_to_sql(&period);
 
         let query = format!(
             r#"
-            SELECT 
-                min(slot) as min_slot,
-                max(slot) as max_slot,
-                count(DISTINCT slot) as unique_slots,
-                count(*) as tx_count
-            FROM transactions
-            WHERE {}
-            "#,
+        SELECT
+            quantiles(0.25, 0.50, 0.75, 0.95, 0.99)(fee) as percentiles,
+            count(*) as tx_count
+        FROM transactions FINAL
+        WHERE {} AND fee IS NOT NULL
+        "#,
             period_clause
         );
 
-        #[derive(clickhouse::Row, serde::Deserialize)]
-        struct SlotStatsResult {
-            min_slot: u64,
-            max_slot: u64,
-            unique_slots: u64,
+        #[derive(Row, Deserialize)]
+        struct FeePercentilesResult {
+            percentiles: Vec<f64>,
             tx_count: u64,
         }
 
-        let result = self.client.query_single::<SlotStatsResult>(&query).await?;
+        let result = self
+            .client
+            .query_with_timeout::<FeePercentilesResult>(&query, self.timeout)
+            .await?;
 
         match result {
-            Some(r) => Ok(SlotStats {
-                min_slot: r.min_slot,
-                max_slot: r.max_slot,
-                unique_slots: r.unique_slots,
-                total_transactions: r.tx_count,
-                avg_tx_per_slot: if r.unique_slots > 0 {
-                    r.tx_count as f64 / r.unique_slots as f64
-                } else {
-                    0.0
-                },
+            Some(r) if r.tx_count > 0 && r.percentiles.len() == 5 => Ok(FeePercentiles {
+                p25: r.percentiles[0] as u64,
+                p50: r.percentiles[1] as u64,
+                p75: r.percentiles[2] as u64,
+                p95: r.percentiles[3] as u64,
+                p99: r.percentiles[4] as u64,
             }),
-            None => Ok(SlotStats::default()),
+            _ => Ok(FeePercentiles::default()),
         }
     }
 
-    /// Get failed transactions
-    pub async fn get_failed_transactions(
-        &self,
-        period: TimePeriod,
-        limit: Option<usize>,
-    ) -> Result<Vec<TransactionResult>> {
+    /// Get priority fee statistics (compute unit price in micro-lamports) alongside base fees
+    pub async fn get_priority_fee_stats(&self, period: TimePeriod) -> Result<PriorityFeeStats> {
         let period_clause = self.period_to_sql(&period);
-        let limit_clause = limit.map(|l| format!("LIMIT {}", l)).unwrap_or_default();
 
         let query = format!(
             r#"
-            SELECT 
-                signature,
-                slot,
-                timestamp,
-                success,
-                fee
-            FROM transactions
-            WHERE {} AND success = 0
-            ORDER BY timestamp DESC
-            {}
-            "#,
-            period_clause, limit_clause
+        SELECT
+            min(priority_fee_microlamports) as min_fee,
+            max(priority_fee_microlamports) as max_fee,
+            avg(priority_fee_microlamports) as avg_fee,
+            quantile(0.95)(priority_fee_microlamports) as p95_fee,
+            count(*) as tx_count
+        FROM transactions FINAL
+        WHERE {} AND priority_fee_microlamports IS NOT NULL
+        "#,
+            period_clause
         );
 
-        #[derive(Serialize, Deserialize, Row)]
-        struct TransactionResultRow {
-            signature: String,
-            slot: u64,
-            timestamp: i64,
-            success: u8,
-            fee: Option<u64>,
-        }
-
-        let mut cursor = self
-            .client
+        #[derive(Row, Deserialize)]
+        struct PriorityFeeResult {
+            min_fee: Option<u64>,
+            max_fee: Option<u64>,
+            avg_fee: Option<f64>,
+            p95_fee: Option<f64>,
+            tx_count: u64,
+        }
+
+        let result = self
             .client
-            .query(&query)
-            .fetch::<TransactionResultRow>()?;
+            .query_with_timeout::<PriorityFeeResult>(&query, self.timeout)
+            .await?;
+        let base_fees = self.get_fee_stats(period).await?;
+
+        match result {
+            Some(r) => Ok(PriorityFeeStats {
+                min: r.min_fee,
+                max: r.max_fee,
+                average: r.avg_fee,
+                p95: r.p95_fee.map(|v| v as u64),
+                transaction_count: r.tx_count,
+                base_fees,
+            }),
+            None => Ok(PriorityFeeStats {
+                base_fees,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Get total fees collected
+    pub async fn get_total_fees(&self, period: TimePeriod) -> Result<u64> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            "SELECT sum(fee) as total FROM transactions FINAL WHERE {} AND fee IS NOT NULL",
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct TotalResult {
+            total: u64,
+        }
+
+        let result = self
+            .client
+            .query_with_timeout::<TotalResult>(&query, self.timeout)
+            .await?;
+        Ok(result.map(|r| r.total).unwrap_or(0))
+    }
+
+    /// Get compute unit consumption statistics
+    pub async fn get_compute_units_stats(&self, period: TimePeriod) -> Result<ComputeUnitsStats> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+        SELECT
+            min(compute_units_consumed) as min_cu,
+            max(compute_units_consumed) as max_cu,
+            avg(compute_units_consumed) as avg_cu,
+            quantile(0.5)(compute_units_consumed) as median_cu,
+            quantile(0.95)(compute_units_consumed) as p95_cu,
+            sum(compute_units_consumed) as total_cu,
+            count(*) as tx_count
+        FROM transactions FINAL
+        WHERE {} AND compute_units_consumed IS NOT NULL
+        "#,
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct ComputeUnitsResult {
+            min_cu: Option<u64>,
+            max_cu: Option<u64>,
+            avg_cu: Option<f64>,
+            median_cu: Option<f64>,
+            p95_cu: Option<f64>,
+            total_cu: Option<u64>,
+            tx_count: u64,
+        }
+
+        let result = self
+            .client
+            .query_with_timeout::<ComputeUnitsResult>(&query, self.timeout)
+            .await?;
+
+        match result {
+            Some(r) => Ok(ComputeUnitsStats {
+                min: r.min_cu.unwrap_or(0),
+                max: r.max_cu.unwrap_or(0),
+                average: r.avg_cu.unwrap_or(0.0),
+                median: r.median_cu.map(|v| v as u64).unwrap_or(0),
+                p95: r.p95_cu.map(|v| v as u64).unwrap_or(0),
+                total: r.total_cu.unwrap_or(0),
+                transaction_count: r.tx_count,
+            }),
+            None => Ok(ComputeUnitsStats::default()),
+        }
+    }
+
+    /// Average TPS of the last `window_seconds`, a "current TPS" cheap enough to poll
+    /// every second
+    pub async fn get_rolling_tps(&self, window_seconds: u64) -> Result<f64> {
+        if window_seconds == 0 {
+            bail!("The rolling TPS window must be at least 1 second");
+        }
+
+        let query = "SELECT count(*) as tx_count FROM transactions FINAL \
+                     WHERE timestamp >= now() - INTERVAL ? SECOND";
+
+        #[derive(Row, Deserialize)]
+        struct CountResult {
+            tx_count: u64,
+        }
+
+        let result = self
+            .client
+            .fetch_optional_with_timeout::<CountResult>(
+                self.client.client.query(query).bind(window_seconds),
+                self.timeout,
+            )
+            .await?;
+        let tx_count = result.map(|r| r.tx_count).unwrap_or(0);
+
+        Ok(tx_count as f64 / window_seconds as f64)
+    }
+
+    /// Get transactions per second
+    ///
+    /// Falls back to the pre-aggregated `tps_minutely` table when the scan over
+    /// `transactions` times out or ClickHouse is under memory pressure
+    pub async fn get_tps(&self, period: TimePeriod) -> Result<f64> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+        SELECT 
+            count(*) as tx_count,
+            (max(timestamp) - min(timestamp)) / 1000.0 as duration_seconds
+        FROM transactions FINAL
+        WHERE {}
+        "#,
+            period_clause
+        );
+
+        let fallback_query = format!(
+            r#"
+        SELECT
+            sum(tx_count) as tx_count,
+            toFloat64(dateDiff('second', min(minute), max(minute)) + 60) as duration_seconds
+        FROM tps_minutely
+        WHERE {}
+        "#,
+            self.period_to_sql_on("minute", &period)
+        );
+
+        #[derive(Row, Deserialize)]
+        struct TpsResult {
+            tx_count: u64,
+            duration_seconds: f64,
+        }
+
+        let result = self
+            .client
+            .query_with_failover::<TpsResult>(&query, &fallback_query)
+            .await?;
+
+        match result.first() {
+            Some(r) if r.duration_seconds > 0.0 => Ok(r.tx_count as f64 / r.duration_seconds),
+            _ => Ok(0.0),
+        }
+    }
+
+    /// Get transaction rate over time
+    ///
+    /// Minute and hour buckets are read from the pre-aggregated `tps_minutely` table. It
+    /// counts every insert, so transactions replayed after a reconnect are counted again
+    /// and, unlike in `transactions`, never merged away.
+    pub async fn get_tps_timeseries(
+        &self,
+        period: TimePeriod,
+        bucket: TimeBucket,
+    ) -> Result<Vec<TpsDataPoint>> {
+        let query = if matches!(bucket, TimeBucket::Minute | TimeBucket::Hour) {
+            format!(
+                r#"
+            SELECT
+                {} as time_bucket,
+                sum(tx_count) as tx_count
+            FROM tps_minutely
+            WHERE {}
+            GROUP BY time_bucket
+            ORDER BY time_bucket
+            "#,
+                self.bucket_to_sql_on("minute", &bucket),
+                self.period_to_sql_on("minute", &period)
+            )
+        } else {
+            format!(
+                r#"
+            SELECT
+                {} as time_bucket,
+                count(*) as tx_count
+            FROM transactions FINAL
+            WHERE {}
+            GROUP BY time_bucket
+            ORDER BY time_bucket
+            "#,
+                self.bucket_to_sql(&bucket),
+                self.period_to_sql(&period)
+            )
+        };
+
+        #[derive(Row, Deserialize, Serialize)]
+        struct TpsSeriesResult {
+            time_bucket: i64,
+            tx_count: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<TpsSeriesResult>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
         let mut results = Vec::new();
 
-        while let Some(row) = cursor.next().await? {
-            results.push(TransactionResult {
-                signature: row.signature,
-                slot: row.slot,
-                timestamp: DateTime::from_timestamp_millis(row.timestamp).unwrap_or_else(Utc::now),
-                success: false,
-                fee: row.fee,
+        let bucket_seconds = bucket.seconds() as f64;
+        for row in rows {
+            results.push(TpsDataPoint {
+                timestamp: row.time_bucket,
+                tps: row.tx_count as f64 / bucket_seconds,
+                transaction_count: row.tx_count,
             });
         }
 
         Ok(results)
     }
 
-    /// Get recent transactions
-    pub async fn get_recent_transactions(
+    /// Get transaction rate over time for each DEX
+    ///
+    /// Transactions whose first program is not a known DEX have an empty `dex` and are
+    /// grouped under `""`, as are rows indexed before DEX detection was added.
+    pub async fn get_tps_by_dex_timeseries(
         &self,
-        limit: usize,
-        filters: Option<TransactionFilters>,
-    ) -> Result<Vec<TransactionResult>> {
-        let where_clause = filters
-            .as_ref()
-            .map(|f| self.build_where_clause(f))
-            .unwrap_or_else(|| "1=1".to_string());
+        period: TimePeriod,
+        bucket: TimeBucket,
+    ) -> Result<Vec<DexTpsDataPoint>> {
+        let period_clause = self.period_to_sql(&period);
+        let bucket_format = self.bucket_to_sql(&bucket);
 
         let query = format!(
             r#"
-                SELECT 
-                    signature,
-                    slot,
-                    timestamp,
-                    success,
-                    fee
-                FROM transactions
-                WHERE {}
-                ORDER BY timestamp DESC
-                LIMIT {}
-                "#,
-            where_clause, limit
+        SELECT
+            dex,
+            {} as time_bucket,
+            count(*) as tx_count
+        FROM transactions FINAL
+        WHERE {}
+        GROUP BY dex, time_bucket
+        ORDER BY time_bucket, dex
+        "#,
+            bucket_format, period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct DexTpsSeriesResult {
+            dex: String,
+            time_bucket: i64,
+            tx_count: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<DexTpsSeriesResult>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        let bucket_seconds = bucket.seconds() as f64;
+        for row in rows {
+            results.push(DexTpsDataPoint {
+                dex: row.dex,
+                timestamp: row.time_bucket,
+                tps: row.tx_count as f64 / bucket_seconds,
+                transaction_count: row.tx_count,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Get transactions in slot range
+    pub async fn get_transactions_by_slot_range(
+        &self,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<TransactionResult>> {
+        let query = format!(
+            r#"
+            SELECT 
+                signature,
+                slot,
+                timestamp,
+                success,
+                fee
+            FROM transactions FINAL
+            WHERE slot >= {} AND slot <= {}
+            ORDER BY slot, tx_index
+            LIMIT 10000
+            "#,
+            start, end
         );
 
         #[derive(Serialize, Deserialize, Row)]
@@ -441,14 +987,16 @@ impl QueryService {
             fee: Option<u64>,
         }
 
-        let mut cursor = self
+        let rows = self
             .client
-            .client
-            .query(&query)
-            .fetch::<TransactionResultRow>()?;
+            .fetch_all_with_timeout::<TransactionResultRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
         let mut results = Vec::new();
 
-        while let Some(row) = cursor.next().await? {
+        for row in rows {
             results.push(TransactionResult {
                 signature: row.signature,
                 slot: row.slot,
@@ -461,144 +1009,2672 @@ impl QueryService {
         Ok(results)
     }
 
-    /// Get transaction by signature
-    pub async fn get_transaction(&self, signature: &str) -> Result<Option<TransactionResult>> {
+    /// Get slot statistics
+    pub async fn get_slot_stats(&self, period: TimePeriod) -> Result<SlotStats> {
+        let period_clause = self.period_to_sql(&period);
+
         let query = format!(
             r#"
             SELECT 
-                signature,
-                slot,
-                timestamp,
-                success,
-                fee
-            FROM transactions
-            WHERE signature = '{}'
-            LIMIT 1
+                min(slot) as min_slot,
+                max(slot) as max_slot,
+                count(DISTINCT slot) as unique_slots,
+                count(*) as tx_count
+            FROM transactions FINAL
+            WHERE {}
             "#,
-            signature
+            period_clause
         );
 
-        #[derive(Serialize, Deserialize, Row)]
-        struct TransactionResultRow {
-            signature: String,
-            slot: u64,
-            timestamp: i64,
-            success: u8,
-            fee: Option<u64>,
+        #[derive(clickhouse::Row, serde::Deserialize)]
+        struct SlotStatsResult {
+            min_slot: u64,
+            max_slot: u64,
+            unique_slots: u64,
+            tx_count: u64,
         }
 
         let result = self
             .client
-            .query_single::<TransactionResultRow>(&query)
+            .query_with_timeout::<SlotStatsResult>(&query, self.timeout)
             .await?;
 
-        Ok(result.map(|row| TransactionResult {
-            signature: row.signature,
-            slot: row.slot,
-            timestamp: DateTime::from_timestamp_millis(row.timestamp).unwrap_or_else(Utc::now),
-            success: row.success == 1,
-            fee: row.fee,
-        }))
-    }
+        match result {
+            Some(r) => Ok(SlotStats {
+                min_slot: r.min_slot,
+                max_slot: r.max_slot,
+                unique_slots: r.unique_slots,
+                total_transactions: r.tx_count,
+                avg_tx_per_slot: if r.unique_slots > 0 {
+                    r.tx_count as f64 / r.unique_slots as f64
+                } else {
+                    0.0
+                },
+            }),
+            None => Ok(SlotStats::default()),
+        }
+    }
+
+    /// Number of transactions and their success rate by number of top level instructions,
+    /// compute budget instructions included
+    pub async fn get_instruction_count_distribution(
+        &self,
+        period: TimePeriod,
+    ) -> Result<Vec<InstructionCountBucket>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                length(JSONExtractArrayRaw(instructions)) as instruction_count,
+                count(*) as tx_count,
+                sum(success) as ok
+            FROM transactions FINAL
+            WHERE {}
+            GROUP BY instruction_count
+            ORDER BY instruction_count
+            "#,
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct InstructionCountRow {
+            instruction_count: u64,
+            tx_count: u64,
+            ok: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<InstructionCountRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(InstructionCountBucket {
+                instruction_count: row.instruction_count,
+                tx_count: row.tx_count,
+                success_rate: row.ok as f64 / row.tx_count as f64 * 100.0,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Histogram of transactions per slot, in `SLOT_DENSITY_BUCKETS` equal width buckets
+    /// from zero to the busiest slot. Slots come from the `slots` table so slots without
+    /// any indexed transaction land in the first bucket.
+    pub async fn get_slot_density_distribution(
+        &self,
+        period: TimePeriod,
+    ) -> Result<Vec<SlotDensityBucket>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            WITH
+                per_slot AS (
+                    SELECT s.slot AS slot, t.tx_count AS tx_count
+                    FROM (SELECT DISTINCT slot FROM slots WHERE {period}) AS s
+                    LEFT JOIN (
+                        SELECT slot, count(*) AS tx_count
+                        FROM transactions FINAL
+                        WHERE {period}
+                        GROUP BY slot
+                    ) AS t ON s.slot = t.slot
+                ),
+                (SELECT intDiv(max(tx_count), {buckets}) + 1 FROM per_slot) AS width
+            SELECT
+                intDiv(tx_count, width) AS bucket,
+                any(width) AS bucket_width,
+                count(*) AS slot_count,
+                sum(tx_count) AS total_txs
+            FROM per_slot
+            GROUP BY bucket
+            ORDER BY bucket
+            "#,
+            period = period_clause,
+            buckets = SLOT_DENSITY_BUCKETS
+        );
+
+        #[derive(Row, Deserialize)]
+        struct SlotDensityRow {
+            bucket: u64,
+            bucket_width: u64,
+            slot_count: u64,
+            total_txs: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<SlotDensityRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
+
+        let Some(width) = rows.first().map(|row| row.bucket_width) else {
+            return Ok(Vec::new());
+        };
+
+        // Empty buckets have no row, fill them in so the histogram has no holes
+        let results = (0..SLOT_DENSITY_BUCKETS)
+            .map(|bucket| {
+                let row = rows.iter().find(|row| row.bucket == bucket);
+                let slot_count = row.map(|row| row.slot_count).unwrap_or(0);
+                let total_txs = row.map(|row| row.total_txs).unwrap_or(0);
+
+                SlotDensityBucket {
+                    bucket_start: bucket * width,
+                    bucket_end: (bucket + 1) * width - 1,
+                    slot_count,
+                    total_txs,
+                    avg_txs_per_slot: if slot_count > 0 {
+                        total_txs as f64 / slot_count as f64
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Get ranges of slots missing from the `slots` table, i.e. slots the ingest
+    /// pipeline never received, with at least `min_gap_size` consecutive missing slots
+    pub async fn get_slot_gaps(
+        &self,
+        period: TimePeriod,
+        min_gap_size: u64,
+    ) -> Result<Vec<SlotGap>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                prev_slot + 1 as start_slot,
+                slot - 1 as end_slot,
+                slot - prev_slot - 1 as gap_size
+            FROM (
+                SELECT
+                    slot,
+                    lagInFrame(slot, 1) OVER (ORDER BY slot) as prev_slot
+                FROM (SELECT DISTINCT slot FROM slots WHERE {})
+            )
+            WHERE prev_slot > 0 AND (slot - prev_slot) > {}
+            ORDER BY start_slot
+            "#,
+            period_clause, min_gap_size
+        );
+
+        #[derive(Row, Deserialize)]
+        struct SlotGapRow {
+            start_slot: u64,
+            end_slot: u64,
+            gap_size: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<SlotGapRow>(self.client.client.query(&query), self.timeout)
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(SlotGap {
+                start_slot: row.start_slot,
+                end_slot: row.end_slot,
+                gap_size: row.gap_size,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Share of the slots in the `slots` table that contain at least one indexed transaction
+    pub async fn get_block_fill_rate(&self, period: TimePeriod) -> Result<BlockFillRate> {
+        let period_clause = self.period_to_sql(&period);
+
+        // IN instead of a join, the transactions side only needs the distinct slots
+        let query = format!(
+            r#"
+            SELECT
+                count(*) as total_slots,
+                countIf(slot IN (
+                    SELECT DISTINCT slot FROM transactions WHERE {period}
+                )) as slots_with_transactions,
+                min(first_seen) as period_start,
+                max(first_seen) as period_end
+            FROM (
+                SELECT slot, min(timestamp) as first_seen
+                FROM slots
+                WHERE {period}
+                GROUP BY slot
+            )
+            "#,
+            period = period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct BlockFillRateRow {
+            total_slots: u64,
+            slots_with_transactions: u64,
+            period_start: i64,
+            period_end: i64,
+        }
+
+        let result = self
+            .client
+            .query_with_timeout::<BlockFillRateRow>(&query, self.timeout)
+            .await?;
+
+        match result {
+            Some(r) if r.total_slots > 0 => Ok(BlockFillRate {
+                period_start: DateTime::from_timestamp_millis(r.period_start)
+                    .unwrap_or_else(Utc::now),
+                period_end: DateTime::from_timestamp_millis(r.period_end).unwrap_or_else(Utc::now),
+                total_slots: r.total_slots,
+                slots_with_transactions: r.slots_with_transactions,
+                fill_rate: r.slots_with_transactions as f64 / r.total_slots as f64,
+            }),
+            _ => Ok(BlockFillRate {
+                period_start: Utc::now(),
+                period_end: Utc::now(),
+                total_slots: 0,
+                slots_with_transactions: 0,
+                fill_rate: 0.0,
+            }),
+        }
+    }
+
+    /// Get statistics of the time between consecutive observed slots
+    ///
+    /// Gaps over 10 seconds, usually caused by slots missing from the stream, are dropped.
+    pub async fn get_average_block_time(&self, period: TimePeriod) -> Result<BlockTimeStats> {
+        let period_clause = self.period_to_sql(&period);
+
+        // The first slot has no predecessor, lagInFrame returns the epoch and the gap
+        // is dropped as an outlier
+        let query = format!(
+            r#"
+            SELECT
+                toFloat64(min(gap_ms)) as min_ms,
+                toFloat64(max(gap_ms)) as max_ms,
+                avg(gap_ms) as avg_ms,
+                toFloat64(quantile(0.95)(gap_ms)) as p95_ms,
+                count(*) as slot_count
+            FROM (
+                SELECT
+                    toUnixTimestamp64Milli(first_seen)
+                        - toUnixTimestamp64Milli(lagInFrame(first_seen, 1) OVER (ORDER BY slot))
+                        as gap_ms
+                FROM (
+                    SELECT slot, min(timestamp) as first_seen
+                    FROM slots
+                    WHERE {}
+                    GROUP BY slot
+                )
+            )
+            WHERE gap_ms BETWEEN 0 AND 10000
+            "#,
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct BlockTimeRow {
+            min_ms: f64,
+            max_ms: f64,
+            avg_ms: f64,
+            p95_ms: f64,
+            slot_count: u64,
+        }
+
+        let result = self
+            .client
+            .query_with_timeout::<BlockTimeRow>(&query, self.timeout)
+            .await?;
+
+        match result {
+            Some(r) if r.slot_count > 0 => Ok(BlockTimeStats {
+                min_ms: r.min_ms,
+                max_ms: r.max_ms,
+                avg_ms: r.avg_ms,
+                p95_ms: r.p95_ms,
+                slot_count: r.slot_count,
+            }),
+            _ => Ok(BlockTimeStats::default()),
+        }
+    }
+
+    /// Get transaction counts and average fees by day of week and hour of day (UTC)
+    pub async fn get_hourly_activity_heatmap(
+        &self,
+        period: TimePeriod,
+    ) -> Result<Vec<HeatmapCell>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                toDayOfWeek(toDateTime(timestamp)) as day_of_week,
+                toHour(toDateTime(timestamp)) as hour_of_day,
+                count(*) as tx_count,
+                ifNull(avg(fee), 0) as avg_fee
+            FROM transactions FINAL
+            WHERE {}
+            GROUP BY day_of_week, hour_of_day
+            ORDER BY day_of_week, hour_of_day
+            "#,
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct HeatmapRow {
+            day_of_week: u8,
+            hour_of_day: u8,
+            tx_count: u64,
+            avg_fee: f64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<HeatmapRow>(self.client.client.query(&query), self.timeout)
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(HeatmapCell {
+                day_of_week: row.day_of_week,
+                hour_of_day: row.hour_of_day,
+                tx_count: row.tx_count,
+                avg_fee: row.avg_fee,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Get failed transactions
+    pub async fn get_failed_transactions(
+        &self,
+        period: TimePeriod,
+        limit: Option<usize>,
+    ) -> Result<Vec<TransactionResult>> {
+        let period_clause = self.period_to_sql(&period);
+        let limit_clause = limit.map(|l| format!("LIMIT {}", l)).unwrap_or_default();
+
+        let query = format!(
+            r#"
+            SELECT 
+                signature,
+                slot,
+                timestamp,
+                success,
+                fee
+            FROM transactions FINAL
+            WHERE {} AND success = 0
+            ORDER BY timestamp DESC
+            {}
+            "#,
+            period_clause, limit_clause
+        );
+
+        #[derive(Serialize, Deserialize, Row)]
+        struct TransactionResultRow {
+            signature: String,
+            slot: u64,
+            timestamp: i64,
+            success: u8,
+            fee: Option<u64>,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<TransactionResultRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(TransactionResult {
+                signature: row.signature,
+                slot: row.slot,
+                timestamp: DateTime::from_timestamp_millis(row.timestamp).unwrap_or_else(Utc::now),
+                success: false,
+                fee: row.fee,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Non-vote transactions that paid no fee, which points at a fee extraction bug
+    pub async fn get_zero_fee_transactions(
+        &self,
+        period: TimePeriod,
+        limit: usize,
+    ) -> Result<Vec<TransactionResult>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                signature,
+                slot,
+                timestamp,
+                success,
+                fee
+            FROM transactions FINAL
+            WHERE {} AND fee = 0 AND is_vote = 0
+            ORDER BY timestamp DESC
+            LIMIT {}
+            "#,
+            period_clause, limit
+        );
+
+        self.fetch_transaction_results(&query).await
+    }
+
+    /// Deepest CPI chain of the period and the average of each transaction's deepest one
+    pub async fn get_cpi_depth_stats(&self, period: TimePeriod) -> Result<CpiDepthStats> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                max(max_cpi_depth) as max_depth,
+                ifNull(avgOrNull(max_cpi_depth), 0) as avg_depth,
+                count(*) as tx_count
+            FROM transactions FINAL
+            WHERE {}
+            "#,
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct CpiDepthRow {
+            max_depth: u8,
+            avg_depth: f64,
+            tx_count: u64,
+        }
+
+        let result = self
+            .client
+            .query_with_timeout::<CpiDepthRow>(&query, self.timeout)
+            .await?;
+
+        Ok(result
+            .map(|row| CpiDepthStats {
+                max_depth: row.max_depth,
+                avg_depth: row.avg_depth,
+                tx_count: row.tx_count,
+            })
+            .unwrap_or_default())
+    }
+
+    /// Transactions whose CPI chain reaches `min_depth` or deeper, deepest first
+    pub async fn get_high_cpi_transactions(
+        &self,
+        period: TimePeriod,
+        min_depth: u8,
+        limit: usize,
+    ) -> Result<Vec<TransactionResult>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                signature,
+                slot,
+                timestamp,
+                success,
+                fee
+            FROM transactions FINAL
+            WHERE {} AND max_cpi_depth >= {}
+            ORDER BY max_cpi_depth DESC, timestamp DESC
+            LIMIT {}
+            "#,
+            period_clause, min_depth, limit
+        );
+
+        self.fetch_transaction_results(&query).await
+    }
+
+    /// Number of non-vote transactions that paid no fee
+    pub async fn count_zero_fee_transactions(&self, period: TimePeriod) -> Result<u64> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            "SELECT count(*) as count FROM transactions FINAL WHERE {} AND fee = 0 AND is_vote = 0",
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct CountResult {
+            count: u64,
+        }
+
+        let result = self
+            .client
+            .query_with_timeout::<CountResult>(&query, self.timeout)
+            .await?;
+        Ok(result.map(|r| r.count).unwrap_or(0))
+    }
+
+    /// Most recent transactions whose log messages contain `pattern`, ignoring case.
+    ///
+    /// Scans the raw `log_messages` JSON string of every transaction in the period, so
+    /// it is slow over long periods, and quotes or backslashes in the logs have to be
+    /// searched for escaped. Prefer queries on `parsed_logs` in production.
+    pub async fn search_transactions_by_log(
+        &self,
+        pattern: &str,
+        period: TimePeriod,
+        limit: usize,
+    ) -> Result<Vec<TransactionResult>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                signature,
+                slot,
+                timestamp,
+                success,
+                fee
+            FROM transactions FINAL
+            WHERE {} AND positionCaseInsensitive(log_messages, ?) > 0
+            ORDER BY timestamp DESC
+            LIMIT {}
+            "#,
+            period_clause, limit
+        );
+
+        self.fetch_bound_transaction_results(self.client.client.query(&query).bind(pattern))
+            .await
+    }
+
+    /// Run a query selecting `signature, slot, timestamp, success, fee`
+    async fn fetch_transaction_results(&self, query: &str) -> Result<Vec<TransactionResult>> {
+        self.fetch_bound_transaction_results(self.client.client.query(query))
+            .await
+    }
+
+    /// `fetch_transaction_results` for a query with bound parameters
+    async fn fetch_bound_transaction_results(
+        &self,
+        query: Query,
+    ) -> Result<Vec<TransactionResult>> {
+        #[derive(Row, Deserialize)]
+        struct TransactionResultRow {
+            signature: String,
+            slot: u64,
+            timestamp: i64,
+            success: u8,
+            fee: Option<u64>,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<TransactionResultRow>(query, self.timeout)
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(TransactionResult {
+                signature: row.signature,
+                slot: row.slot,
+                timestamp: DateTime::from_timestamp_millis(row.timestamp).unwrap_or_else(Utc::now),
+                success: row.success != 0,
+                fee: row.fee,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Transactions with the largest lamport change on the fee payer, in either direction
+    pub async fn get_largest_net_transfers(
+        &self,
+        period: TimePeriod,
+        limit: usize,
+    ) -> Result<Vec<TransactionResult>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                signature,
+                slot,
+                timestamp,
+                success,
+                fee
+            FROM transactions FINAL
+            WHERE {}
+            ORDER BY abs(net_balance_change) DESC
+            LIMIT {}
+            "#,
+            period_clause, limit
+        );
+
+        self.fetch_transaction_results(&query).await
+    }
+
+    /// Get recent transactions
+    pub async fn get_recent_transactions(
+        &self,
+        limit: usize,
+        filters: impl Into<TransactionFilters>,
+    ) -> Result<Vec<TransactionResult>> {
+        let where_clause = self.build_where_clause(&filters.into());
+
+        let query = format!(
+            r#"
+                SELECT 
+                    signature,
+                    slot,
+                    timestamp,
+                    success,
+                    fee
+                FROM transactions FINAL
+                WHERE {}
+                ORDER BY timestamp DESC
+                LIMIT {}
+                "#,
+            where_clause.sql, limit
+        );
+
+        #[derive(Serialize, Deserialize, Row)]
+        struct TransactionResultRow {
+            signature: String,
+            slot: u64,
+            timestamp: i64,
+            success: u8,
+            fee: Option<u64>,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<TransactionResultRow>(
+                where_clause.bind(self.client.client.query(&query)),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(TransactionResult {
+                signature: row.signature,
+                slot: row.slot,
+                timestamp: DateTime::from_timestamp_millis(row.timestamp).unwrap_or_else(Utc::now),
+                success: row.success == 1,
+                fee: row.fee,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Get recent transactions page by page, newest first.
+    ///
+    /// Pass the returned cursor to get the next page, it is `None` once the last page was returned.
+    /// Unlike offsets, the cursor doesn't shift when new transactions arrive between pages.
+    pub async fn get_recent_transactions_paged(
+        &self,
+        limit: usize,
+        cursor: Option<PaginationCursor>,
+        filters: impl Into<TransactionFilters>,
+    ) -> Result<(Vec<TransactionResult>, Option<PaginationCursor>)> {
+        let mut where_clause = self.build_where_clause(&filters.into());
+
+        if let Some(cursor) = cursor {
+            where_clause.sql.push_str(&format!(
+                " AND (slot, tx_index) < ({}, {})",
+                cursor.last_slot, cursor.last_tx_index
+            ));
+        }
+
+        let query = format!(
+            r#"
+                SELECT
+                    signature,
+                    slot,
+                    tx_index,
+                    timestamp,
+                    success,
+                    fee
+                FROM transactions FINAL
+                WHERE {}
+                ORDER BY slot DESC, tx_index DESC
+                LIMIT {}
+                "#,
+            where_clause.sql, limit
+        );
+
+        #[derive(Serialize, Deserialize, Row)]
+        struct PagedTransactionRow {
+            signature: String,
+            slot: u64,
+            tx_index: u64,
+            timestamp: i64,
+            success: u8,
+            fee: Option<u64>,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<PagedTransactionRow>(
+                where_clause.bind(self.client.client.query(&query)),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+        let mut last_position = None;
+
+        for row in rows {
+            last_position = Some((row.slot, row.tx_index));
+            results.push(TransactionResult {
+                signature: row.signature,
+                slot: row.slot,
+                timestamp: DateTime::from_timestamp_millis(row.timestamp).unwrap_or_else(Utc::now),
+                success: row.success == 1,
+                fee: row.fee,
+            });
+        }
+
+        let next_cursor = match last_position {
+            Some((last_slot, last_tx_index)) if results.len() == limit => Some(PaginationCursor {
+                last_slot,
+                last_tx_index,
+            }),
+            _ => None,
+        };
+
+        Ok((results, next_cursor))
+    }
+
+    /// Write every transaction matching `filters` to `writer` as CSV, with a header row.
+    /// Rows are streamed from ClickHouse one at a time, in no particular order, so the
+    /// export size is not limited by memory. Returns the number of rows written.
+    ///
+    /// Exports run for as long as they take, the `with_timeout` limit does not apply.
+    pub async fn export_transactions_csv<W: AsyncWrite + Unpin>(
+        &self,
+        writer: W,
+        filters: TransactionFilters,
+    ) -> Result<u64> {
+        self.export_transactions_csv_with_progress(writer, filters, |_| {})
+            .await
+    }
+
+    /// `export_transactions_csv` calling `on_progress` with the rows written so far
+    /// after every `EXPORT_PROGRESS_INTERVAL` rows
+    pub async fn export_transactions_csv_with_progress<W: AsyncWrite + Unpin>(
+        &self,
+        writer: W,
+        filters: TransactionFilters,
+        mut on_progress: impl FnMut(u64),
+    ) -> Result<u64> {
+        let where_clause = self.build_where_clause(&filters);
+
+        let query = format!(
+            r#"
+            SELECT
+                signature,
+                slot,
+                timestamp,
+                success,
+                fee
+            FROM transactions FINAL
+            WHERE {}
+            "#,
+            where_clause.sql
+        );
+
+        #[derive(Row, Deserialize)]
+        struct ExportRow {
+            signature: String,
+            slot: u64,
+            timestamp: i64,
+            success: u8,
+            fee: Option<u64>,
+        }
+
+        // Lift `max_execution_time`, a large export streams for longer than any timeout
+        let mut cursor = where_clause
+            .bind(
+                self.client
+                    .client
+                    .query(&query)
+                    .with_option("max_execution_time", "0"),
+            )
+            .fetch::<ExportRow>()?;
+        let mut csv_writer = csv_async::AsyncSerializer::from_writer(writer);
+        let mut rows_written = 0;
+
+        while let Some(row) = cursor.next().await? {
+            csv_writer
+                .serialize(TransactionResult {
+                    signature: row.signature,
+                    slot: row.slot,
+                    timestamp: DateTime::from_timestamp_millis(row.timestamp)
+                        .unwrap_or_else(Utc::now),
+                    success: row.success == 1,
+                    fee: row.fee,
+                })
+                .await
+                .context("Failed to write CSV row")?;
+
+            rows_written += 1;
+            if rows_written % EXPORT_PROGRESS_INTERVAL == 0 {
+                on_progress(rows_written);
+            }
+        }
+
+        csv_writer.flush().await.context("Failed to flush CSV")?;
+
+        Ok(rows_written)
+    }
+
+    /// Get recent transactions that invoke `program_id` in a top level instruction
+    pub async fn get_transactions_by_program(
+        &self,
+        program_id: &str,
+        period: TimePeriod,
+        limit: usize,
+    ) -> Result<Vec<TransactionResult>> {
+        let filters = TransactionFilters::builder()
+            .with_program_id(program_id)
+            .with_period(period)
+            .build()?;
+
+        self.get_recent_transactions(limit, filters).await
+    }
+
+    /// Get recent transactions that include `account_key`, lookup table accounts included
+    pub async fn get_transactions_by_account(
+        &self,
+        account_key: &str,
+        period: TimePeriod,
+        limit: usize,
+    ) -> Result<Vec<TransactionResult>> {
+        let filters = TransactionFilters::builder()
+            .with_account_key(account_key)
+            .with_period(period)
+            .build()?;
+
+        self.get_recent_transactions(limit, filters).await
+    }
+
+    /// Get transaction by signature
+    pub async fn get_transaction(&self, signature: &str) -> Result<Option<TransactionResult>> {
+        let query = r#"
+            SELECT
+                signature,
+                slot,
+                timestamp,
+                success,
+                fee
+            FROM transactions FINAL
+            WHERE signature = ?
+            LIMIT 1
+            "#;
+
+        #[derive(Serialize, Deserialize, Row)]
+        struct TransactionResultRow {
+            signature: String,
+            slot: u64,
+            timestamp: i64,
+            success: u8,
+            fee: Option<u64>,
+        }
+
+        let result = self
+            .client
+            .fetch_optional_with_timeout::<TransactionResultRow>(
+                self.client.client.query(query).bind(signature),
+                self.timeout,
+            )
+            .await?;
+
+        Ok(result.map(|row| TransactionResult {
+            signature: row.signature,
+            slot: row.slot,
+            timestamp: DateTime::from_timestamp_millis(row.timestamp).unwrap_or_else(Utc::now),
+            success: row.success == 1,
+            fee: row.fee,
+        }))
+    }
+
+    /// One row report of a UTC calendar day, e.g. for a daily digest. For the current
+    /// day the TPS is averaged over the time elapsed so far.
+    pub async fn get_daily_summary(&self, date: NaiveDate) -> Result<DailySummary> {
+        let query = r#"
+            SELECT
+                count(*) as total_txs,
+                if(count(*) > 0, avg(success) * 100, 0) as success_rate,
+                ifNull(avg(fee), 0) as avg_fee,
+                ifNull(sum(fee), 0) as total_fees,
+                uniqExact(fee_payer) as unique_traders,
+                ifNull((
+                    SELECT dex
+                    FROM transactions FINAL
+                    WHERE toDate(toDateTime(timestamp)) = ? AND dex != ''
+                    GROUP BY dex
+                    ORDER BY count(*) DESC
+                    LIMIT 1
+                ), '') as top_dex
+            FROM transactions FINAL
+            WHERE toDate(toDateTime(timestamp)) = ?
+            "#;
+
+        #[derive(Row, Deserialize)]
+        struct DailySummaryRow {
+            total_txs: u64,
+            success_rate: f64,
+            avg_fee: f64,
+            total_fees: u64,
+            unique_traders: u64,
+            top_dex: String,
+        }
+
+        let day = date.to_string();
+        let result = self
+            .client
+            .fetch_optional_with_timeout::<DailySummaryRow>(
+                self.client.client.query(query).bind(&day).bind(&day),
+                self.timeout,
+            )
+            .await?;
+
+        let now = Utc::now();
+        let seconds = if date == now.date_naive() {
+            now.time().num_seconds_from_midnight().max(1) as f64
+        } else {
+            86_400.0
+        };
+
+        Ok(match result {
+            Some(row) => DailySummary {
+                date: day,
+                total_txs: row.total_txs,
+                success_rate: row.success_rate,
+                avg_fee: row.avg_fee,
+                total_fees: row.total_fees,
+                avg_tps: row.total_txs as f64 / seconds,
+                unique_traders: row.unique_traders,
+                top_dex: row.top_dex,
+            },
+            None => DailySummary {
+                date: day,
+                ..Default::default()
+            },
+        })
+    }
+
+    // ========== Account Queries ==========
+
+    /// Every recorded write of an account in `period`, oldest first
+    pub async fn get_account_history(
+        &self,
+        pubkey: &str,
+        period: TimePeriod,
+    ) -> Result<Vec<AccountSnapshot>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT pubkey, lamports, timestamp, write_version, txn_signature
+            FROM accounts
+            WHERE pubkey = ? AND {}
+            ORDER BY write_version ASC
+            "#,
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct AccountSnapshotRow {
+            pubkey: String,
+            lamports: u64,
+            timestamp: i64,
+            write_version: u64,
+            txn_signature: Option<String>,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<AccountSnapshotRow>(
+                self.client.client.query(&query).bind(pubkey),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(AccountSnapshot {
+                pubkey: row.pubkey,
+                lamports: row.lamports,
+                timestamp: DateTime::from_timestamp_millis(row.timestamp).unwrap_or_else(Utc::now),
+                write_version: row.write_version,
+                txn_signature: row.txn_signature,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Lamport balance of an account at the end of each time bucket, from its last
+    /// write in the bucket, and the change from the previous bucket. Buckets without a
+    /// write are skipped, the first bucket has a delta of 0.
+    pub async fn get_account_lamport_change_timeseries(
+        &self,
+        pubkey: &str,
+        period: TimePeriod,
+        bucket: TimeBucket,
+    ) -> Result<Vec<LamportDataPoint>> {
+        let period_clause = self.period_to_sql(&period);
+        let bucket_format = self.bucket_to_sql(&bucket);
+
+        let query = format!(
+            r#"
+            SELECT
+                toInt64(toUnixTimestamp(time_bucket)) as bucket_start,
+                lamports,
+                toInt64(lamports) - toInt64(lagInFrame(lamports, 1, lamports) OVER (
+                    ORDER BY time_bucket ROWS BETWEEN 1 PRECEDING AND CURRENT ROW
+                )) as delta
+            FROM (
+                SELECT {} as time_bucket, argMax(lamports, write_version) as lamports
+                FROM accounts
+                WHERE pubkey = ? AND {}
+                GROUP BY time_bucket
+            )
+            ORDER BY time_bucket
+            "#,
+            bucket_format, period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct LamportRow {
+            bucket_start: i64,
+            lamports: u64,
+            delta: i64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<LamportRow>(
+                self.client.client.query(&query).bind(pubkey),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(LamportDataPoint {
+                timestamp: row.bucket_start,
+                lamports: row.lamports,
+                delta: row.delta,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Get the most frequently written accounts, the contention points of the period
+    pub async fn get_account_write_frequency(
+        &self,
+        period: TimePeriod,
+        limit: usize,
+    ) -> Result<Vec<AccountWriteFreq>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                pubkey,
+                count(*) as write_count,
+                count(*) / greatest(uniqExact(slot), 1) as avg_writes_per_slot
+            FROM accounts
+            WHERE {}
+            GROUP BY pubkey
+            ORDER BY write_count DESC
+            LIMIT {}
+            "#,
+            period_clause, limit
+        );
+
+        #[derive(Row, Deserialize)]
+        struct AccountWriteFreqRow {
+            pubkey: String,
+            write_count: u64,
+            avg_writes_per_slot: f64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<AccountWriteFreqRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(AccountWriteFreq {
+                pubkey: row.pubkey,
+                write_count: row.write_count,
+                avg_writes_per_slot: row.avg_writes_per_slot,
+            });
+        }
+
+        Ok(results)
+    }
+
+    // ========== Volume Queries ==========
+
+    /// Get volume statistics
+    ///
+    /// Volume is the lamport balance change of each transaction's fee payer
+    pub async fn get_volume(&self, filters: VolumeFilters) -> Result<VolumeStats> {
+        let where_clause = self.volume_where_clause(&filters);
+
+        let query = format!(
+            r#"
+            SELECT
+                sum(volume) as total_volume,
+                count(*) as tx_count,
+                avg(volume) as avg_volume
+            FROM (
+                SELECT {} as volume
+                FROM transactions FINAL
+                WHERE {}
+            )
+            WHERE volume >= {}
+            "#,
+            VOLUME_SQL,
+            where_clause.sql,
+            filters.min_volume.unwrap_or(0)
+        );
+
+        #[derive(Row, Deserialize)]
+        struct VolumeResult {
+            total_volume: u64,
+            tx_count: u64,
+            avg_volume: f64,
+        }
+
+        let result = self
+            .client
+            .fetch_optional_with_timeout::<VolumeResult>(
+                where_clause.bind(self.client.client.query(&query)),
+                self.timeout,
+            )
+            .await?;
+
+        match result {
+            Some(r) => Ok(VolumeStats {
+                total_volume: r.total_volume,
+                transaction_count: r.tx_count,
+                average_volume: if r.tx_count > 0 { r.avg_volume } else { 0.0 },
+                period: filters.period,
+            }),
+            None => Ok(VolumeStats {
+                total_volume: 0,
+                transaction_count: 0,
+                average_volume: 0.0,
+                period: filters.period,
+            }),
+        }
+    }
+
+    /// Get volume by DEX
+    pub async fn get_volume_by_dex(&self, _period: TimePeriod) -> Result<HashMap<String, u64>> {
+        // Implementation
+        todo!()
+    }
+
+    /// Get volume by time bucket (hourly/daily)
+    pub async fn get_volume_timeseries(
+        &self,
+        filters: VolumeFilters,
+        bucket: TimeBucket,
+    ) -> Result<Vec<VolumeDataPoint>> {
+        let where_clause = self.volume_where_clause(&filters);
+        let bucket_format = self.bucket_to_sql(&bucket);
+
+        let query = format!(
+            r#"
+            SELECT
+                toInt64(toUnixTimestamp(time_bucket)) as bucket_start,
+                sum(volume) as volume,
+                count(*) as tx_count
+            FROM (
+                SELECT {} as time_bucket, {} as volume
+                FROM transactions FINAL
+                WHERE {}
+            )
+            WHERE volume >= {}
+            GROUP BY time_bucket
+            ORDER BY time_bucket
+            "#,
+            bucket_format,
+            VOLUME_SQL,
+            where_clause.sql,
+            filters.min_volume.unwrap_or(0)
+        );
+
+        #[derive(Row, Deserialize)]
+        struct VolumeSeriesResult {
+            bucket_start: i64,
+            volume: u64,
+            tx_count: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<VolumeSeriesResult>(
+                where_clause.bind(self.client.client.query(&query)),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(VolumeDataPoint {
+                timestamp: DateTime::from_timestamp(row.bucket_start, 0).unwrap_or_else(Utc::now),
+                volume: row.volume,
+                transaction_count: row.tx_count,
+            });
+        }
+
+        Ok(results)
+    }
+
+    // ========== Token Pair Queries ==========
+
+    /// Get top token pairs by volume
+    pub async fn get_top_pairs(
+        &self,
+        limit: usize,
+        period: TimePeriod,
+    ) -> Result<Vec<TokenPairStats>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                token_in,
+                token_out,
+                count(*) as swap_count,
+                sum(amount_in) as total_volume
+            FROM swap_events
+            WHERE {}
+            GROUP BY token_in, token_out
+            ORDER BY total_volume DESC
+            LIMIT {}
+            "#,
+            period_clause, limit
+        );
+
+        #[derive(Row, Deserialize)]
+        struct TokenPairRow {
+            token_in: String,
+            token_out: String,
+            swap_count: u64,
+            total_volume: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<TokenPairRow>(self.client.client.query(&query), self.timeout)
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(TokenPairStats {
+                token_a: row.token_in,
+                token_b: row.token_out,
+                swap_count: row.swap_count,
+                total_volume: row.total_volume,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Get pair statistics, counting swaps in both directions
+    pub async fn get_pair_stats(
+        &self,
+        token_a: &str,
+        token_b: &str,
+        period: TimePeriod,
+    ) -> Result<PairStats> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                sum(amount_in) as total_volume,
+                count(*) as swap_count,
+                uniqExact(trader) as unique_traders
+            FROM swap_events
+            WHERE {period}
+                AND ((token_in = ? AND token_out = ?) OR (token_in = ? AND token_out = ?))
+            "#,
+            period = period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct PairStatsResult {
+            total_volume: u64,
+            swap_count: u64,
+            unique_traders: u64,
+        }
+
+        let query = self
+            .client
+            .client
+            .query(&query)
+            .bind(token_a)
+            .bind(token_b)
+            .bind(token_b)
+            .bind(token_a);
+        let result = self
+            .client
+            .fetch_optional_with_timeout::<PairStatsResult>(query, self.timeout)
+            .await?;
+
+        let (total_volume, swap_count, unique_traders) = result
+            .map(|r| (r.total_volume, r.swap_count, r.unique_traders))
+            .unwrap_or_default();
+
+        Ok(PairStats {
+            token_a: token_a.to_string(),
+            token_b: token_b.to_string(),
+            total_volume,
+            swap_count,
+            unique_traders,
+        })
+    }
+
+    // ========== Token Queries ==========
+
+    /// Get the most traded tokens, counting both the sold and bought side of each swap
+    pub async fn get_top_traded_tokens(
+        &self,
+        period: TimePeriod,
+        metric: TokenMetric,
+        limit: usize,
+    ) -> Result<Vec<TokenMintStats>> {
+        let period_clause = self.period_to_sql(&period);
+        let order_by = match metric {
+            TokenMetric::ByVolume => "total_volume",
+            TokenMetric::BySwapCount => "swap_count",
+            TokenMetric::ByUniqueTraders => "unique_traders",
+        };
+
+        let query = format!(
+            r#"
+            SELECT
+                sides.mint as mint,
+                any(token_mints.display_name) as display_name,
+                sum(sides.amount) as total_volume,
+                count(*) as swap_count,
+                uniqExact(sides.trader) as unique_traders,
+                avgOrNull(sides.price_usd) as avg_price_usd
+            FROM (
+                SELECT token_in as mint, amount_in as amount, trader, price_usd
+                FROM swap_events
+                WHERE {period}
+                UNION ALL
+                SELECT token_out as mint, amount_out as amount, trader, price_usd
+                FROM swap_events
+                WHERE {period}
+            ) AS sides
+            LEFT JOIN (SELECT mint, display_name FROM token_mints FINAL) AS token_mints
+                ON token_mints.mint = sides.mint
+            GROUP BY sides.mint
+            ORDER BY {order_by} DESC
+            LIMIT {limit}
+            "#,
+            period = period_clause,
+            order_by = order_by,
+            limit = limit
+        );
+
+        #[derive(Row, Deserialize)]
+        struct TokenMintStatsRow {
+            mint: String,
+            display_name: Option<String>,
+            total_volume: u64,
+            swap_count: u64,
+            unique_traders: u64,
+            avg_price_usd: Option<f64>,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<TokenMintStatsRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(TokenMintStats {
+                mint: row.mint,
+                display_name: row.display_name,
+                total_volume: row.total_volume,
+                swap_count: row.swap_count,
+                unique_traders: row.unique_traders,
+                avg_price_usd: row.avg_price_usd,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Swap volume per mint, ranked by total volume, see `get_volume_by_token_sorted`
+    pub async fn get_volume_by_token(
+        &self,
+        period: TimePeriod,
+        limit: usize,
+    ) -> Result<Vec<TokenVolumeStats>> {
+        self.get_volume_by_token_sorted(period, TokenVolumeSort::TotalVolume, limit)
+            .await
+    }
+
+    /// Swap volume per mint: the amounts swapped in (sold into pools) and out (bought from
+    /// pools) and their difference. Amounts are raw token units, so they can be compared
+    /// between periods of one mint but not across mints with different decimals.
+    pub async fn get_volume_by_token_sorted(
+        &self,
+        period: TimePeriod,
+        sort: TokenVolumeSort,
+        limit: usize,
+    ) -> Result<Vec<TokenVolumeStats>> {
+        let period_clause = self.period_to_sql(&period);
+        let order_by = match sort {
+            TokenVolumeSort::NetFlow => "net_flow",
+            TokenVolumeSort::TotalVolume => "total_in_volume + total_out_volume",
+            TokenVolumeSort::SwapCount => "swap_count",
+        };
+
+        let query = format!(
+            r#"
+            SELECT
+                mint,
+                sumIf(amount_in, mint = token_in) as total_in_volume,
+                sumIf(amount_out, mint = token_out) as total_out_volume,
+                toInt64(total_in_volume) - toInt64(total_out_volume) as net_flow,
+                count(*) as swap_count
+            FROM swap_events
+            ARRAY JOIN [token_in, token_out] AS mint
+            WHERE {}
+            GROUP BY mint
+            ORDER BY {} DESC
+            LIMIT {}
+            "#,
+            period_clause, order_by, limit
+        );
+
+        #[derive(Row, Deserialize)]
+        struct TokenVolumeRow {
+            mint: String,
+            total_in_volume: u64,
+            total_out_volume: u64,
+            net_flow: i64,
+            swap_count: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<TokenVolumeRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(TokenVolumeStats {
+                mint: row.mint,
+                total_in_volume: row.total_in_volume,
+                total_out_volume: row.total_out_volume,
+                net_flow: row.net_flow,
+                swap_count: row.swap_count,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Sum of the absolute token balance changes of `mint` in UI units. Both sides of a
+    /// transfer are counted, so a transfer of 10 tokens adds 20.
+    pub async fn get_token_volume(&self, mint: &str, period: TimePeriod) -> Result<f64> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT sum(abs(JSONExtractFloat(change, 'delta'))) as volume
+            FROM transactions FINAL
+            ARRAY JOIN JSONExtractArrayRaw(token_balance_changes) AS change
+            WHERE {} AND JSONExtractString(change, 'mint') = ?
+            "#,
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct VolumeResult {
+            volume: f64,
+        }
+
+        let result = self
+            .client
+            .fetch_optional_with_timeout::<VolumeResult>(
+                self.client.client.query(&query).bind(mint),
+                self.timeout,
+            )
+            .await?;
+        Ok(result.map(|r| r.volume).unwrap_or(0.0))
+    }
+
+    // ========== Program Queries ==========
+
+    /// Most invoked programs, counting top level instructions
+    pub async fn get_program_usage_stats(
+        &self,
+        period: TimePeriod,
+        limit: usize,
+    ) -> Result<Vec<ProgramUsageStat>> {
+        let period_clause = self.period_to_sql(&period);
+
+        // `program_ids` holds each program once per transaction, the instruction
+        // count comes from the instructions themselves
+        let query = format!(
+            r#"
+            SELECT
+                program_id,
+                sum(arrayCount(
+                    ix -> ix.1 = program_id,
+                    JSONExtract(instructions, 'Array(Tuple(program_id String))')
+                )) as invocation_count,
+                count(*) as unique_transactions
+            FROM transactions FINAL
+            ARRAY JOIN program_ids AS program_id
+            WHERE {}
+            GROUP BY program_id
+            ORDER BY invocation_count DESC
+            LIMIT {}
+            "#,
+            period_clause, limit
+        );
+
+        #[derive(Row, Deserialize)]
+        struct ProgramUsageRow {
+            program_id: String,
+            invocation_count: u64,
+            unique_transactions: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<ProgramUsageRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(ProgramUsageStat {
+                program_id: row.program_id,
+                invocation_count: row.invocation_count,
+                unique_transactions: row.unique_transactions,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Programs invoked for the first time within `period`, newest first. Useful to spot
+    /// new deployments; only top level invocations of indexed transactions count.
+    pub async fn get_new_programs(&self, period: TimePeriod) -> Result<Vec<ProgramFirstSeen>> {
+        let period_clause = self.period_to_sql_on("first_timestamp", &period);
+
+        let query = format!(
+            r#"
+            SELECT
+                program_id,
+                min(first_slot) as first_slot,
+                min(first_timestamp) as first_timestamp
+            FROM program_first_seen
+            GROUP BY program_id
+            HAVING {}
+            ORDER BY first_slot DESC
+            "#,
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct FirstSeenRow {
+            program_id: String,
+            first_slot: u64,
+            first_timestamp: i64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<FirstSeenRow>(self.client.client.query(&query), self.timeout)
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(ProgramFirstSeen {
+                program_id: row.program_id,
+                first_slot: row.first_slot,
+                first_seen: DateTime::from_timestamp_millis(row.first_timestamp)
+                    .unwrap_or_else(Utc::now),
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Get the swaps announced by an `Instruction: Swap` program log, newest first
+    ///
+    /// Works for any Anchor program that logs its instruction names, without decoding
+    /// the instruction data.
+    pub async fn get_swap_events_from_logs(&self, period: TimePeriod) -> Result<Vec<LogSwapEvent>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                signature,
+                slot,
+                timestamp,
+                JSONExtractString(event, 'program_id') as program_id
+            FROM transactions FINAL
+            ARRAY JOIN JSONExtractArrayRaw(parsed_logs) as event
+            WHERE {}
+                AND position(parsed_logs, 'Instruction: Swap') > 0
+                AND JSONExtractString(event, 'event_type') = 'log'
+                AND JSONExtractString(event, 'data') = 'Instruction: Swap'
+            ORDER BY timestamp DESC
+            "#,
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct LogSwapRow {
+            signature: String,
+            slot: u64,
+            timestamp: i64,
+            program_id: String,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<LogSwapRow>(self.client.client.query(&query), self.timeout)
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(LogSwapEvent {
+                signature: row.signature,
+                slot: row.slot,
+                timestamp: DateTime::from_timestamp_millis(row.timestamp).unwrap_or_else(Utc::now),
+                program_id: row.program_id,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Get the most common instruction types by Anchor discriminator, the first 8 bytes
+    /// of the first non compute budget instruction's data
+    pub async fn get_instruction_type_stats(
+        &self,
+        period: TimePeriod,
+        limit: usize,
+    ) -> Result<Vec<InstructionTypeStat>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                instruction_discriminator as discriminator,
+                dex,
+                count(*) as count
+            FROM transactions FINAL
+            WHERE {} AND instruction_discriminator != ''
+            GROUP BY discriminator, dex
+            ORDER BY count DESC
+            LIMIT {}
+            "#,
+            period_clause, limit
+        );
+
+        #[derive(Row, Deserialize)]
+        struct InstructionTypeRow {
+            discriminator: String,
+            dex: String,
+            count: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<InstructionTypeRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(InstructionTypeStat {
+                discriminator: row.discriminator,
+                count: row.count,
+                dex: row.dex,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Get the net lamports flowing into accounts owned by `program_id` per time bucket
+    ///
+    /// Only transactions invoking the program count, and ownership comes from the
+    /// `accounts` table, so accounts that were never streamed are not included.
+    pub async fn get_net_sol_flow(
+        &self,
+        program_id: &str,
+        period: TimePeriod,
+        bucket: TimeBucket,
+    ) -> Result<Vec<SolFlow>> {
+        let period_clause = self.period_to_sql(&period);
+        let bucket_format = self.bucket_to_sql(&bucket);
+
+        // balance_deltas also covers accounts loaded from lookup tables, which have no
+        // entry in account_keys_array on rows indexed before is_versioned was added,
+        // so the two are joined by index
+        let query = format!(
+            r#"
+            SELECT
+                {} as time_bucket,
+                sum(balance_deltas[i]) as net_inflow,
+                uniqExact(signature) as tx_count
+            FROM transactions FINAL
+            ARRAY JOIN arrayEnumerate(account_keys_array) as i
+            WHERE {}
+                AND has(program_ids, ?)
+                AND account_keys_array[i] IN (
+                    SELECT DISTINCT pubkey FROM accounts WHERE owner = ?
+                )
+            GROUP BY time_bucket
+            ORDER BY time_bucket
+            "#,
+            bucket_format, period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct SolFlowRow {
+            time_bucket: i64,
+            net_inflow: i64,
+            tx_count: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<SolFlowRow>(
+                self.client
+                    .client
+                    .query(&query)
+                    .bind(program_id)
+                    .bind(program_id),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(SolFlow {
+                program_id: program_id.to_string(),
+                net_inflow_lamports: row.net_inflow,
+                tx_count: row.tx_count,
+                period_start: DateTime::from_timestamp(row.time_bucket, 0).unwrap_or_else(Utc::now),
+            });
+        }
+
+        Ok(results)
+    }
+
+    // ========== DEX Queries ==========
+
+    /// Compare DEX performance
+    pub async fn compare_dexes(&self, period: TimePeriod) -> Result<Vec<DexStats>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                dex,
+                count(*) as tx_count,
+                sum({}) as total_volume,
+                avg(toUInt8(success)) as success_rate,
+                ifNull(avg(fee), 0) as avg_fee
+            FROM transactions FINAL
+            WHERE dex != '' AND {}
+            GROUP BY dex
+            ORDER BY tx_count DESC
+            "#,
+            VOLUME_SQL, period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct DexStatsRow {
+            dex: String,
+            tx_count: u64,
+            total_volume: u64,
+            success_rate: f64,
+            avg_fee: f64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<DexStatsRow>(self.client.client.query(&query), self.timeout)
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(DexStats {
+                dex: row.dex,
+                transaction_count: row.tx_count,
+                total_volume: row.total_volume,
+                success_rate: row.success_rate * 100.0,
+                average_fee: row.avg_fee,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Share of failed transactions per DEX, highest first. Failures are only indexed
+    /// with `include_failed` enabled, otherwise every rate is 0.
+    pub async fn get_revert_rate_by_dex(&self, period: TimePeriod) -> Result<Vec<DexRevertRate>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                dex,
+                count(*) as total,
+                countIf(success = 0) as failed,
+                failed / total as rate
+            FROM transactions FINAL
+            WHERE dex != '' AND {}
+            GROUP BY dex
+            ORDER BY rate DESC
+            "#,
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct DexRevertRow {
+            dex: String,
+            total: u64,
+            failed: u64,
+            rate: f64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<DexRevertRow>(self.client.client.query(&query), self.timeout)
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(DexRevertRate {
+                dex: row.dex,
+                total_txs: row.total,
+                failed_txs: row.failed,
+                revert_rate: row.rate * 100.0,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Get DEX market share, as a percentage of DEX transactions
+    pub async fn get_dex_market_share(&self, period: TimePeriod) -> Result<HashMap<String, f64>> {
+        let stats = self.compare_dexes(period).await?;
+        let total: u64 = stats.iter().map(|s| s.transaction_count).sum();
+
+        if total == 0 {
+            return Ok(HashMap::new());
+        }
+
+        Ok(stats
+            .into_iter()
+            .map(|s| {
+                let share = (s.transaction_count as f64) / (total as f64) * 100.0;
+                (s.dex, share)
+            })
+            .collect())
+    }
+
+    /// Find possible sandwich attacks: the same fee payer at positions N and N + 2 of a
+    /// slot with a different fee payer in between
+    ///
+    /// Only indexed transactions are seen, so a sandwich with an unindexed transaction at
+    /// one of the three positions is missed.
+    pub async fn get_sandwich_attack_candidates(
+        &self,
+        period: TimePeriod,
+        limit: usize,
+    ) -> Result<Vec<SandwichCandidate>> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            WITH txs AS (
+                SELECT slot, tx_index, signature, fee_payer
+                FROM transactions FINAL
+                WHERE is_vote = 0 AND {}
+            )
+            SELECT
+                front.slot as slot,
+                front.fee_payer as attacker,
+                victim.signature as victim_signature,
+                front.signature as front_run_sig,
+                back.signature as back_run_sig
+            FROM txs AS front
+            INNER JOIN txs AS victim
+                ON victim.slot = front.slot AND victim.tx_index = front.tx_index + 1
+            INNER JOIN txs AS back
+                ON back.slot = front.slot AND back.tx_index = front.tx_index + 2
+            WHERE back.fee_payer = front.fee_payer AND victim.fee_payer != front.fee_payer
+            ORDER BY slot DESC, front.tx_index
+            LIMIT {}
+            "#,
+            period_clause, limit
+        );
+
+        #[derive(Row, Deserialize)]
+        struct SandwichRow {
+            slot: u64,
+            attacker: String,
+            victim_signature: String,
+            front_run_sig: String,
+            back_run_sig: String,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<SandwichRow>(self.client.client.query(&query), self.timeout)
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(SandwichCandidate {
+                slot: row.slot,
+                attacker: row.attacker,
+                victim_signature: row.victim_signature,
+                front_run_sig: row.front_run_sig,
+                back_run_sig: row.back_run_sig,
+            });
+        }
+
+        Ok(results)
+    }
+
+    // ========== User/Trader Queries ==========
+
+    /// Count transactions flagged by `Transformer::detect_arbitrage`
+    pub async fn count_arbitrage_transactions(&self, period: TimePeriod) -> Result<u64> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            "SELECT count(*) as count FROM transactions FINAL WHERE {} AND is_arbitrage = 1",
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct CountResult {
+            count: u64,
+        }
+
+        let result = self
+            .client
+            .query_with_timeout::<CountResult>(&query, self.timeout)
+            .await?;
+        Ok(result.map(|r| r.count).unwrap_or(0))
+    }
+
+    /// Arbitrage transactions per time bucket, and their share of all transactions
+    pub async fn get_arbitrage_timeseries(
+        &self,
+        period: TimePeriod,
+        bucket: TimeBucket,
+    ) -> Result<Vec<ArbitrageDataPoint>> {
+        let period_clause = self.period_to_sql(&period);
+        let bucket_format = self.bucket_to_sql(&bucket);
+
+        let query = format!(
+            r#"
+            SELECT
+                {} as time_bucket,
+                count(*) as total,
+                countIf(is_arbitrage = 1) as arbitrage
+            FROM transactions FINAL
+            WHERE {}
+            GROUP BY time_bucket
+            ORDER BY time_bucket
+            "#,
+            bucket_format, period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct ArbitrageRow {
+            time_bucket: i64,
+            total: u64,
+            arbitrage: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<ArbitrageRow>(self.client.client.query(&query), self.timeout)
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(ArbitrageDataPoint {
+                timestamp: row.time_bucket,
+                arbitrage_count: row.arbitrage,
+                total_count: row.total,
+                arbitrage_rate: row.arbitrage as f64 / row.total as f64 * 100.0,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Count distinct fee payers
+    ///
+    /// `uniqExact` keeps every fee payer in memory. On very large periods `uniq`, an
+    /// approximation within a few percent, is much cheaper.
+    pub async fn count_unique_traders(&self, period: TimePeriod) -> Result<u64> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT uniqExact(fee_payer) as count
+            FROM transactions FINAL
+            WHERE {} AND fee_payer != ''
+            "#,
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct CountResult {
+            count: u64,
+        }
+
+        let result = self
+            .client
+            .query_with_timeout::<CountResult>(&query, self.timeout)
+            .await?;
+        Ok(result.map(|r| r.count).unwrap_or(0))
+    }
+
+    /// Count distinct fee payers per time bucket, see `count_unique_traders`
+    pub async fn get_unique_traders_timeseries(
+        &self,
+        period: TimePeriod,
+        bucket: TimeBucket,
+    ) -> Result<Vec<UniqueTraderDataPoint>> {
+        let period_clause = self.period_to_sql(&period);
+        let bucket_format = self.bucket_to_sql(&bucket);
+
+        let query = format!(
+            r#"
+            SELECT
+                {} as time_bucket,
+                uniqExact(fee_payer) as unique_traders
+            FROM transactions FINAL
+            WHERE {} AND fee_payer != ''
+            GROUP BY time_bucket
+            ORDER BY time_bucket
+            "#,
+            bucket_format, period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct UniqueTradersRow {
+            time_bucket: i64,
+            unique_traders: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<UniqueTradersRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(UniqueTraderDataPoint {
+                timestamp: row.time_bucket,
+                unique_traders: row.unique_traders,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Get top traders
+    pub async fn get_top_traders(
+        &self,
+        _period: TimePeriod,
+        _limit: usize,
+    ) -> Result<Vec<TraderStats>> {
+        // Implementation
+        todo!()
+    }
+
+    /// Rough profit and loss of the traders with at least `min_swaps` priced swaps, most
+    /// profitable first.
+    ///
+    /// This is an approximation, not accounting:
+    /// - Every mint is priced in USDC at its volume weighted average rate over the
+    ///   period's swaps against USDC. Swaps involving a mint without such a swap are left
+    ///   out, as are swaps from undecoded instructions.
+    /// - Amounts are the ones in the swap instruction (input and quoted or minimum
+    ///   output), not what was settled, and fees are ignored.
+    /// - A swap wins if its output is worth more than its input at those prices, which
+    ///   measures timing against the period's average rate rather than realized profit.
+    pub async fn get_trader_pnl_estimates(
+        &self,
+        period: TimePeriod,
+        limit: usize,
+        min_swaps: u64,
+    ) -> Result<Vec<TraderPnl>> {
+        let period_clause = self.period_to_sql(&period);
+
+        // USDC per raw unit of each mint, in raw USDC units
+        let query = format!(
+            r#"
+            WITH prices AS (
+                SELECT mint, sum(usdc) / sum(amount) as usdc_per_unit
+                FROM (
+                    SELECT token_in as mint, amount_in as amount, amount_out as usdc
+                    FROM swap_events
+                    WHERE token_out = '{usdc}' AND {period}
+                    UNION ALL
+                    SELECT token_out as mint, amount_out as amount, amount_in as usdc
+                    FROM swap_events
+                    WHERE token_in = '{usdc}' AND {period}
+                )
+                GROUP BY mint
+                HAVING sum(amount) > 0
+                UNION ALL
+                SELECT '{usdc}' as mint, 1 as usdc_per_unit
+            )
+            SELECT
+                trader,
+                sum(value_out - value_in) / 1e6 as estimated_pnl_usd,
+                count(*) as total_swaps,
+                countIf(value_out > value_in) / count(*) * 100 as win_rate
+            FROM (
+                SELECT
+                    swaps.trader as trader,
+                    swaps.amount_in * price_in.usdc_per_unit as value_in,
+                    swaps.amount_out * price_out.usdc_per_unit as value_out
+                FROM swap_events AS swaps
+                INNER JOIN prices AS price_in ON price_in.mint = swaps.token_in
+                INNER JOIN prices AS price_out ON price_out.mint = swaps.token_out
+                WHERE {swaps_period}
+            )
+            GROUP BY trader
+            HAVING total_swaps >= {min_swaps}
+            ORDER BY estimated_pnl_usd DESC
+            LIMIT {limit}
+            "#,
+            usdc = USDC_MINT,
+            period = period_clause,
+            swaps_period = self.period_to_sql_on("swaps.timestamp", &period),
+            min_swaps = min_swaps,
+            limit = limit
+        );
+
+        #[derive(Row, Deserialize)]
+        struct TraderPnlRow {
+            trader: String,
+            estimated_pnl_usd: f64,
+            total_swaps: u64,
+            win_rate: f64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<TraderPnlRow>(self.client.client.query(&query), self.timeout)
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(TraderPnl {
+                address: row.trader,
+                estimated_pnl_usd: row.estimated_pnl_usd,
+                total_swaps: row.total_swaps,
+                win_rate: row.win_rate,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Get trader activity
+    pub async fn get_trader_activity(
+        &self,
+        address: &str,
+        period: TimePeriod,
+    ) -> Result<TraderActivity> {
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                count(*) as tx_count,
+                min(timestamp) as first_seen,
+                max(timestamp) as last_seen,
+                sum({}) as total_volume
+            FROM transactions FINAL
+            WHERE fee_payer = ? AND {}
+            "#,
+            VOLUME_SQL, period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct TraderActivityRow {
+            tx_count: u64,
+            first_seen: i64,
+            last_seen: i64,
+            total_volume: u64,
+        }
+
+        let result = self
+            .client
+            .fetch_optional_with_timeout::<TraderActivityRow>(
+                self.client.client.query(&query).bind(address),
+                self.timeout,
+            )
+            .await?;
+
+        // min/max of no rows are the epoch, not a real first/last transaction
+        Ok(match result {
+            Some(row) if row.tx_count > 0 => TraderActivity {
+                address: address.to_string(),
+                tx_count: row.tx_count,
+                first_seen: DateTime::from_timestamp_millis(row.first_seen),
+                last_seen: DateTime::from_timestamp_millis(row.last_seen),
+                total_volume: row.total_volume,
+            },
+            _ => TraderActivity {
+                address: address.to_string(),
+                tx_count: 0,
+                first_seen: None,
+                last_seen: None,
+                total_volume: 0,
+            },
+        })
+    }
+
+    /// Transaction count and volume of every address in `addresses`, in a single query.
+    /// Addresses without transactions in the period get zeroed stats.
+    pub async fn get_transactions_by_fee_payer_batch(
+        &self,
+        addresses: &[String],
+        period: TimePeriod,
+    ) -> Result<HashMap<String, TraderStats>> {
+        if addresses.len() > MAX_BATCH_ADDRESSES {
+            anyhow::bail!(
+                "At most {} addresses per batch, got {}",
+                MAX_BATCH_ADDRESSES,
+                addresses.len()
+            );
+        }
+
+        let mut results: HashMap<String, TraderStats> = addresses
+            .iter()
+            .map(|address| {
+                let stats = TraderStats {
+                    address: address.clone(),
+                    tx_count: 0,
+                    total_volume: 0,
+                };
+                (address.clone(), stats)
+            })
+            .collect();
+        if results.is_empty() {
+            return Ok(results);
+        }
+
+        let period_clause = self.period_to_sql(&period);
+
+        let query = format!(
+            r#"
+            SELECT
+                fee_payer,
+                count(*) as tx_count,
+                sum({}) as total_volume
+            FROM transactions FINAL
+            WHERE fee_payer IN ? AND {}
+            GROUP BY fee_payer
+            "#,
+            VOLUME_SQL, period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct FeePayerRow {
+            fee_payer: String,
+            tx_count: u64,
+            total_volume: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<FeePayerRow>(
+                self.client.client.query(&query).bind(addresses),
+                self.timeout,
+            )
+            .await?;
+
+        for row in rows {
+            if let Some(stats) = results.get_mut(&row.fee_payer) {
+                stats.tx_count = row.tx_count;
+                stats.total_volume = row.total_volume;
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Failed transactions per time bucket and error message, for the `top_n_errors` most
+    /// frequent messages of the period. Buckets without a given error have no row for it.
+    pub async fn get_failed_transaction_error_timeseries(
+        &self,
+        period: TimePeriod,
+        bucket: TimeBucket,
+        top_n_errors: usize,
+    ) -> Result<Vec<ErrorTimeseriesPoint>> {
+        let period_clause = self.period_to_sql(&period);
+        let bucket_format = self.bucket_to_sql(&bucket);
+
+        let query = format!(
+            r#"
+            SELECT
+                {} as time_bucket,
+                ifNull(error_message, 'unknown') as error,
+                count(*) as count
+            FROM transactions FINAL
+            WHERE success = 0 AND {} AND error IN (
+                SELECT ifNull(error_message, 'unknown') as top_error
+                FROM transactions FINAL
+                WHERE success = 0 AND {}
+                GROUP BY top_error
+                ORDER BY count(*) DESC
+                LIMIT {}
+            )
+            GROUP BY time_bucket, error
+            ORDER BY time_bucket, count DESC
+            "#,
+            bucket_format, period_clause, period_clause, top_n_errors
+        );
+
+        #[derive(Row, Deserialize)]
+        struct ErrorBucketRow {
+            time_bucket: i64,
+            error: String,
+            count: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<ErrorBucketRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
+        let mut results = Vec::new();
+
+        for row in rows {
+            results.push(ErrorTimeseriesPoint {
+                timestamp: row.time_bucket,
+                error_message: row.error,
+                count: row.count,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Get error patterns
+    pub async fn analyze_errors(&self, period: TimePeriod) -> Result<ErrorAnalysis> {
+        let period_clause = self.period_to_sql(&period);
+
+        let count_query = format!(
+            "SELECT count(*) as total FROM transactions FINAL WHERE success = 0 AND {}",
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct CountResult {
+            total: u64,
+        }
+
+        let total_failed = self
+            .client
+            .query_with_timeout::<CountResult>(&count_query, self.timeout)
+            .await?
+            .map(|r| r.total)
+            .unwrap_or(0);
+
+        let query = format!(
+            r#"
+            SELECT
+                ifNull(error_message, 'unknown') as error,
+                count(*) as frequency
+            FROM transactions FINAL
+            WHERE success = 0 AND {}
+            GROUP BY error
+            ORDER BY frequency DESC
+            LIMIT 50
+            "#,
+            period_clause
+        );
+
+        #[derive(Row, Deserialize)]
+        struct ErrorPatternRow {
+            error: String,
+            frequency: u64,
+        }
+
+        let rows = self
+            .client
+            .fetch_all_with_timeout::<ErrorPatternRow>(
+                self.client.client.query(&query),
+                self.timeout,
+            )
+            .await?;
+        let mut common_error_patterns = Vec::new();
+
+        for row in rows {
+            common_error_patterns.push((row.error, row.frequency));
+        }
+
+        Ok(ErrorAnalysis {
+            total_failed,
+            common_error_patterns,
+        })
+    }
+}
+
+// Filter types
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilters {
+    pub dex: Option<String>,
+    pub program_id: Option<String>,
+    pub account_key: Option<String>,
+    pub fee_payer: Option<String>,
+    pub success: Option<bool>,
+    pub min_fee: Option<u64>,
+    pub max_fee: Option<u64>,
+    pub min_cu: Option<u64>,
+    pub max_cu: Option<u64>,
+    pub period: Option<TimePeriod>,
+    pub slot_range: Option<(u64, u64)>,
+}
 
-    // ========== Volume Queries ==========
+impl TransactionFilters {
+    pub fn builder() -> TransactionFiltersBuilder {
+        TransactionFiltersBuilder::default()
+    }
+}
 
-    /// Get volume statistics
-    pub async fn get_volume(&self, _filters: VolumeFilters) -> Result<VolumeStats> {
-        // Implementation
-        todo!()
+impl From<TimePeriod> for TransactionFilters {
+    fn from(period: TimePeriod) -> Self {
+        Self {
+            period: Some(period),
+            ..Default::default()
+        }
     }
+}
 
-    /// Get volume by DEX
-    pub async fn get_volume_by_dex(&self, _period: TimePeriod) -> Result<HashMap<String, u64>> {
-        // Implementation
-        todo!()
+/// Fluent builder for `TransactionFilters`, `build` rejects inverted ranges
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFiltersBuilder {
+    filters: TransactionFilters,
+}
+
+impl TransactionFiltersBuilder {
+    pub fn with_period(mut self, period: TimePeriod) -> Self {
+        self.filters.period = Some(period);
+        self
     }
 
-    /// Get volume by time bucket (hourly/daily)
-    pub async fn get_volume_timeseries(
-        &self,
-        _period: TimePeriod,
-        _bucket: TimeBucket,
-    ) -> Result<Vec<VolumeDataPoint>> {
-        // Implementation
-        todo!()
+    pub fn with_success(mut self, success: bool) -> Self {
+        self.filters.success = Some(success);
+        self
     }
 
-    // ========== Token Pair Queries ==========
+    pub fn with_fee_range(mut self, min_fee: u64, max_fee: u64) -> Self {
+        self.filters.min_fee = Some(min_fee);
+        self.filters.max_fee = Some(max_fee);
+        self
+    }
 
-    /// Get top token pairs by volume
-    pub async fn get_top_pairs(
-        &self,
-        _limit: usize,
-        _period: TimePeriod,
-    ) -> Result<Vec<TokenPairStats>> {
-        // Implementation
-        todo!()
+    pub fn with_min_fee(mut self, min_fee: u64) -> Self {
+        self.filters.min_fee = Some(min_fee);
+        self
     }
 
-    /// Get pair statistics
-    pub async fn get_pair_stats(
-        &self,
-        _token_a: &str,
-        _token_b: &str,
-        _period: TimePeriod,
-    ) -> Result<PairStats> {
-        // Implementation
-        todo!()
+    pub fn with_max_fee(mut self, max_fee: u64) -> Self {
+        self.filters.max_fee = Some(max_fee);
+        self
     }
 
-    // ========== DEX Queries ==========
+    pub fn with_min_cu(mut self, min_cu: u64) -> Self {
+        self.filters.min_cu = Some(min_cu);
+        self
+    }
 
-    /// Compare DEX performance
-    pub async fn compare_dexes(&self, _period: TimePeriod) -> Result<Vec<DexStats>> {
-        // Implementation
-        todo!()
+    pub fn with_max_cu(mut self, max_cu: u64) -> Self {
+        self.filters.max_cu = Some(max_cu);
+        self
     }
 
-    /// Get DEX market share
-    pub async fn get_dex_market_share(&self, _period: TimePeriod) -> Result<HashMap<String, f64>> {
-        // Implementation
-        todo!()
+    pub fn with_slot_range(mut self, start_slot: u64, end_slot: u64) -> Self {
+        self.filters.slot_range = Some((start_slot, end_slot));
+        self
     }
 
-    // ========== User/Trader Queries ==========
+    pub fn with_program_id(mut self, program_id: &str) -> Self {
+        self.filters.program_id = Some(program_id.to_string());
+        self
+    }
 
-    /// Get top traders
-    pub async fn get_top_traders(
-        &self,
-        _period: TimePeriod,
-        _limit: usize,
-    ) -> Result<Vec<TraderStats>> {
-        // Implementation
-        todo!()
+    pub fn with_account_key(mut self, account_key: &str) -> Self {
+        self.filters.account_key = Some(account_key.to_string());
+        self
     }
 
-    /// Get trader activity
-    pub async fn get_trader_activity(
-        &self,
-        _address: &str,
-        _period: TimePeriod,
-    ) -> Result<TraderActivity> {
-        // Implementation
-        todo!()
+    pub fn with_fee_payer(mut self, fee_payer: &str) -> Self {
+        self.filters.fee_payer = Some(fee_payer.to_string());
+        self
     }
 
-    /// Get error patterns
-    pub async fn analyze_errors(&self, _period: TimePeriod) -> Result<ErrorAnalysis> {
-        todo!()
+    pub fn build(self) -> Result<TransactionFilters> {
+        if let (Some(min_fee), Some(max_fee)) = (self.filters.min_fee, self.filters.max_fee) {
+            anyhow::ensure!(
+                min_fee <= max_fee,
+                "min_fee ({}) is greater than max_fee ({})",
+                min_fee,
+                max_fee
+            );
+        }
+
+        if let (Some(min_cu), Some(max_cu)) = (self.filters.min_cu, self.filters.max_cu) {
+            anyhow::ensure!(
+                min_cu <= max_cu,
+                "min_cu ({}) is greater than max_cu ({})",
+                min_cu,
+                max_cu
+            );
+        }
+
+        if let Some((start_slot, end_slot)) = self.filters.slot_range {
+            anyhow::ensure!(
+                start_slot <= end_slot,
+                "start slot ({}) is after end slot ({})",
+                start_slot,
+                end_slot
+            );
+        }
+
+        Ok(self.filters)
     }
 }
 
-// Filter types
-#[derive(Debug, Clone, Default)]
-pub struct TransactionFilters {
-    pub dex: Option<String>,
-    pub success: Option<bool>,
-    pub min_fee: Option<u64>,
-    pub max_fee: Option<u64>,
-    pub period: Option<TimePeriod>,
-    pub slot_range: Option<(u64, u64)>,
+/// Position of the last transaction of a page, see `get_recent_transactions_paged`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PaginationCursor {
+    pub last_slot: u64,
+    pub last_tx_index: u64,
+}
+
+impl PaginationCursor {
+    /// Encode as base64 JSON, to pass around as an opaque string
+    pub fn encode(&self) -> Result<String> {
+        Ok(general_purpose::STANDARD.encode(serde_json::to_vec(self)?))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self> {
+        let json = general_purpose::STANDARD
+            .decode(cursor)
+            .context("cursor is not valid base64")?;
+        serde_json::from_slice(&json).context("cursor is not a valid pagination cursor")
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -620,17 +3696,147 @@ pub enum TimePeriod {
     },
 }
 
+impl FromStr for TimePeriod {
+    type Err = anyhow::Error;
+
+    /// Parses `<number><unit>` with unit `m`, `h`, `d` or `w` (e.g. "15m", "3h", "90d"),
+    /// or one of the aliases "today", "this-week" and "this-month"
+    fn from_str(s: &str) -> Result<Self> {
+        let now = Utc::now();
+        let start_of_today = now
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight is a valid time")
+            .and_utc();
+
+        let start = match s {
+            "1h" => return Ok(TimePeriod::LastHour),
+            "24h" => return Ok(TimePeriod::Last24Hours),
+            "7d" => return Ok(TimePeriod::Last7Days),
+            "30d" => return Ok(TimePeriod::Last30Days),
+            "today" => start_of_today,
+            "this-week" => {
+                start_of_today - Duration::days(now.weekday().num_days_from_monday() as i64)
+            }
+            "this-month" => start_of_today - Duration::days(now.day0() as i64),
+            _ => {
+                let unit_len = s.chars().last().map_or(0, char::len_utf8);
+                let (amount, unit) = s.split_at(s.len() - unit_len);
+                let amount: i64 = match amount.parse() {
+                    Ok(amount) if amount > 0 => amount,
+                    _ => bail!(
+                        "invalid period '{}', expected <number><m|h|d|w> (e.g. 15m, 3h, 7d) \
+                         or one of today, this-week, this-month",
+                        s
+                    ),
+                };
+
+                let duration = match unit {
+                    "m" => Duration::try_minutes(amount),
+                    "h" => Duration::try_hours(amount),
+                    "d" => Duration::try_days(amount),
+                    "w" => Duration::try_weeks(amount),
+                    _ => bail!(
+                        "invalid period unit '{}' in '{}', expected one of m, h, d, w",
+                        unit,
+                        s
+                    ),
+                };
+
+                match duration {
+                    Some(duration) => now - duration,
+                    None => bail!("period '{}' is too long", s),
+                }
+            }
+        };
+
+        Ok(TimePeriod::Custom { start, end: now })
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
+pub enum TokenMetric {
+    ByVolume,
+    BySwapCount,
+    ByUniqueTraders,
+}
+
+/// Ranking of `QueryService::get_volume_by_token_sorted`, largest first
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenVolumeSort {
+    NetFlow,
+    TotalVolume,
+    SwapCount,
+}
+
+impl FromStr for TokenVolumeSort {
+    type Err = anyhow::Error;
+
+    /// Parses "net_flow", "total_volume" or "swap_count"
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "net_flow" => Ok(TokenVolumeSort::NetFlow),
+            "total_volume" => Ok(TokenVolumeSort::TotalVolume),
+            "swap_count" => Ok(TokenVolumeSort::SwapCount),
+            _ => bail!(
+                "invalid sort '{}', expected net_flow, total_volume or swap_count",
+                s
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TimeBucket {
     Minute,
+    FiveMinutes,
+    FifteenMinutes,
+    Minutes(u32),
     Hour,
     Day,
     Week,
 }
 
+impl TimeBucket {
+    /// Length of the bucket, what a bucket's transaction count is divided by to get its TPS
+    pub fn seconds(&self) -> u64 {
+        match self {
+            TimeBucket::Minute => 60,
+            TimeBucket::FiveMinutes => 5 * 60,
+            TimeBucket::FifteenMinutes => 15 * 60,
+            TimeBucket::Minutes(minutes) => *minutes as u64 * 60,
+            TimeBucket::Hour => 60 * 60,
+            TimeBucket::Day => 24 * 60 * 60,
+            TimeBucket::Week => 7 * 24 * 60 * 60,
+        }
+    }
+}
+
+impl FromStr for TimeBucket {
+    type Err = anyhow::Error;
+
+    /// Parses "M", "H", "D", "W" or `<number>M` for minute buckets (e.g. "5M", "15M")
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "M" | "1M" => Ok(TimeBucket::Minute),
+            "H" => Ok(TimeBucket::Hour),
+            "D" => Ok(TimeBucket::Day),
+            "W" => Ok(TimeBucket::Week),
+            _ => match s.strip_suffix('M').map(str::parse::<u32>) {
+                Some(Ok(minutes)) if minutes > 0 => Ok(TimeBucket::Minutes(minutes)),
+                _ => bail!(
+                    "invalid bucket '{}', expected one of M, H, D, W or <number>M (e.g. 5M, 15M)",
+                    s
+                ),
+            },
+        }
+    }
+}
+
 // result types
 
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct TransactionResult {
     pub signature: String,
     pub slot: u64,
@@ -639,7 +3845,40 @@ pub struct TransactionResult {
     pub fee: Option<u64>,
 }
 
+impl From<&ClickHouseTransaction> for TransactionResult {
+    fn from(tx: &ClickHouseTransaction) -> Self {
+        Self {
+            signature: tx.signature.clone(),
+            slot: tx.slot,
+            timestamp: DateTime::from_timestamp_millis(tx.timestamp).unwrap_or_else(Utc::now),
+            success: tx.success,
+            fee: tx.fee,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct LamportDataPoint {
+    /// Start of the time bucket, unix seconds
+    pub timestamp: i64,
+    pub lamports: u64,
+    /// Change from the previous bucket with a write
+    pub delta: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct AccountSnapshot {
+    pub pubkey: String,
+    pub lamports: u64,
+    pub timestamp: DateTime<Utc>,
+    pub write_version: u64,
+    pub txn_signature: Option<String>,
+}
+
 #[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
 pub struct FeeStats {
     pub min: Option<u64>,
     pub max: Option<u64>,
@@ -649,14 +3888,110 @@ pub struct FeeStats {
     pub transaction_count: u64,
 }
 
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct FeeStatsByType {
+    pub vote: FeeStats,
+    pub non_vote: FeeStats,
+}
+
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct ComputeUnitsStats {
+    pub min: u64,
+    pub max: u64,
+    pub average: f64,
+    pub median: u64,
+    pub p95: u64,
+    pub total: u64,
+    pub transaction_count: u64,
+}
+
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct FeePercentiles {
+    pub p25: u64,
+    pub p50: u64,
+    pub p75: u64,
+    pub p95: u64,
+    pub p99: u64,
+}
+
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct PriorityFeeStats {
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+    pub average: Option<f64>,
+    pub p95: Option<u64>,
+    /// Transactions that set a compute unit price
+    pub transaction_count: u64,
+    pub base_fees: FeeStats,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SuccessRateDataPoint {
+    pub timestamp: i64,
+    /// Percentage, between 0 and 100
+    pub success_rate: f64,
+    pub total_count: u64,
+    pub failed_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct RetryPattern {
+    pub time_bucket: i64,
+    pub failure_count: u64,
+    pub next_bucket_tx_count: u64,
+    /// `failure_count / next_bucket_tx_count`
+    pub estimated_retry_ratio: f64,
+}
+
+/// Transactions paying `cu_price_bucket_start..=cu_price_bucket_end` micro-lamports
+/// per compute unit
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CuPriceSuccessBucket {
+    pub cu_price_bucket_start: u64,
+    pub cu_price_bucket_end: u64,
+    pub tx_count: u64,
+    /// Percentage of successful transactions
+    pub success_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct FeeSpikeEvent {
+    pub bucket_start: i64,
+    pub avg_fee: f64,
+    /// Average fee of the previous 5 buckets
+    pub baseline_avg_fee: f64,
+    pub spike_ratio: f64,
+    pub tx_count: u64,
+}
+
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct TpsDataPoint {
     pub timestamp: i64,
     pub tps: f64,
     pub transaction_count: u64,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DexTpsDataPoint {
+    /// Empty for transactions not attributed to a DEX
+    pub dex: String,
+    pub timestamp: i64,
+    pub tps: f64,
+    pub transaction_count: u64,
+}
+
 #[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
 pub struct SlotStats {
     pub min_slot: u64,
     pub max_slot: u64,
@@ -665,7 +4000,105 @@ pub struct SlotStats {
     pub avg_tx_per_slot: f64,
 }
 
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct DailySummary {
+    /// `YYYY-MM-DD`, UTC
+    pub date: String,
+    pub total_txs: u64,
+    /// Percentage of successful transactions
+    pub success_rate: f64,
+    pub avg_fee: f64,
+    pub total_fees: u64,
+    pub avg_tps: f64,
+    pub unique_traders: u64,
+    /// DEX with the most transactions, empty if none
+    pub top_dex: String,
+}
+
+/// Slots with `bucket_start..=bucket_end` transactions
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SlotDensityBucket {
+    pub bucket_start: u64,
+    pub bucket_end: u64,
+    pub slot_count: u64,
+    pub total_txs: u64,
+    pub avg_txs_per_slot: f64,
+}
+
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct CpiDepthStats {
+    pub max_depth: u8,
+    /// Average of the deepest CPI of each transaction
+    pub avg_depth: f64,
+    pub tx_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct InstructionCountBucket {
+    pub instruction_count: u64,
+    pub tx_count: u64,
+    /// Percentage, between 0 and 100
+    pub success_rate: f64,
+}
+
+/// Time between consecutive slots, Solana targets 400 ms
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct BlockTimeStats {
+    pub min_ms: f64,
+    pub max_ms: f64,
+    pub avg_ms: f64,
+    pub p95_ms: f64,
+    pub slot_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct BlockFillRate {
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub total_slots: u64,
+    pub slots_with_transactions: u64,
+    /// Between 0 and 1
+    pub fill_rate: f64,
+}
+
+/// Activity in one hour of the week, cells without transactions are omitted
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct HeatmapCell {
+    /// 1 = Monday ... 7 = Sunday
+    pub day_of_week: u8,
+    /// 0-23, UTC
+    pub hour_of_day: u8,
+    pub tx_count: u64,
+    pub avg_fee: f64,
+}
+
+/// An inclusive range of consecutive missing slots
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SlotGap {
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub gap_size: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct AccountWriteFreq {
+    pub pubkey: String,
+    pub write_count: u64,
+    /// Writes per slot the account was written in
+    pub avg_writes_per_slot: f64,
+}
+
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct VolumeStats {
     pub total_volume: u64,
     pub transaction_count: u64,
@@ -674,6 +4107,7 @@ pub struct VolumeStats {
 }
 
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct VolumeDataPoint {
     pub timestamp: DateTime<Utc>,
     pub volume: u64,
@@ -681,6 +4115,7 @@ pub struct VolumeDataPoint {
 }
 
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct TokenPairStats {
     pub token_a: String,
     pub token_b: String,
@@ -689,6 +4124,31 @@ pub struct TokenPairStats {
 }
 
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct TokenMintStats {
+    pub mint: String,
+    pub display_name: Option<String>,
+    pub total_volume: u64,
+    pub swap_count: u64,
+    pub unique_traders: u64,
+    pub avg_price_usd: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct TokenVolumeStats {
+    pub mint: String,
+    /// Raw amount swapped into pools (sold)
+    pub total_in_volume: u64,
+    /// Raw amount swapped out of pools (bought)
+    pub total_out_volume: u64,
+    /// `total_in_volume - total_out_volume`, positive when the token was mostly sold
+    pub net_flow: i64,
+    pub swap_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct PairStats {
     pub token_a: String,
     pub token_b: String,
@@ -698,6 +4158,62 @@ pub struct PairStats {
 }
 
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ProgramFirstSeen {
+    pub program_id: String,
+    pub first_slot: u64,
+    pub first_seen: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ProgramUsageStat {
+    pub program_id: String,
+    pub invocation_count: u64,
+    pub unique_transactions: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct InstructionTypeStat {
+    /// Hex encoded, e.g. `f8c69e91e17587c8` for Anchor's `swap`
+    pub discriminator: String,
+    pub count: u64,
+    pub dex: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct LogSwapEvent {
+    pub signature: String,
+    pub slot: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Program that logged the swap
+    pub program_id: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SolFlow {
+    pub program_id: String,
+    /// Negative when more lamports left the program's accounts than entered them
+    pub net_inflow_lamports: i64,
+    pub tx_count: u64,
+    pub period_start: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DexRevertRate {
+    pub dex: String,
+    pub total_txs: u64,
+    pub failed_txs: u64,
+    /// Percentage, between 0 and 100
+    pub revert_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct DexStats {
     pub dex: String,
     pub transaction_count: u64,
@@ -707,13 +4223,55 @@ pub struct DexStats {
 }
 
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ArbitrageDataPoint {
+    pub timestamp: i64,
+    pub arbitrage_count: u64,
+    pub total_count: u64,
+    /// Percentage of all transactions, between 0 and 100
+    pub arbitrage_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SandwichCandidate {
+    pub slot: u64,
+    /// Fee payer of the front and back run
+    pub attacker: String,
+    pub victim_signature: String,
+    pub front_run_sig: String,
+    pub back_run_sig: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct UniqueTraderDataPoint {
+    pub timestamp: i64,
+    pub unique_traders: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct TraderStats {
     pub address: String,
     pub tx_count: u64,
     pub total_volume: u64,
 }
 
+/// See `QueryService::get_trader_pnl_estimates` for how rough these numbers are
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct TraderPnl {
+    pub address: String,
+    pub estimated_pnl_usd: f64,
+    /// Swaps with a price for both sides
+    pub total_swaps: u64,
+    /// Percentage of the swaps, between 0 and 100
+    pub win_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct TraderActivity {
     pub address: String,
     pub tx_count: u64,
@@ -723,6 +4281,16 @@ pub struct TraderActivity {
 }
 
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct ErrorTimeseriesPoint {
+    pub timestamp: i64,
+    /// `unknown` for failures stored without a message
+    pub error_message: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub struct ErrorAnalysis {
     pub total_failed: u64,
     pub common_error_patterns: Vec<(String, u64)>,
@@ -0,0 +1,130 @@
+use ingest::types::TransactionInstruction;
+
+const JUPITER_V6: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+const ORCA_WHIRLPOOL: &str = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc";
+const RAYDIUM_AMM_V4: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+const RAYDIUM_CLMM: &str = "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK";
+const RAYDIUM_CPMM: &str = "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C";
+const METEORA_DLMM: &str = "LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo";
+const METEORA_POOLS: &str = "Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB";
+
+/// Known DEX programs: (program id, dex)
+pub const DEX_PROGRAMS: &[(&str, &str)] = &[
+    (JUPITER_V6, "jupiter"),
+    (RAYDIUM_AMM_V4, "raydium"),
+    (RAYDIUM_CLMM, "raydium"),
+    (RAYDIUM_CPMM, "raydium"),
+    (ORCA_WHIRLPOOL, "orca"),
+    (METEORA_DLMM, "meteora"),
+    (METEORA_POOLS, "meteora"),
+];
+
+/// Anchor discriminator of Jupiter v6 `shared_accounts_route`
+const JUPITER_SHARED_ACCOUNTS_ROUTE: [u8; 8] = [193, 32, 155, 51, 65, 214, 156, 129];
+/// Anchor discriminator of Orca Whirlpool `swap_v2`
+const WHIRLPOOL_SWAP_V2: [u8; 8] = [43, 4, 237, 11, 26, 201, 30, 98];
+
+type SwapDecoder = fn(&TransactionInstruction, &[u8]) -> Option<SwapLeg>;
+
+/// Swap instructions we can decode: (program id, dex, discriminator, decoder).
+///
+/// Only instructions that carry both mints in their account list are decoded,
+/// other layouts (Raydium v4, Meteora DAMM v2, Jupiter `route`) would need the
+/// transaction's token balances to resolve the traded mints.
+const SWAP_DECODERS: &[(&str, &str, [u8; 8], SwapDecoder)] = &[
+    (
+        JUPITER_V6,
+        "jupiter",
+        JUPITER_SHARED_ACCOUNTS_ROUTE,
+        decode_jupiter_shared_accounts_route,
+    ),
+    (
+        ORCA_WHIRLPOOL,
+        "orca",
+        WHIRLPOOL_SWAP_V2,
+        decode_whirlpool_swap_v2,
+    ),
+];
+
+/// A swap decoded from a single instruction. Amounts are the ones specified in
+/// the instruction (input amount and quoted/threshold output amount), not the
+/// amounts that were actually settled.
+#[derive(Debug, Clone)]
+pub struct SwapLeg {
+    pub trader: String,
+    pub token_in: String,
+    pub token_out: String,
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+/// Decode `instruction` if it is a known DEX swap, returning the DEX name and the swap.
+/// `data` is the base64-decoded instruction data.
+pub fn decode_swap(
+    instruction: &TransactionInstruction,
+    data: &[u8],
+) -> Option<(&'static str, SwapLeg)> {
+    let (_, dex, _, decode) = SWAP_DECODERS
+        .iter()
+        .find(|(program_id, _, discriminator, _)| {
+            *program_id == instruction.program_id && data.starts_with(discriminator)
+        })?;
+
+    Some((*dex, decode(instruction, data)?))
+}
+
+/// Accounts: user_transfer_authority at 2, source_mint at 7, destination_mint at 8.
+/// Data ends with in_amount u64, quoted_out_amount u64, slippage_bps u16, platform_fee_bps u8.
+fn decode_jupiter_shared_accounts_route(
+    instruction: &TransactionInstruction,
+    data: &[u8],
+) -> Option<SwapLeg> {
+    if data.len() < 8 + 19 {
+        return None;
+    }
+    let tail = data.len() - 19;
+
+    Some(SwapLeg {
+        trader: instruction.accounts.get(2)?.clone(),
+        token_in: instruction.accounts.get(7)?.clone(),
+        token_out: instruction.accounts.get(8)?.clone(),
+        amount_in: read_u64(data, tail)?,
+        amount_out: read_u64(data, tail + 8)?,
+    })
+}
+
+/// Accounts: token_authority at 3, token_mint_a at 5, token_mint_b at 6.
+/// Data: amount u64, other_amount_threshold u64, sqrt_price_limit u128,
+/// amount_specified_is_input bool, a_to_b bool.
+fn decode_whirlpool_swap_v2(instruction: &TransactionInstruction, data: &[u8]) -> Option<SwapLeg> {
+    let amount = read_u64(data, 8)?;
+    let other_amount_threshold = read_u64(data, 16)?;
+    let amount_specified_is_input = *data.get(40)? != 0;
+    let a_to_b = *data.get(41)? != 0;
+
+    let mint_a = instruction.accounts.get(5)?.clone();
+    let mint_b = instruction.accounts.get(6)?.clone();
+    let (token_in, token_out) = if a_to_b {
+        (mint_a, mint_b)
+    } else {
+        (mint_b, mint_a)
+    };
+    let (amount_in, amount_out) = if amount_specified_is_input {
+        (amount, other_amount_threshold)
+    } else {
+        (other_amount_threshold, amount)
+    };
+
+    Some(SwapLeg {
+        trader: instruction.accounts.get(3)?.clone(),
+        token_in,
+        token_out,
+        amount_in,
+        amount_out,
+    })
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
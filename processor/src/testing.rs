@@ -0,0 +1,136 @@
+//! Throwaway ClickHouse server and synthetic rows for the tests and benchmarks.
+//! Enabled by the `test-utils` feature, the server needs Docker.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use ingest::types::JITO_TIP_ACCOUNTS;
+use testcontainers::{
+    ContainerAsync, GenericImage, ImageExt,
+    core::{IntoContainerPort, WaitFor},
+    runners::AsyncRunner,
+};
+
+use crate::{ClickhouseClient, clickhouse_types::ClickHouseTransaction};
+
+const USER: &str = "test";
+const PASSWORD: &str = "test";
+const DATABASE: &str = "test";
+
+/// Start a ClickHouse container, returning it with its HTTP URL. The server is removed
+/// when the container is dropped.
+pub async fn start_clickhouse() -> (ContainerAsync<GenericImage>, String) {
+    let container = GenericImage::new("clickhouse/clickhouse-server", "24.8")
+        .with_exposed_port(8123.tcp())
+        .with_wait_for(WaitFor::message_on_stderr("Ready for connections"))
+        .with_env_var("CLICKHOUSE_USER", USER)
+        .with_env_var("CLICKHOUSE_PASSWORD", PASSWORD)
+        .with_env_var("CLICKHOUSE_DB", DATABASE)
+        .start()
+        .await
+        .expect("failed to start ClickHouse container");
+
+    let port = container
+        .get_host_port_ipv4(8123.tcp())
+        .await
+        .expect("ClickHouse port is not mapped");
+
+    (container, format!("http://127.0.0.1:{}", port))
+}
+
+/// Connect to the server started by `start_clickhouse` and create the tables
+pub async fn connect(url: &str, compression: bool) -> ClickhouseClient {
+    // The server may log readiness slightly before it accepts queries
+    for _ in 0..30 {
+        let client = ClickhouseClient::builder()
+            .url(url)
+            .user(USER)
+            .password(PASSWORD)
+            .database(DATABASE)
+            .compression(compression)
+            .build()
+            .await;
+        if let Ok(client) = client {
+            return client;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    panic!("ClickHouse at {} did not become ready", url);
+}
+
+/// Logs of a typical Jupiter swap, so compressed inserts carry realistic JSON
+const SWAP_LOGS: &[&str] = &[
+    "Program ComputeBudget111111111111111111111111111111 invoke [1]",
+    "Program ComputeBudget111111111111111111111111111111 success",
+    "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 invoke [1]",
+    "Program log: Instruction: Route",
+    "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc invoke [2]",
+    "Program log: Instruction: Swap",
+    "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [3]",
+    "Program log: Instruction: Transfer",
+    "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA consumed 4645 of 180517 compute units",
+    "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success",
+    "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc consumed 41250 of 210371 compute units",
+    "Program whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc success",
+    "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 consumed 75890 of 250000 compute units",
+    "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 success",
+];
+
+/// `count` synthetic transactions with distinct signatures starting at `offset`, with little
+/// JSON in them
+pub fn transactions(count: usize, offset: usize) -> Vec<ClickHouseTransaction> {
+    let now = Utc::now().timestamp_millis();
+
+    (offset..offset + count)
+        .map(|i| ClickHouseTransaction {
+            signature: format!("{:0>88}", i),
+            slot: 300_000_000 + (i / 1000) as u64,
+            is_vote: false,
+            tx_index: (i % 1000) as u64,
+            success: i % 10 != 0,
+            error_message: None,
+            fee: Some(5000 + (i % 100) as u64),
+            compute_units_consumed: Some(150_000),
+            // Spread over the last two days so the 24h filter matches about half
+            timestamp: now - (i % 172_800) as i64 * 1000,
+            pre_balances: "[1000000000,0,0]".to_string(),
+            post_balances: "[999995000,0,0]".to_string(),
+            log_messages: "[]".to_string(),
+            account_keys: "[]".to_string(),
+            instructions: "[]".to_string(),
+            inner_instructions: "[]".to_string(),
+            dex: "jupiter".to_string(),
+            priority_fee_microlamports: None,
+            write_version: 0,
+            program_ids: vec!["JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4".to_string()],
+            token_balance_changes: "[]".to_string(),
+            // Every 20th transaction tips Jito
+            account_keys_array: (i % 20 == 0)
+                .then(|| JITO_TIP_ACCOUNTS[i % 8].to_string())
+                .into_iter()
+                .collect(),
+            fee_payer: String::new(),
+            net_balance_change: -5000,
+            is_jito_bundle: i % 20 == 0,
+            parsed_logs: "[]".to_string(),
+            balance_deltas: vec![-5000, 0, 0],
+            is_versioned: false,
+            is_arbitrage: false,
+            max_cpi_depth: 0,
+        })
+        .collect()
+}
+
+/// Like `transactions`, but every row carries the logs of a swap: JSON heavy rows, where
+/// compression matters most
+pub fn swap_transactions(count: usize, offset: usize) -> Vec<ClickHouseTransaction> {
+    let log_messages = serde_json::to_string(SWAP_LOGS).unwrap();
+    transactions(count, offset)
+        .into_iter()
+        .map(|tx| ClickHouseTransaction {
+            log_messages: log_messages.clone(),
+            ..tx
+        })
+        .collect()
+}
@@ -1,8 +1,37 @@
-use anyhow::{Ok, Result};
+use anyhow::{Ok, Result, bail};
+use base64::{Engine as _, engine::general_purpose};
 use chrono::Utc;
-use ingest::types::{SolanaAccount, SolanaTransaction};
+use ingest::types::{SolanaAccount, SolanaBlock, SolanaSlot, SolanaTransaction};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
 
-use crate::clickhouse_types::{ClickHouseAccount, ClickHouseSlot, ClickHouseTransaction};
+use crate::{
+    clickhouse_types::{
+        ClickHouseAccount, ClickHouseBlock, ClickHouseSlot, ClickHouseSwapEvent,
+        ClickHouseTransaction,
+    },
+    decoders, swaps,
+};
+
+const COMPUTE_BUDGET_PROGRAM: &str = "ComputeBudget111111111111111111111111111111";
+/// Instruction tag of `ComputeBudgetInstruction::SetComputeUnitPrice`
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+/// Wrapped SOL, swaps of SOL go through a wSOL token account
+const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+/// Fees above this many lamports are unusual enough to be logged, see
+/// `Transformer::exceeds_fee_bound`
+const MAX_FEE: u64 = 10_000_000;
+
+/// An entry of a transaction's program logs, see `Transformer::parse_log_messages`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParsedLogEvent {
+    /// Program executing when the line was logged, empty if the logs were truncated
+    pub program_id: String,
+    /// `invoke`, `log` or `data`
+    pub event_type: String,
+    /// Log text or base64 encoded data, `None` for invocations
+    pub data: Option<String>,
+}
 
 pub struct Transformer;
 
@@ -18,9 +47,18 @@ impl Transformer {
             write_version: account.write_version,
             txn_signature: account.txn_signature.clone(),
             timestamp: Utc::now().timestamp_millis(),
+            slot: account.slot,
+            decoded_data: Self::decode_account_data(account),
         })
     }
 
+    /// JSON of the account data if its owner has a decoder, see `decoders::ACCOUNT_DECODERS`
+    pub fn decode_account_data(account: &SolanaAccount) -> Option<String> {
+        let data = general_purpose::STANDARD.decode(&account.data).ok()?;
+        decoders::decode_account(&account.owner, &data).map(|decoded| decoded.to_string())
+    }
+
+    #[instrument(skip_all, fields(signature = %tx.signature, slot = tx.slot))]
     pub fn transform_transaction(tx: &SolanaTransaction) -> Result<ClickHouseTransaction> {
         Ok(ClickHouseTransaction {
             signature: tx.signature.clone(),
@@ -28,6 +66,7 @@ impl Transformer {
             is_vote: tx.is_vote,
             tx_index: tx.index,
             success: tx.success,
+            error_message: tx.error_message.clone(),
             fee: tx.fee,
             compute_units_consumed: tx.compute_units_consumed,
             timestamp: Utc::now().timestamp_millis(),
@@ -36,13 +75,267 @@ impl Transformer {
             log_messages: serde_json::to_string(&tx.log_messages)?,
             account_keys: serde_json::to_string(&tx.account_keys)?,
             instructions: serde_json::to_string(&tx.instructions)?,
+            inner_instructions: serde_json::to_string(&tx.inner_instructions)?,
+            dex: Self::detect_dex(tx).to_string(),
+            priority_fee_microlamports: Self::extract_priority_fee(tx),
+            write_version: 0,
+            program_ids: Self::program_ids(tx),
+            token_balance_changes: serde_json::to_string(&tx.token_balance_changes)?,
+            account_keys_array: tx.account_keys.clone(),
+            fee_payer: Self::extract_fee_payer(tx),
+            net_balance_change: Self::net_balance_change(tx),
+            is_jito_bundle: tx.is_jito_bundle,
+            parsed_logs: serde_json::to_string(&Self::parse_log_messages(&tx.log_messages))?,
+            balance_deltas: Self::balance_deltas(tx),
+            is_versioned: tx.is_versioned,
+            is_arbitrage: Self::detect_arbitrage(tx),
+            max_cpi_depth: Self::max_cpi_depth(tx),
         })
     }
 
-    pub fn transform_slot(slot: u64) -> ClickHouseSlot {
+    /// Extract program invocations, `Program log:` lines and `Program data:` payloads
+    /// from the log messages. Other lines (`consumed`, `success`, ...) are skipped.
+    pub fn parse_log_messages(log_messages: &[String]) -> Vec<ParsedLogEvent> {
+        let mut events = Vec::new();
+        // Programs currently executing, the innermost CPI last
+        let mut call_stack: Vec<&str> = Vec::new();
+
+        for line in log_messages {
+            let current_program = call_stack.last().copied().unwrap_or_default().to_string();
+
+            if let Some(text) = line.strip_prefix("Program log: ") {
+                events.push(ParsedLogEvent {
+                    program_id: current_program,
+                    event_type: "log".to_string(),
+                    data: Some(text.to_string()),
+                });
+            } else if let Some(data) = line.strip_prefix("Program data: ") {
+                events.push(ParsedLogEvent {
+                    program_id: current_program,
+                    event_type: "data".to_string(),
+                    data: Some(data.to_string()),
+                });
+            } else if let Some(rest) = line.strip_prefix("Program ") {
+                let mut words = rest.split_whitespace();
+                match (words.next(), words.next()) {
+                    (Some(program_id), Some("invoke")) => {
+                        call_stack.push(program_id);
+                        events.push(ParsedLogEvent {
+                            program_id: program_id.to_string(),
+                            event_type: "invoke".to_string(),
+                            data: None,
+                        });
+                    }
+                    (Some(_), Some("success" | "failed:")) => {
+                        call_stack.pop();
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        events
+    }
+
+    /// Reject transactions that can't have come from a well-formed update: a signature
+    /// that isn't a base58 encoded 64 byte signature (87 or 88 characters, rarely fewer),
+    /// slot 0 or balances that don't line up with the account keys
+    pub fn validate_transaction(tx: &SolanaTransaction) -> Result<()> {
+        let signature_len = bs58::decode(&tx.signature)
+            .into_vec()
+            .map(|bytes| bytes.len())
+            .unwrap_or(0);
+        if signature_len != 64 {
+            bail!("Invalid signature {:?}", tx.signature);
+        }
+        if tx.slot == 0 {
+            bail!("Slot is 0");
+        }
+        if tx.pre_balances.len() != tx.account_keys.len() {
+            bail!(
+                "{} pre balances for {} account keys",
+                tx.pre_balances.len(),
+                tx.account_keys.len()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Whether the fee is above `MAX_FEE`. Priority fees can legitimately get this high
+    /// during congestion, so such transactions are only counted and logged.
+    pub fn exceeds_fee_bound(tx: &SolanaTransaction) -> bool {
+        tx.fee.is_some_and(|fee| fee > MAX_FEE)
+    }
+
+    /// Account that paid the transaction fee, the first account key
+    pub fn extract_fee_payer(tx: &SolanaTransaction) -> String {
+        if tx.fee_payer.is_empty() {
+            tx.account_keys.first().cloned().unwrap_or_default()
+        } else {
+            tx.fee_payer.clone()
+        }
+    }
+
+    /// Lamport change of the fee payer, clamped to the `i64` range
+    pub fn net_balance_change(tx: &SolanaTransaction) -> i64 {
+        let pre = tx.pre_balances.first().copied().unwrap_or(0) as i128;
+        let post = tx.post_balances.first().copied().unwrap_or(0) as i128;
+
+        (post - pre).clamp(i64::MIN as i128, i64::MAX as i128) as i64
+    }
+
+    /// Lamport change of every account, in `account_keys` order, clamped to the `i64` range
+    pub fn balance_deltas(tx: &SolanaTransaction) -> Vec<i64> {
+        tx.pre_balances
+            .iter()
+            .zip(&tx.post_balances)
+            .map(|(&pre, &post)| {
+                (post as i128 - pre as i128).clamp(i64::MIN as i128, i64::MAX as i128) as i64
+            })
+            .collect()
+    }
+
+    /// Depth of the deepest inner instruction, 0 if the transaction made no CPI
+    pub fn max_cpi_depth(tx: &SolanaTransaction) -> u8 {
+        tx.inner_instructions
+            .iter()
+            .map(|instruction| instruction.depth)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Unique program ids of the top level instructions, in invocation order
+    pub fn program_ids(tx: &SolanaTransaction) -> Vec<String> {
+        let mut program_ids: Vec<String> = Vec::new();
+
+        for instruction in &tx.instructions {
+            if !program_ids.contains(&instruction.program_id) {
+                program_ids.push(instruction.program_id.clone());
+            }
+        }
+
+        program_ids
+    }
+
+    /// Compute unit price in micro-lamports set by a `SetComputeUnitPrice` instruction, if any
+    pub fn extract_priority_fee(tx: &SolanaTransaction) -> Option<u64> {
+        tx.instructions
+            .iter()
+            .filter(|instruction| instruction.program_id == COMPUTE_BUDGET_PROGRAM)
+            .find_map(|instruction| {
+                let data = general_purpose::STANDARD.decode(&instruction.data).ok()?;
+
+                match data.split_first() {
+                    Some((&SET_COMPUTE_UNIT_PRICE, price)) => {
+                        Some(u64::from_le_bytes(price.get(..8)?.try_into().ok()?))
+                    }
+                    _ => None,
+                }
+            })
+    }
+
+    /// Name of the DEX the transaction was sent to, empty if it is not a DEX transaction.
+    ///
+    /// Looks at the first instruction, skipping compute budget instructions which wallets
+    /// put in front of the actual program call.
+    pub fn detect_dex(tx: &SolanaTransaction) -> &'static str {
+        tx.instructions
+            .iter()
+            .find(|instruction| instruction.program_id != COMPUTE_BUDGET_PROGRAM)
+            .and_then(|instruction| {
+                swaps::DEX_PROGRAMS
+                    .iter()
+                    .find(|(program_id, _)| *program_id == instruction.program_id)
+            })
+            .map(|(_, dex)| *dex)
+            .unwrap_or_default()
+    }
+
+    /// Whether the transaction looks like an arbitrage, a swap route leaving and
+    /// re-entering the same token (A → B → A) within the transaction.
+    ///
+    /// Every hop moves a mint into a pool and another out of one, so the intermediate
+    /// mints of any multi-hop route have both inflows and outflows. Only a mint the fee
+    /// payer gained is a round trip: A → B → C leaves the fee payer with more C, which no
+    /// pool received. SOL usually goes through a wSOL account closed within the
+    /// transaction, so a wSOL round trip counts when the fee payer's lamports didn't
+    /// decrease by more than the fee. Arbitrages paid by a relayer, or tipping from the
+    /// fee payer's lamports, are missed.
+    pub fn detect_arbitrage(tx: &SolanaTransaction) -> bool {
+        let fee_payer = Self::extract_fee_payer(tx);
+        let (payer_changes, route_changes): (Vec<_>, Vec<_>) = tx
+            .token_balance_changes
+            .iter()
+            .partition(|change| change.owner == fee_payer);
+
+        if payer_changes.iter().any(|change| change.delta < 0.0) {
+            return false;
+        }
+
+        let fee = i64::try_from(tx.fee.unwrap_or(0)).unwrap_or(i64::MAX);
+        let kept_lamports = Self::net_balance_change(tx).saturating_add(fee) >= 0;
+        let payer_gained = |mint: &str| {
+            payer_changes
+                .iter()
+                .any(|change| change.mint == mint && change.delta > 0.0)
+                || (mint == WSOL_MINT && kept_lamports)
+        };
+
+        route_changes.iter().any(|inflow| {
+            inflow.delta > 0.0
+                && payer_gained(&inflow.mint)
+                && route_changes
+                    .iter()
+                    .any(|outflow| outflow.delta < 0.0 && outflow.mint == inflow.mint)
+        })
+    }
+
+    pub fn transform_slot(slot: &SolanaSlot) -> ClickHouseSlot {
         ClickHouseSlot {
-            slot,
+            slot: slot.slot,
+            timestamp: slot.timestamp.timestamp_millis(),
+        }
+    }
+
+    pub fn transform_block(block: &SolanaBlock) -> ClickHouseBlock {
+        ClickHouseBlock {
+            slot: block.slot,
+            blockhash: block.blockhash.clone(),
+            parent_slot: block.parent_slot,
+            block_time: block.block_time,
+            block_height: block.block_height,
             timestamp: Utc::now().timestamp_millis(),
         }
     }
+
+    /// Extract swap events from the known DEX swap instructions of a successful transaction
+    pub fn transform_swap_events(tx: &SolanaTransaction) -> Vec<ClickHouseSwapEvent> {
+        if !tx.success {
+            return Vec::new();
+        }
+
+        let timestamp = Utc::now().timestamp_millis();
+
+        tx.instructions
+            .iter()
+            .filter_map(|instruction| {
+                let data = general_purpose::STANDARD.decode(&instruction.data).ok()?;
+                let (dex, swap) = swaps::decode_swap(instruction, &data)?;
+
+                Some(ClickHouseSwapEvent {
+                    signature: tx.signature.clone(),
+                    slot: tx.slot,
+                    timestamp,
+                    dex: dex.to_string(),
+                    token_in: swap.token_in,
+                    token_out: swap.token_out,
+                    amount_in: swap.amount_in,
+                    amount_out: swap.amount_out,
+                    trader: swap.trader,
+                    price_usd: None,
+                })
+            })
+            .collect()
+    }
 }
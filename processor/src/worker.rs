@@ -1,20 +1,245 @@
-use anyhow::Result;
-use ingest::types::IndexEvent;
-use std::time::{self, Duration};
-use tracing::{error, info};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use clickhouse::inserter::{Inserter, Quantities};
+use ingest::{checkpoint::CheckpointStore, types::IndexEvent};
+use std::{
+    env,
+    path::PathBuf,
+    time::{self, Duration},
+};
+use tokio::sync::{broadcast, mpsc, watch};
+use tracing::{Span, error, field, info, instrument, warn};
 
 use crate::{
-    clickhouse::ClickhouseClient,
-    clickhouse_types::{ClickHouseAccount, ClickHouseSlot, ClickHouseTransaction},
+    clickhouse::{ClickhouseClient, RETENTION_TABLES},
+    clickhouse_types::{
+        ClickHouseAccount, ClickHouseBlock, ClickHouseSlot, ClickHouseSwapEvent,
+        ClickHouseTransaction,
+    },
+    dead_letter::DeadLetterQueue,
+    dedup::SignatureCache,
+    metrics,
     transformer::Transformer,
 };
 
+/// Number of recent signatures kept for deduplicating replayed transactions
+const MAX_SEEN_SIGNATURES: usize = 200_000;
+
+/// Account buffer usage (in percent of `max_account_buffer_size`) logged as a warning
+const ACCOUNT_BUFFER_WARN_PERCENT: usize = 80;
+
+/// Batching settings of the `Processor`
+#[derive(Debug, Clone)]
+pub struct ProcessorConfig {
+    /// Rows buffered per table before it is flushed
+    pub batch_size: usize,
+    /// Interval of the periodic `flush_all`
+    pub flush_interval: Duration,
+    /// Rows buffered across all tables before a `flush_all` is forced, guards against
+    /// buffers growing without bound while inserts keep failing. If the forced flush
+    /// fails, every further event waits `flush_interval`.
+    pub max_buffer_size: usize,
+    /// Accounts buffered before they are flushed right away, at most `max_buffer_size`.
+    /// If that flush fails too, every further account waits `flush_interval`, slowing the
+    /// stream down instead of buffering account bursts without bound while ClickHouse is
+    /// unavailable.
+    pub max_account_buffer_size: usize,
+    /// Dead letter file, `./dead_letter.jsonl` if unset
+    pub dead_letter_path: Option<PathBuf>,
+    /// Days of data kept in ClickHouse, set as a TTL on startup. Unset leaves the
+    /// tables' TTL as it is.
+    pub data_retention_days: Option<u32>,
+    /// LZ4 compress the inserts sent to ClickHouse
+    pub compression: bool,
+}
+
+impl Default for ProcessorConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 1000,
+            flush_interval: Duration::from_secs(5),
+            max_buffer_size: 100_000,
+            max_account_buffer_size: 50_000,
+            dead_letter_path: None,
+            data_retention_days: None,
+            compression: true,
+        }
+    }
+}
+
+impl ProcessorConfig {
+    /// Read `PROCESSOR_BATCH_SIZE`, `PROCESSOR_FLUSH_INTERVAL_MS`, `PROCESSOR_MAX_BUFFER_SIZE`
+    /// and `PROCESSOR_MAX_ACCOUNT_BUFFER_SIZE`, falling back to the defaults for unset
+    /// variables
+    pub fn from_env() -> Result<Self> {
+        let defaults = Self::default();
+
+        let batch_size = match env::var("PROCESSOR_BATCH_SIZE") {
+            Ok(size) => size
+                .parse()
+                .ok()
+                .filter(|size| *size > 0)
+                .context("PROCESSOR_BATCH_SIZE must be a positive integer")?,
+            Err(_) => defaults.batch_size,
+        };
+
+        let flush_interval = match env::var("PROCESSOR_FLUSH_INTERVAL_MS") {
+            Ok(millis) => millis
+                .parse()
+                .ok()
+                .filter(|millis| *millis > 0)
+                .map(Duration::from_millis)
+                .context("PROCESSOR_FLUSH_INTERVAL_MS must be a positive integer")?,
+            Err(_) => defaults.flush_interval,
+        };
+
+        let max_buffer_size = match env::var("PROCESSOR_MAX_BUFFER_SIZE") {
+            Ok(size) => size
+                .parse()
+                .ok()
+                .filter(|size| *size >= batch_size)
+                .context("PROCESSOR_MAX_BUFFER_SIZE must be an integer >= the batch size")?,
+            Err(_) => defaults.max_buffer_size.max(batch_size),
+        };
+
+        let max_account_buffer_size = match env::var("PROCESSOR_MAX_ACCOUNT_BUFFER_SIZE") {
+            Ok(size) => size
+                .parse()
+                .ok()
+                .filter(|size| (batch_size..=max_buffer_size).contains(size))
+                .context(
+                    "PROCESSOR_MAX_ACCOUNT_BUFFER_SIZE must be an integer between the batch \
+                     size and PROCESSOR_MAX_BUFFER_SIZE",
+                )?,
+            Err(_) => defaults
+                .max_account_buffer_size
+                .clamp(batch_size, max_buffer_size),
+        };
+
+        Ok(Self {
+            batch_size,
+            flush_interval,
+            max_buffer_size,
+            max_account_buffer_size,
+            dead_letter_path: None,
+            data_retention_days: None,
+            compression: defaults.compression,
+        })
+    }
+}
+
+/// Builds a `Processor` from a `ProcessorConfig`, see `Processor::with_config`
+pub struct ProcessorBuilder {
+    config: ProcessorConfig,
+    checkpoint: Option<CheckpointStore>,
+    dead_letter: Option<DeadLetterQueue>,
+    tx_stream: Option<broadcast::Sender<ClickHouseTransaction>>,
+}
+
+impl ProcessorBuilder {
+    /// Save the last flushed slot to `checkpoint` after every successful `flush_all`
+    pub fn with_checkpoint(mut self, checkpoint: CheckpointStore) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    /// Write failed transaction batches to `dead_letter`, takes precedence over
+    /// `ProcessorConfig::dead_letter_path`
+    pub fn with_dead_letter(mut self, dead_letter: DeadLetterQueue) -> Self {
+        self.dead_letter = Some(dead_letter);
+        self
+    }
+
+    /// Send every transaction to `tx_stream` once it is inserted
+    pub fn with_transaction_stream(
+        mut self,
+        tx_stream: broadcast::Sender<ClickHouseTransaction>,
+    ) -> Self {
+        self.tx_stream = Some(tx_stream);
+        self
+    }
+
+    /// Connect to ClickHouse and create the processor
+    pub async fn build(
+        self,
+        clickhouse_url: &str,
+        clickhouse_user: &str,
+        clickhouse_password: &str,
+        clickhouse_db: &str,
+    ) -> Result<Processor> {
+        let clickhouse = ClickhouseClient::builder()
+            .url(clickhouse_url)
+            .user(clickhouse_user)
+            .password(clickhouse_password)
+            .database(clickhouse_db)
+            .compression(self.config.compression)
+            .build()
+            .await?;
+
+        if let Some(days) = self.config.data_retention_days {
+            for table in RETENTION_TABLES {
+                clickhouse.set_ttl(table, days).await?;
+            }
+        }
+
+        self.build_with_client(clickhouse)
+    }
+
+    /// Create the processor on an already set up client, `data_retention_days` is not
+    /// applied. Lets tests record the inserted rows with a mock server.
+    pub fn build_with_client(self, clickhouse: ClickhouseClient) -> Result<Processor> {
+        let dead_letter = match (self.dead_letter, self.config.dead_letter_path) {
+            (Some(dead_letter), _) => dead_letter,
+            (None, Some(path)) => DeadLetterQueue::new(path),
+            (None, None) => DeadLetterQueue::new("./dead_letter.jsonl"),
+        };
+
+        let tx_inserter = clickhouse.create_transaction_inserter()?;
+
+        Ok(Processor {
+            clickhouse,
+            tx_inserter,
+            tx_buffer: Vec::with_capacity(self.config.batch_size),
+            account_buffer: Vec::with_capacity(100),
+            slot_buffer: Vec::with_capacity(100),
+            block_buffer: Vec::with_capacity(100),
+            swap_buffer: Vec::with_capacity(100),
+            seen_signatures: SignatureCache::new(MAX_SEEN_SIGNATURES),
+            checkpoint: self.checkpoint,
+            dead_letter,
+            tx_stream: self.tx_stream,
+            max_slot: None,
+            transactions_flushed: 0,
+            batch_size: self.config.batch_size,
+            max_buffer_size: self.config.max_buffer_size,
+            max_account_buffer_size: self.config.max_account_buffer_size,
+            flush_interval: self.config.flush_interval,
+        })
+    }
+}
+
 pub struct Processor {
     clickhouse: ClickhouseClient,
+    /// Buffered transactions are streamed into this inserter when they are committed
+    tx_inserter: Inserter<ClickHouseTransaction>,
+    /// Transactions waiting for the next commit, the only copy of each row: they are
+    /// written to `tx_inserter` on commit and dead lettered if that fails
     tx_buffer: Vec<ClickHouseTransaction>,
     account_buffer: Vec<ClickHouseAccount>,
     slot_buffer: Vec<ClickHouseSlot>,
+    block_buffer: Vec<ClickHouseBlock>,
+    swap_buffer: Vec<ClickHouseSwapEvent>,
+    seen_signatures: SignatureCache,
+    checkpoint: Option<CheckpointStore>,
+    dead_letter: DeadLetterQueue,
+    /// Subscribers to inserted transactions, e.g. WebSocket clients
+    tx_stream: Option<broadcast::Sender<ClickHouseTransaction>>,
+    /// Highest slot seen so far, checkpointed once everything is flushed
+    max_slot: Option<u64>,
+    transactions_flushed: u64,
     batch_size: usize,
+    max_buffer_size: usize,
+    max_account_buffer_size: usize,
     pub flush_interval: Duration,
 }
 
@@ -25,58 +250,252 @@ impl Processor {
         clickhouse_password: &str,
         clickhouse_db: &str,
     ) -> Result<Self> {
-        let clickhouse = ClickhouseClient::new(
-            clickhouse_url,
-            clickhouse_user,
-            clickhouse_password,
-            clickhouse_db,
-        )
-        .await?;
+        Self::with_config(ProcessorConfig::default())
+            .build(
+                clickhouse_url,
+                clickhouse_user,
+                clickhouse_password,
+                clickhouse_db,
+            )
+            .await
+    }
 
-        Ok(Self {
-            clickhouse,
-            tx_buffer: Vec::with_capacity(1000),
-            account_buffer: Vec::with_capacity(100),
-            slot_buffer: Vec::with_capacity(100),
-            batch_size: 1000,
-            flush_interval: Duration::from_secs(5),
-        })
+    pub fn with_config(config: ProcessorConfig) -> ProcessorBuilder {
+        ProcessorBuilder {
+            config,
+            checkpoint: None,
+            dead_letter: None,
+            tx_stream: None,
+        }
     }
 
+    /// Total number of transactions written to ClickHouse
+    pub fn transactions_flushed(&self) -> u64 {
+        self.transactions_flushed
+    }
+
+    /// Process `events` until the channel closes, flushing every table each
+    /// `flush_interval`. Returns the number of transactions flushed after `shutdown`
+    /// was signalled, the ingest side is expected to drop its sender on shutdown so
+    /// the channel is drained before the final flush.
+    ///
+    /// Events and flushes are handled by this one task, so the processor needs no lock.
+    /// A flush holds up event processing while it runs: with large batches or a slow
+    /// ClickHouse, events pile up in the bounded channel and the gRPC stream is slowed
+    /// down by backpressure rather than by contention on a shared processor.
+    pub async fn run(
+        &mut self,
+        mut events: mpsc::Receiver<IndexEvent>,
+        mut shutdown: watch::Receiver<bool>,
+    ) -> Result<u64> {
+        let mut flush_timer = tokio::time::interval(self.flush_interval);
+        // Ticks missed during a long flush are dropped instead of causing back to back flushes
+        flush_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        // Transactions flushed before shutdown started, to report what the shutdown flushed
+        let mut flushed_before_shutdown = None;
+
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Some(event) => {
+                        if let Err(e) = self.process_event(event).await {
+                            error!("Processing error: {}", e);
+                        }
+                    }
+                    None => {
+                        warn!("Event channel closed, flushing and exiting");
+                        break;
+                    }
+                },
+
+                _ = flush_timer.tick() => {
+                    if let Err(e) = self.flush_all().await {
+                        error!("Periodic flush error: {}", e);
+                    }
+                }
+
+                // Keep draining until the ingest side drops its sender
+                _ = shutdown.changed(), if flushed_before_shutdown.is_none() => {
+                    flushed_before_shutdown = Some(self.transactions_flushed);
+                }
+            }
+        }
+
+        self.flush_all().await?;
+
+        Ok(self.transactions_flushed - flushed_before_shutdown.unwrap_or(0))
+    }
+
+    fn track_slot(&mut self, slot: u64) {
+        self.max_slot = Some(self.max_slot.map_or(slot, |max| max.max(slot)));
+    }
+
+    #[instrument(
+        skip_all,
+        fields(table = field::Empty, slot = field::Empty, signature = field::Empty)
+    )]
     pub async fn process_event(&mut self, event: IndexEvent) -> Result<()> {
+        let span = Span::current();
+
         match event {
             IndexEvent::Account(account) => {
+                span.record("table", "accounts");
+                span.record("slot", account.slot);
+                metrics::ACCOUNTS_RECEIVED_TOTAL.inc();
                 let ch_account = Transformer::transform_account(&account)?;
                 self.account_buffer.push(ch_account);
 
-                if self.account_buffer.len() >= self.batch_size {
+                // Logged once on the way up, flushes failing is the only way to get here
+                let warn_at = self.max_account_buffer_size * ACCOUNT_BUFFER_WARN_PERCENT / 100;
+                if self.account_buffer.len() == warn_at {
+                    warn!(
+                        "Account buffer {}% full ({} of {} rows)",
+                        ACCOUNT_BUFFER_WARN_PERCENT, warn_at, self.max_account_buffer_size
+                    );
+                }
+
+                if self.account_buffer.len() >= self.max_account_buffer_size {
+                    self.flush_full_account_buffer().await;
+                } else if self.account_buffer.len() >= self.batch_size {
                     self.flush_accounts().await?;
                 }
             }
             IndexEvent::Transaction(transaction) => {
-                let ch_tx = Transformer::transform_transaction(&transaction)?;
+                span.record("table", "transactions");
+                span.record("slot", transaction.slot);
+                span.record("signature", transaction.signature.as_str());
+                metrics::TRANSACTIONS_RECEIVED_TOTAL.inc();
+
+                if let Err(e) = Transformer::validate_transaction(&transaction) {
+                    metrics::MALFORMED_TRANSACTIONS_TOTAL.inc();
+                    warn!("Dropping malformed transaction: {}: {:?}", e, transaction);
+                    return Ok(());
+                }
+                if Transformer::exceeds_fee_bound(&transaction) {
+                    metrics::HIGH_FEE_TRANSACTIONS_TOTAL.inc();
+                    warn!(
+                        "Transaction {} paid an unusually high fee of {:?} lamports",
+                        transaction.signature, transaction.fee
+                    );
+                }
+
+                // Reconnects from a past slot replay transactions we already have
+                if self.seen_signatures.contains(&transaction.signature) {
+                    metrics::DUPLICATE_TX_SKIPPED.inc();
+                    return Ok(());
+                }
+
+                self.track_slot(transaction.slot);
+                let ch_tx = ClickHouseTransaction {
+                    // Replayed transactions are merged by signature, keeping the latest write
+                    write_version: Utc::now().timestamp_millis() as u64,
+                    ..Transformer::transform_transaction(&transaction)?
+                };
+                self.swap_buffer
+                    .extend(Transformer::transform_swap_events(&transaction));
+
                 self.tx_buffer.push(ch_tx);
+                // Marked seen only once buffered so a failed transform is retried on replay
+                self.seen_signatures.insert(&transaction.signature);
+                self.commit_transactions(false).await?;
 
-                if self.tx_buffer.len() >= self.batch_size {
-                    self.flush_transactions().await?;
+                if self.swap_buffer.len() >= self.batch_size {
+                    self.flush_swap_events().await?;
                 }
             }
             IndexEvent::Slot(slot) => {
-                let ch_slot = Transformer::transform_slot(slot);
+                span.record("table", "slots");
+                span.record("slot", slot.slot);
+                metrics::SLOTS_RECEIVED_TOTAL.inc();
+                self.track_slot(slot.slot);
+                let ch_slot = Transformer::transform_slot(&slot);
                 self.slot_buffer.push(ch_slot);
 
                 if self.slot_buffer.len() >= self.batch_size {
                     self.flush_slots().await?;
                 }
             }
-            IndexEvent::Block(_block) => {
-                // handle blocks if needed
+            IndexEvent::Block(block) => {
+                span.record("table", "blocks");
+                span.record("slot", block.slot);
+                metrics::BLOCKS_RECEIVED_TOTAL.inc();
+                self.track_slot(block.slot);
+                self.block_buffer.push(Transformer::transform_block(&block));
+
+                if self.block_buffer.len() >= self.batch_size {
+                    self.flush_blocks().await?;
+                }
             }
         };
 
+        if self.buffered_rows() >= self.max_buffer_size {
+            warn!(
+                "{} rows buffered (max {}), forcing a flush",
+                self.buffered_rows(),
+                self.max_buffer_size
+            );
+            // Same backoff as `flush_full_account_buffer`, instead of retrying every event
+            if let Err(e) = self.flush_all().await {
+                error!(
+                    "Forced flush failed, pausing {}ms before the next event: {}",
+                    self.flush_interval.as_millis(),
+                    e
+                );
+                tokio::time::sleep(self.flush_interval).await;
+            }
+        }
+
+        self.update_buffer_gauges();
+
         Ok(())
     }
 
+    /// Flush the account buffer once it reached `max_account_buffer_size`. If ClickHouse is
+    /// still failing, wait `flush_interval` before taking the next event: the bounded
+    /// event channel fills up and the gRPC stream slows down, instead of the buffer
+    /// growing with every account update.
+    async fn flush_full_account_buffer(&mut self) {
+        error!(
+            "Account buffer full ({} of {} rows), flushing now",
+            self.account_buffer.len(),
+            self.max_account_buffer_size
+        );
+
+        if let Err(e) = self.flush_accounts().await {
+            error!(
+                "Account flush failed, pausing {}ms before the next event: {}",
+                self.flush_interval.as_millis(),
+                e
+            );
+            tokio::time::sleep(self.flush_interval).await;
+        }
+    }
+
+    fn buffered_rows(&self) -> usize {
+        self.tx_buffer.len()
+            + self.account_buffer.len()
+            + self.slot_buffer.len()
+            + self.block_buffer.len()
+            + self.swap_buffer.len()
+    }
+
+    fn update_buffer_gauges(&self) {
+        let buffers = [
+            ("transactions", self.tx_buffer.len()),
+            ("accounts", self.account_buffer.len()),
+            ("slots", self.slot_buffer.len()),
+            ("blocks", self.block_buffer.len()),
+            ("swap_events", self.swap_buffer.len()),
+        ];
+
+        for (buffer, len) in buffers {
+            metrics::BUFFER_SIZE
+                .with_label_values(&[buffer])
+                .set(len as i64);
+        }
+    }
+
     async fn flush_accounts(&mut self) -> Result<()> {
         if self.account_buffer.is_empty() {
             return Ok(());
@@ -84,6 +503,9 @@ impl Processor {
 
         let count = self.account_buffer.len();
         let start_time = time::Instant::now();
+        let timer = metrics::FLUSH_DURATION_SECONDS
+            .with_label_values(&["accounts"])
+            .start_timer();
 
         match self
             .clickhouse
@@ -91,6 +513,7 @@ impl Processor {
             .await
         {
             Ok(_) => {
+                timer.observe_duration();
                 let duration = start_time.elapsed().as_millis();
                 info!(
                     "Inserted {} accounts to clickhouse db in {} millis",
@@ -99,6 +522,10 @@ impl Processor {
                 self.account_buffer.clear();
             }
             Err(e) => {
+                timer.stop_and_discard();
+                metrics::FLUSH_ERRORS_TOTAL
+                    .with_label_values(&["accounts"])
+                    .inc();
                 error!("Failed to insert accounts: {}", e);
                 return Err(e);
             }
@@ -108,31 +535,72 @@ impl Processor {
     }
 
     async fn flush_transactions(&mut self) -> Result<()> {
-        if self.tx_buffer.is_empty() {
+        self.commit_transactions(true).await
+    }
+
+    /// Write the buffered transactions to `tx_inserter` and commit them. Unless `force` is
+    /// set this waits for `batch_size` rows, `flush_all` commits the rest every
+    /// `flush_interval`.
+    #[instrument(skip_all, fields(table = "transactions", batch_size = self.tx_buffer.len()))]
+    async fn commit_transactions(&mut self, force: bool) -> Result<()> {
+        if self.tx_buffer.is_empty() || (!force && self.tx_buffer.len() < self.batch_size) {
             return Ok(());
         }
 
-        let count = self.tx_buffer.len();
         let start_time = time::Instant::now();
-
-        match self
-            .clickhouse
-            .batch_insert_transactions(&self.tx_buffer)
-            .await
-        {
-            Ok(_) => {
-                let duration = start_time.elapsed().as_millis();
+        match self.write_transactions().await {
+            Ok(quantities) => {
+                let duration = start_time.elapsed();
+                metrics::FLUSH_DURATION_SECONDS
+                    .with_label_values(&["transactions"])
+                    .observe(duration.as_secs_f64());
                 info!(
                     "Inserted {} transactions to clickhouse db in {} millis",
-                    count, duration
+                    quantities.rows,
+                    duration.as_millis()
                 );
+                self.transactions_flushed += quantities.rows;
+
+                // Lagging subscribers drop old rows, sending never waits for them
+                if let Some(tx_stream) = &self.tx_stream
+                    && tx_stream.receiver_count() > 0
+                {
+                    for tx in self.tx_buffer.drain(..) {
+                        let _ = tx_stream.send(tx);
+                    }
+                }
                 self.tx_buffer.clear();
             }
-            Err(e) => {
-                error!("Failed to insert transactions: {}", e);
-                return Err(e);
-            }
+            Err(e) => self.dead_letter_transactions(e.into())?,
+        }
+
+        Ok(())
+    }
+
+    async fn write_transactions(&mut self) -> clickhouse::error::Result<Quantities> {
+        for tx in &self.tx_buffer {
+            self.tx_inserter.write(tx).await?;
         }
+        self.tx_inserter.force_commit().await
+    }
+
+    /// Park the uncommitted transactions so one bad insert doesn't stop the processor
+    fn dead_letter_transactions(&mut self, e: anyhow::Error) -> Result<()> {
+        metrics::FLUSH_ERRORS_TOTAL
+            .with_label_values(&["transactions"])
+            .inc();
+        error!("Failed to insert transactions: {}", e);
+
+        self.dead_letter.append(&self.tx_buffer, &e.to_string())?;
+        warn!(
+            "Wrote {} transactions to dead letter queue {}",
+            self.tx_buffer.len(),
+            self.dead_letter.path().display()
+        );
+        self.tx_buffer.clear();
+
+        // Start over with a fresh INSERT, the failed one can't be resumed
+        self.tx_inserter = self.clickhouse.create_transaction_inserter()?;
 
         Ok(())
     }
@@ -143,12 +611,20 @@ impl Processor {
         }
 
         let count = self.slot_buffer.len();
+        let timer = metrics::FLUSH_DURATION_SECONDS
+            .with_label_values(&["slots"])
+            .start_timer();
         match self.clickhouse.batch_insert_slots(&self.slot_buffer).await {
             Ok(_) => {
+                timer.observe_duration();
                 info!("Inserted {} slots to ClickHouse", count);
                 self.slot_buffer.clear();
             }
             Err(e) => {
+                timer.stop_and_discard();
+                metrics::FLUSH_ERRORS_TOTAL
+                    .with_label_values(&["slots"])
+                    .inc();
                 error!("Failed to insert slots: {}", e);
                 return Err(e);
             }
@@ -157,10 +633,85 @@ impl Processor {
         Ok(())
     }
 
+    async fn flush_blocks(&mut self) -> Result<()> {
+        if self.block_buffer.is_empty() {
+            return Ok(());
+        }
+
+        let count = self.block_buffer.len();
+        let timer = metrics::FLUSH_DURATION_SECONDS
+            .with_label_values(&["blocks"])
+            .start_timer();
+        match self
+            .clickhouse
+            .batch_insert_blocks(&self.block_buffer)
+            .await
+        {
+            Ok(_) => {
+                timer.observe_duration();
+                info!("Inserted {} blocks to ClickHouse", count);
+                self.block_buffer.clear();
+            }
+            Err(e) => {
+                timer.stop_and_discard();
+                metrics::FLUSH_ERRORS_TOTAL
+                    .with_label_values(&["blocks"])
+                    .inc();
+                error!("Failed to insert blocks: {}", e);
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn flush_swap_events(&mut self) -> Result<()> {
+        if self.swap_buffer.is_empty() {
+            return Ok(());
+        }
+
+        let count = self.swap_buffer.len();
+        let timer = metrics::FLUSH_DURATION_SECONDS
+            .with_label_values(&["swap_events"])
+            .start_timer();
+        match self
+            .clickhouse
+            .batch_insert_swap_events(&self.swap_buffer)
+            .await
+        {
+            Ok(_) => {
+                timer.observe_duration();
+                info!("Inserted {} swap events to ClickHouse", count);
+                self.swap_buffer.clear();
+            }
+            Err(e) => {
+                timer.stop_and_discard();
+                metrics::FLUSH_ERRORS_TOTAL
+                    .with_label_values(&["swap_events"])
+                    .inc();
+                error!("Failed to insert swap events: {}", e);
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip_all, fields(slot = self.max_slot))]
     pub async fn flush_all(&mut self) -> Result<()> {
         self.flush_transactions().await?;
         self.flush_accounts().await?;
         self.flush_slots().await?;
+        self.flush_blocks().await?;
+        self.flush_swap_events().await?;
+        self.update_buffer_gauges();
+
+        if let (Some(checkpoint), Some(slot)) = (&self.checkpoint, self.max_slot)
+            && let Err(e) = checkpoint.save(slot)
+        {
+            warn!("Failed to save checkpoint at slot {}: {}", slot, e);
+        }
+
         Ok(())
     }
 }
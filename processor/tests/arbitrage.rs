@@ -0,0 +1,113 @@
+use ingest::{
+    testing::MockSubscribeUpdate,
+    types::{SolanaTransaction, TokenBalanceChange},
+};
+use processor::Transformer;
+
+const FEE_PAYER: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+const POOL_1: &str = "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2";
+const POOL_2: &str = "7XawhbbxtsRcQA8KTkHT9f9nc6d69UwqCDh6U5EEbEmX";
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qNsxDiBAZmYFEvNCHqrDtkMEaC";
+const BONK_MINT: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+const JUP_MINT: &str = "JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN";
+
+const FEE: u64 = 5_000;
+
+fn change(mint: &str, owner: &str, delta: f64) -> TokenBalanceChange {
+    TokenBalanceChange {
+        mint: mint.to_string(),
+        owner: owner.to_string(),
+        pre_amount: 1_000.0,
+        post_amount: 1_000.0 + delta,
+        delta,
+    }
+}
+
+fn transaction(
+    pre_lamports: u64,
+    post_lamports: u64,
+    changes: Vec<TokenBalanceChange>,
+) -> SolanaTransaction {
+    let update = MockSubscribeUpdate::transaction()
+        .fee(FEE)
+        .account(FEE_PAYER, pre_lamports, post_lamports)
+        .build_transaction();
+
+    SolanaTransaction {
+        token_balance_changes: changes,
+        ..SolanaTransaction::try_from(update).unwrap()
+    }
+}
+
+#[test]
+fn round_trip_back_to_the_input_mint_is_an_arbitrage() {
+    // USDC → BONK → USDC, ending with 1 USDC more
+    let tx = transaction(
+        1_000_000,
+        1_000_000 - FEE,
+        vec![
+            change(USDC_MINT, FEE_PAYER, 1.0),
+            change(USDC_MINT, POOL_1, 100.0),
+            change(BONK_MINT, POOL_1, -5_000.0),
+            change(BONK_MINT, POOL_2, 5_000.0),
+            change(USDC_MINT, POOL_2, -101.0),
+        ],
+    );
+
+    assert!(Transformer::detect_arbitrage(&tx));
+}
+
+#[test]
+fn multi_hop_route_to_another_mint_is_not_an_arbitrage() {
+    // USDC → BONK → JUP
+    let tx = transaction(
+        1_000_000,
+        1_000_000 - FEE,
+        vec![
+            change(USDC_MINT, FEE_PAYER, -100.0),
+            change(JUP_MINT, FEE_PAYER, 120.0),
+            change(USDC_MINT, POOL_1, 100.0),
+            change(BONK_MINT, POOL_1, -5_000.0),
+            change(BONK_MINT, POOL_2, 5_000.0),
+            change(JUP_MINT, POOL_2, -120.0),
+        ],
+    );
+
+    assert!(!Transformer::detect_arbitrage(&tx));
+}
+
+#[test]
+fn multi_hop_route_from_sol_is_not_an_arbitrage() {
+    // SOL → BONK → JUP through a temporary wSOL account, absent from the token balances
+    let tx = transaction(
+        2_000_000_000,
+        1_000_000_000 - FEE,
+        vec![
+            change(JUP_MINT, FEE_PAYER, 120.0),
+            change(SOL_MINT, POOL_1, 1.0),
+            change(BONK_MINT, POOL_1, -5_000.0),
+            change(BONK_MINT, POOL_2, 5_000.0),
+            change(JUP_MINT, POOL_2, -120.0),
+        ],
+    );
+
+    assert!(!Transformer::detect_arbitrage(&tx));
+}
+
+#[test]
+fn sol_round_trip_through_a_temporary_wsol_account_is_an_arbitrage() {
+    // SOL → BONK → SOL, the profit lands in the fee payer's lamports
+    let tx = transaction(
+        1_000_000_000,
+        1_000_100_000 - FEE,
+        vec![
+            change(SOL_MINT, POOL_1, 1.0),
+            change(BONK_MINT, POOL_1, -5_000.0),
+            change(BONK_MINT, POOL_2, 5_000.0),
+            change(SOL_MINT, POOL_2, -1.0001),
+        ],
+    );
+
+    assert!(Transformer::detect_arbitrage(&tx));
+}
@@ -0,0 +1,64 @@
+//! Bytes an insert puts on the wire with and without LZ4, without a ClickHouse server.
+//!
+//! `cargo test -p processor --test compression -- --nocapture` prints the measured ratio.
+
+use clickhouse::{Client, Compression};
+use processor::{
+    ClickhouseClient, clickhouse_types::ClickHouseTransaction, testing::swap_transactions,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// Same batch as the `insert_compression` benchmark
+const BATCH_SIZE: usize = 1000;
+
+/// Insert `batch` into a bare HTTP server and return the number of bytes it received
+async fn insert_bytes(compression: Compression, batch: &[ClickHouseTransaction]) -> usize {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        let mut buf = vec![0; 64 * 1024];
+        // The rows are streamed with chunked encoding, which ends with an empty chunk
+        while !received.ends_with(b"\r\n0\r\n\r\n") {
+            let n = socket.read(&mut buf).await.unwrap();
+            assert!(n > 0, "connection closed before the insert ended");
+            received.extend_from_slice(&buf[..n]);
+        }
+        socket
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        received.len()
+    });
+
+    let client = Client::default()
+        .with_url(url)
+        .with_compression(compression)
+        .with_validation(false);
+    ClickhouseClient::from_client(client)
+        .batch_insert_transactions(batch)
+        .await
+        .unwrap();
+
+    server.await.unwrap()
+}
+
+#[tokio::test]
+async fn lz4_shrinks_json_heavy_inserts() {
+    let batch = swap_transactions(BATCH_SIZE, 0);
+
+    let uncompressed = insert_bytes(Compression::None, &batch).await;
+    let compressed = insert_bytes(Compression::Lz4, &batch).await;
+    let ratio = uncompressed as f64 / compressed as f64;
+    println!(
+        "{} rows: {} bytes uncompressed, {} bytes with LZ4, ratio {:.1}",
+        BATCH_SIZE, uncompressed, compressed, ratio
+    );
+
+    assert!(ratio > 5.0, "LZ4 ratio only {:.1}", ratio);
+}
@@ -0,0 +1,137 @@
+use processor::decoders::{AccountDecoder, SplTokenAccountDecoder, decode_account};
+use serde_json::json;
+
+const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qNsxDiBAZmYFEvNCHqrDtkMEaC";
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const OWNER: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+const DELEGATE: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+const CLOSE_AUTHORITY: &str = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc";
+
+/// Rent exempt reserve of a token account, in lamports
+const RENT_EXEMPT_RESERVE: u64 = 2_039_280;
+
+#[derive(Default)]
+struct TokenAccount {
+    delegate: Option<(&'static str, u64)>,
+    is_native: Option<u64>,
+    close_authority: Option<&'static str>,
+}
+
+fn pubkey(address: &str) -> Vec<u8> {
+    bs58::decode(address).into_vec().unwrap()
+}
+
+/// `COption` layout, a little-endian u32 tag followed by the (zeroed if unset) value
+fn optional(value: Option<Vec<u8>>, len: usize) -> Vec<u8> {
+    match value {
+        Some(value) => [vec![1, 0, 0, 0], value].concat(),
+        None => vec![0; 4 + len],
+    }
+}
+
+fn token_account_data(mint: &str, amount: u64, state: u8, account: TokenAccount) -> Vec<u8> {
+    let mut data = pubkey(mint);
+    data.extend(pubkey(OWNER));
+    data.extend(amount.to_le_bytes());
+    data.extend(optional(account.delegate.map(|(d, _)| pubkey(d)), 32));
+    data.push(state);
+    data.extend(optional(
+        account
+            .is_native
+            .map(|reserve| reserve.to_le_bytes().to_vec()),
+        8,
+    ));
+    data.extend(
+        account
+            .delegate
+            .map_or(0, |(_, amount)| amount)
+            .to_le_bytes(),
+    );
+    data.extend(optional(account.close_authority.map(pubkey), 32));
+    data
+}
+
+#[test]
+fn token_account_without_optional_fields() {
+    let data = token_account_data(USDC_MINT, 1_500_000, 1, TokenAccount::default());
+    assert_eq!(data.len(), 165);
+
+    assert_eq!(
+        SplTokenAccountDecoder.decode(&data),
+        Some(json!({
+            "mint": USDC_MINT,
+            "owner": OWNER,
+            "amount": 1_500_000,
+            "delegate": null,
+            "state": "initialized",
+            "is_native": null,
+            "delegated_amount": 0,
+            "close_authority": null,
+        }))
+    );
+}
+
+#[test]
+fn token_account_with_delegate_and_close_authority() {
+    let data = token_account_data(
+        USDC_MINT,
+        1_500_000,
+        2,
+        TokenAccount {
+            delegate: Some((DELEGATE, 500_000)),
+            close_authority: Some(CLOSE_AUTHORITY),
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(
+        SplTokenAccountDecoder.decode(&data),
+        Some(json!({
+            "mint": USDC_MINT,
+            "owner": OWNER,
+            "amount": 1_500_000,
+            "delegate": DELEGATE,
+            "state": "frozen",
+            "is_native": null,
+            "delegated_amount": 500_000,
+            "close_authority": CLOSE_AUTHORITY,
+        }))
+    );
+}
+
+#[test]
+fn wrapped_sol_account() {
+    let data = token_account_data(
+        SOL_MINT,
+        1_000_000_000,
+        1,
+        TokenAccount {
+            is_native: Some(RENT_EXEMPT_RESERVE),
+            ..Default::default()
+        },
+    );
+
+    let decoded = decode_account(TOKEN_PROGRAM, &data).unwrap();
+    assert_eq!(decoded["mint"], SOL_MINT);
+    assert_eq!(decoded["amount"], 1_000_000_000);
+    assert_eq!(decoded["is_native"], RENT_EXEMPT_RESERVE);
+}
+
+#[test]
+fn unknown_layouts_are_not_decoded() {
+    let data = token_account_data(USDC_MINT, 1, 1, TokenAccount::default());
+
+    // Mint and multisig accounts of the token program, and truncated data
+    for len in [0, 82, 164, 166, 355] {
+        let mut data = data.clone();
+        data.resize(len, 0);
+        assert_eq!(SplTokenAccountDecoder.decode(&data), None, "{} bytes", len);
+    }
+
+    let unknown_state = token_account_data(USDC_MINT, 1, 3, TokenAccount::default());
+    assert_eq!(SplTokenAccountDecoder.decode(&unknown_state), None);
+
+    // No decoder is registered for other owners
+    assert_eq!(decode_account(OWNER, &data), None);
+}
@@ -0,0 +1,166 @@
+//! Stream update to ClickHouse row, without a gRPC endpoint or ClickHouse server
+
+use clickhouse::{
+    Client, Compression,
+    test::{Mock, handlers},
+};
+use ingest::{
+    testing::MockSubscribeUpdate, types::IndexEvent, yellowstone_client::YellowstoneClient,
+};
+use processor::{
+    ClickhouseClient, Transformer,
+    clickhouse_types::{ClickHouseSwapEvent, ClickHouseTransaction},
+    dead_letter::DeadLetterQueue,
+    worker::{Processor, ProcessorConfig},
+};
+use tokio::sync::mpsc;
+
+const TRADER: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+const JUPITER_V6: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+const COMPUTE_BUDGET: &str = "ComputeBudget111111111111111111111111111111";
+const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
+const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qNsxDiBAZmYFEvNCHqrDtkMEaC";
+
+/// Anchor discriminator of Jupiter v6 `shared_accounts_route`
+const SHARED_ACCOUNTS_ROUTE: [u8; 8] = [193, 32, 155, 51, 65, 214, 156, 129];
+
+fn swap_data(in_amount: u64, quoted_out_amount: u64) -> Vec<u8> {
+    let mut data = SHARED_ACCOUNTS_ROUTE.to_vec();
+    data.push(0); // id
+    data.extend_from_slice(&0u32.to_le_bytes()); // empty route plan
+    data.extend_from_slice(&in_amount.to_le_bytes());
+    data.extend_from_slice(&quoted_out_amount.to_le_bytes());
+    data.extend_from_slice(&50u16.to_le_bytes()); // slippage_bps
+    data.push(0); // platform_fee_bps
+    data
+}
+
+fn set_compute_unit_price(micro_lamports: u64) -> Vec<u8> {
+    let mut data = vec![3];
+    data.extend_from_slice(&micro_lamports.to_le_bytes());
+    data
+}
+
+async fn into_event(update: MockSubscribeUpdate) -> IndexEvent {
+    let (event_tx, mut event_rx) = mpsc::channel(1);
+    YellowstoneClient::process_update(update.build(), &event_tx)
+        .await
+        .unwrap();
+
+    event_rx.try_recv().expect("an event should have been sent")
+}
+
+fn account(seed: u8) -> String {
+    bs58::encode([seed; 32]).into_string()
+}
+
+fn swap_transaction(signature: [u8; 64]) -> MockSubscribeUpdate {
+    let (program_authority, source_ata, program_source, program_dest, dest_ata) =
+        (account(1), account(2), account(3), account(4), account(5));
+    let swap_accounts = [
+        "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+        program_authority.as_str(),
+        TRADER,
+        source_ata.as_str(),
+        program_source.as_str(),
+        program_dest.as_str(),
+        dest_ata.as_str(),
+        SOL_MINT,
+        USDC_MINT,
+    ];
+
+    MockSubscribeUpdate::transaction()
+        .signature(signature)
+        .slot(300_000_000)
+        .fee(15_000)
+        .account(TRADER, 2_000_000_000, 999_985_000)
+        .instruction(COMPUTE_BUDGET, &[], &set_compute_unit_price(10_000))
+        .instruction(
+            JUPITER_V6,
+            &swap_accounts,
+            &swap_data(1_000_000_000, 150_000_000),
+        )
+}
+
+#[tokio::test]
+async fn swap_transaction_through_the_pipeline() {
+    let IndexEvent::Transaction(tx) = into_event(swap_transaction([9; 64])).await else {
+        panic!("expected a transaction event");
+    };
+
+    Transformer::validate_transaction(&tx).unwrap();
+    let row = Transformer::transform_transaction(&tx).unwrap();
+    assert_eq!(row.signature, bs58::encode([9u8; 64]).into_string());
+    assert_eq!(row.slot, 300_000_000);
+    assert_eq!(row.fee, Some(15_000));
+    assert!(row.success);
+    assert_eq!(row.dex, "jupiter");
+    assert_eq!(row.fee_payer, TRADER);
+    assert_eq!(row.priority_fee_microlamports, Some(10_000));
+    assert_eq!(row.program_ids, vec![COMPUTE_BUDGET, JUPITER_V6]);
+    assert_eq!(row.net_balance_change, 999_985_000 - 2_000_000_000);
+
+    let swaps = Transformer::transform_swap_events(&tx);
+    assert_eq!(swaps.len(), 1);
+    assert_eq!(swaps[0].dex, "jupiter");
+    assert_eq!(swaps[0].trader, TRADER);
+    assert_eq!(swaps[0].token_in, SOL_MINT);
+    assert_eq!(swaps[0].token_out, USDC_MINT);
+    assert_eq!(swaps[0].amount_in, 1_000_000_000);
+    assert_eq!(swaps[0].amount_out, 150_000_000);
+}
+
+#[tokio::test]
+async fn failed_swap_has_no_swap_events() {
+    let update = MockSubscribeUpdate::transaction()
+        .account(TRADER, 1_000_000, 995_000)
+        .instruction(JUPITER_V6, &[TRADER], &swap_data(1, 1))
+        .failed(vec![0, 0, 0, 0]);
+
+    let IndexEvent::Transaction(tx) = into_event(update).await else {
+        panic!("expected a transaction event");
+    };
+
+    let row = Transformer::transform_transaction(&tx).unwrap();
+    assert!(!row.success);
+    assert!(row.error_message.is_some());
+    assert!(Transformer::transform_swap_events(&tx).is_empty());
+}
+
+#[tokio::test]
+async fn processor_inserts_the_swap_transaction() {
+    // Answers the inserts in order and records their rows
+    let mock = Mock::new();
+    let transactions = mock.add(handlers::record::<ClickHouseTransaction>());
+    let swap_events = mock.add(handlers::record::<ClickHouseSwapEvent>());
+
+    let client = Client::default()
+        .with_url(mock.url())
+        .with_compression(Compression::None)
+        .with_validation(false);
+    let mut processor = Processor::with_config(ProcessorConfig::default())
+        .with_dead_letter(DeadLetterQueue::new(
+            std::env::temp_dir().join("pipeline_dead_letter.jsonl"),
+        ))
+        .build_with_client(ClickhouseClient::from_client(client))
+        .unwrap();
+
+    let event = into_event(swap_transaction([7; 64])).await;
+    processor.process_event(event.clone()).await.unwrap();
+    // Replays of the same transaction are skipped
+    processor.process_event(event).await.unwrap();
+    processor.flush_all().await.unwrap();
+
+    let rows: Vec<ClickHouseTransaction> = transactions.collect().await;
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].signature, bs58::encode([7u8; 64]).into_string());
+    assert_eq!(rows[0].fee, Some(15_000));
+    assert!(rows[0].success);
+    assert!(rows[0].write_version > 0);
+    assert_eq!(processor.transactions_flushed(), 1);
+
+    let swaps: Vec<ClickHouseSwapEvent> = swap_events.collect().await;
+    assert_eq!(swaps.len(), 1);
+    assert_eq!(swaps[0].token_in, SOL_MINT);
+    assert_eq!(swaps[0].token_out, USDC_MINT);
+}
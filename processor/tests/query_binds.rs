@@ -0,0 +1,57 @@
+//! User supplied strings are bound as query parameters, never spliced into the SQL.
+//!
+//! Starts a throwaway ClickHouse container, run with `cargo test -p processor -- --ignored`.
+
+use processor::{
+    query::{QueryService, TimePeriod, TransactionFilters},
+    testing::{connect, start_clickhouse},
+};
+
+const MALICIOUS_INPUTS: &[&str] = &[
+    "'; DROP TABLE transactions; --",
+    "' OR 1=1 --",
+    "\\' OR '1'='1",
+    "x' UNION SELECT signature, slot, timestamp, success, fee FROM transactions --",
+    "? ?? {signature}",
+];
+
+#[tokio::test]
+#[ignore = "needs Docker"]
+async fn sql_metacharacters_are_treated_as_values() {
+    let (_container, url) = start_clickhouse().await;
+    let qs = QueryService::new(connect(&url, true).await);
+
+    for input in MALICIOUS_INPUTS {
+        let transaction = qs.get_transaction(input).await.unwrap();
+        assert!(transaction.is_none(), "{:?} matched a transaction", input);
+
+        let history = qs
+            .get_account_history(input, TimePeriod::Last30Days)
+            .await
+            .unwrap();
+        assert!(history.is_empty(), "{:?} matched account writes", input);
+
+        let transactions = qs
+            .get_transactions_by_account(input, TimePeriod::Last30Days, 10)
+            .await
+            .unwrap();
+        assert!(transactions.is_empty(), "{:?} matched transactions", input);
+
+        let activity = qs
+            .get_trader_activity(input, TimePeriod::Last30Days)
+            .await
+            .unwrap();
+        assert_eq!(activity.tx_count, 0, "{:?} matched trader activity", input);
+
+        let matches = qs
+            .search_transactions_by_log(input, TimePeriod::Last30Days, 10)
+            .await
+            .unwrap();
+        assert!(matches.is_empty(), "{:?} matched log messages", input);
+    }
+
+    // Still there after the DROP TABLE attempt
+    qs.count_transactions(TransactionFilters::default())
+        .await
+        .unwrap();
+}
@@ -0,0 +1,152 @@
+use ingest::types::{SolanaTransaction, TokenBalanceChange, TransactionInstruction};
+use processor::Transformer;
+use proptest::prelude::*;
+
+fn instruction() -> impl Strategy<Value = TransactionInstruction> {
+    (
+        ".{0,44}",
+        prop::collection::vec(".{0,44}", 0..8),
+        // Mostly valid base64, sometimes garbage
+        prop_oneof!["[A-Za-z0-9+/]{0,64}={0,2}", ".{0,32}"],
+        any::<u8>(),
+        prop::option::of(any::<u16>()),
+    )
+        .prop_map(
+            |(program_id, accounts, data, depth, parent_instruction_index)| {
+                TransactionInstruction {
+                    program_id,
+                    accounts,
+                    data,
+                    depth,
+                    parent_instruction_index,
+                }
+            },
+        )
+}
+
+fn token_balance_change() -> impl Strategy<Value = TokenBalanceChange> {
+    (".{0,44}", ".{0,44}", any::<f64>(), any::<f64>()).prop_map(
+        |(mint, owner, pre_amount, post_amount)| TokenBalanceChange {
+            mint,
+            owner,
+            pre_amount,
+            post_amount,
+            delta: post_amount - pre_amount,
+        },
+    )
+}
+
+fn solana_transaction() -> impl Strategy<Value = SolanaTransaction> {
+    (
+        (
+            ".{0,88}",
+            any::<u64>(),
+            any::<bool>(),
+            any::<u64>(),
+            any::<bool>(),
+            proptest::option::of(".{0,64}"),
+            proptest::option::of(any::<u64>()),
+            proptest::option::of(any::<u64>()),
+        ),
+        (
+            prop::collection::vec(any::<u64>(), 0..8),
+            prop::collection::vec(any::<u64>(), 0..8),
+            prop::collection::vec(instruction(), 0..6),
+            prop::collection::vec(instruction(), 0..6),
+            prop::collection::vec(".{0,64}", 0..4),
+            prop::collection::vec(".{0,44}", 0..8),
+            ".{0,44}",
+            prop::collection::vec(token_balance_change(), 0..4),
+        ),
+    )
+        .prop_map(
+            |(
+                (signature, slot, is_vote, index, success, error_message, fee, compute_units),
+                (
+                    pre_balances,
+                    post_balances,
+                    instructions,
+                    inner_instructions,
+                    log_messages,
+                    account_keys,
+                    fee_payer,
+                    token_balance_changes,
+                ),
+            )| SolanaTransaction {
+                signature,
+                slot,
+                is_vote,
+                index,
+                success,
+                error_message,
+                fee,
+                pre_balances,
+                post_balances,
+                compute_units_consumed: compute_units,
+                instructions,
+                inner_instructions,
+                log_messages,
+                account_keys,
+                fee_payer,
+                token_balance_changes,
+                is_jito_bundle: false,
+                is_versioned: false,
+            },
+        )
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(1000))]
+
+    #[test]
+    fn transform_transaction_never_fails(tx in solana_transaction()) {
+        let row = Transformer::transform_transaction(&tx);
+        prop_assert!(row.is_ok());
+        let _ = Transformer::transform_swap_events(&tx);
+    }
+
+    #[test]
+    fn balances_serialize_to_valid_json(tx in solana_transaction()) {
+        let row = Transformer::transform_transaction(&tx).unwrap();
+
+        let pre_balances: Vec<u64> = serde_json::from_str(&row.pre_balances).unwrap();
+        let post_balances: Vec<u64> = serde_json::from_str(&row.post_balances).unwrap();
+        prop_assert_eq!(pre_balances, tx.pre_balances);
+        prop_assert_eq!(post_balances, tx.post_balances);
+    }
+
+    #[test]
+    fn extreme_balances_do_not_overflow(pre in any::<u64>(), post in any::<u64>()) {
+        let tx = SolanaTransaction {
+            pre_balances: vec![pre],
+            post_balances: vec![post],
+            ..empty_transaction()
+        };
+
+        let expected = (post as i128 - pre as i128).clamp(i64::MIN as i128, i64::MAX as i128);
+        prop_assert_eq!(Transformer::net_balance_change(&tx) as i128, expected);
+    }
+}
+
+fn empty_transaction() -> SolanaTransaction {
+    SolanaTransaction {
+        signature: String::new(),
+        slot: 0,
+        is_vote: false,
+        index: 0,
+        success: true,
+        error_message: None,
+        fee: None,
+        pre_balances: Vec::new(),
+        post_balances: Vec::new(),
+        compute_units_consumed: None,
+        instructions: Vec::new(),
+        inner_instructions: Vec::new(),
+        log_messages: Vec::new(),
+        account_keys: Vec::new(),
+        fee_payer: String::new(),
+        token_balance_changes: Vec::new(),
+        is_jito_bundle: false,
+        is_versioned: false,
+    }
+}
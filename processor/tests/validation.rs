@@ -0,0 +1,84 @@
+use ingest::{testing::MockSubscribeUpdate, types::SolanaTransaction};
+use processor::Transformer;
+
+const FEE_PAYER: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+const PROGRAM: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+
+fn valid_transaction() -> SolanaTransaction {
+    let update = MockSubscribeUpdate::transaction()
+        .signature([4; 64])
+        .slot(300_000_000)
+        .fee(5_000)
+        .account(FEE_PAYER, 1_000_000, 995_000)
+        .build_transaction();
+
+    SolanaTransaction::try_from(update).unwrap()
+}
+
+#[test]
+fn well_formed_transaction_is_valid() {
+    let tx = valid_transaction();
+
+    assert!(Transformer::validate_transaction(&tx).is_ok());
+    assert!(!Transformer::exceeds_fee_bound(&tx));
+}
+
+#[test]
+fn malformed_transactions_are_rejected() {
+    let cases: &[(&str, fn(&mut SolanaTransaction))] = &[
+        ("signature is not base58", |tx| {
+            tx.signature = "0OIl".repeat(22)
+        }),
+        ("63 byte signature", |tx| {
+            tx.signature = bs58::encode([4u8; 63]).into_string()
+        }),
+        ("empty signature", |tx| tx.signature.clear()),
+        ("slot 0", |tx| tx.slot = 0),
+        ("more account keys than balances", |tx| {
+            tx.account_keys.push(PROGRAM.to_string())
+        }),
+        ("more balances than account keys", |tx| {
+            tx.pre_balances.push(0)
+        }),
+    ];
+
+    for (case, corrupt) in cases {
+        let mut tx = valid_transaction();
+        corrupt(&mut tx);
+
+        assert!(
+            Transformer::validate_transaction(&tx).is_err(),
+            "{} should be rejected",
+            case
+        );
+    }
+}
+
+#[test]
+fn high_fee_transactions_are_kept() {
+    let cases = [
+        (Some(10_000_000), false),
+        (Some(10_000_001), true),
+        (Some(u64::MAX), true),
+        (None, false),
+    ];
+
+    for (fee, exceeds) in cases {
+        let tx = SolanaTransaction {
+            fee,
+            ..valid_transaction()
+        };
+
+        assert!(
+            Transformer::validate_transaction(&tx).is_ok(),
+            "fee {:?}",
+            fee
+        );
+        assert_eq!(
+            Transformer::exceeds_fee_bound(&tx),
+            exceeds,
+            "fee {:?}",
+            fee
+        );
+    }
+}
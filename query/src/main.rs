@@ -1,12 +1,40 @@
-use anyhow::Result;
+mod output;
+
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, Utc};
 use clap::{Parser, Subcommand};
+use indexer_core::config::Config;
 use processor::ClickhouseClient;
-use processor::query::{QueryService, TimeBucket, TimePeriod, TransactionFilters};
+use processor::clickhouse::RETENTION_TABLES;
+use processor::clickhouse_types::ClickHouseTokenMint;
+use processor::dead_letter::{DeadLetterBatch, DeadLetterQueue};
+use processor::query::{
+    HeatmapCell, InstructionCountBucket, PaginationCursor, QueryService, SlotDensityBucket,
+    TimeBucket, TimePeriod, TokenMetric, TokenVolumeSort, TransactionFilters,
+};
+use serde_json::json;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+
+use crate::output::OutputFormat;
 
 #[derive(Parser)]
 #[command(name = "dex-query")]
 #[command(about = "Query DEX transaction data")]
 struct Cli {
+    /// TOML config file, environment variables override its values
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Output format
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// Query timeout in milliseconds, defaults to QUERY_TIMEOUT_MS or 30000
+    #[arg(long, global = true)]
+    timeout: Option<u64>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -17,6 +45,12 @@ enum Commands {
     Count {
         /// Time period (e.g., "1h", "24h", "7d", "30d")
         period: Option<String>,
+        /// Minimum compute units consumed
+        #[arg(long)]
+        min_cu: Option<u64>,
+        /// Maximum compute units consumed
+        #[arg(long)]
+        max_cu: Option<u64>,
     },
     /// Get recent transactions
     Recent {
@@ -24,14 +58,60 @@ enum Commands {
         limit: Option<usize>,
         /// Optional period filter
         period: Option<String>,
+        /// Minimum compute units consumed
+        #[arg(long)]
+        min_cu: Option<u64>,
+        /// Maximum compute units consumed
+        #[arg(long)]
+        max_cu: Option<u64>,
+        /// Cursor printed by the previous page
+        #[arg(long)]
+        cursor: Option<String>,
     },
     /// Get success rate (percentage)
     SuccessRate {
         period: Option<String>,
     },
+    /// Get success rate over time
+    SuccessRateTimeseries {
+        period: Option<String>,
+        bucket: Option<String>,
+    },
     /// Get fee statistics
     FeeStats {
         period: Option<String>,
+        /// Separate stats for vote and non-vote transactions
+        #[arg(long)]
+        split_vote: bool,
+    },
+    /// Get fee percentiles (p25/p50/p75/p95/p99)
+    FeePercentiles {
+        period: Option<String>,
+        /// Print as JSON, same as `--output json`
+        #[arg(long)]
+        json: bool,
+    },
+    /// Estimate how many transactions retry failures of the previous bucket
+    RetryRate {
+        period: Option<String>,
+        bucket: Option<String>,
+    },
+    /// Get time buckets with abnormally high average fees
+    FeeSpikes {
+        period: Option<String>,
+        bucket: Option<String>,
+        /// Minimum ratio to the average of the previous 5 buckets (default 3)
+        threshold: Option<f64>,
+    },
+    /// Get the success rate by compute unit price
+    CuCorrelation {
+        period: Option<String>,
+        /// Number of equal width price buckets (default 10)
+        buckets: Option<u8>,
+    },
+    /// Get compute unit consumption statistics
+    ComputeStats {
+        period: Option<String>,
     },
     /// Get total Fees
     TotalFees {
@@ -41,24 +121,239 @@ enum Commands {
     Tps {
         period: Option<String>,
     },
+    /// Get the average TPS of the last seconds
+    RollingTps {
+        /// Length of the window in seconds, defaults to 60
+        window_seconds: Option<u64>,
+    },
     /// Get transactions per second (TPS) in time series
     TpsTimeseries {
         period: Option<String>,
         bucket: Option<String>,
     },
+    /// Get the time between consecutive slots
+    BlockTime {
+        period: Option<String>,
+    },
+    /// Get transaction rate over time for each DEX
+    DexTps {
+        period: Option<String>,
+        bucket: Option<String>,
+    },
+    /// Get the share of failed transactions of each DEX
+    DexRevertRate {
+        period: Option<String>,
+    },
     // Get slot stats
     SlotStats {
         period: Option<String>,
     },
+    /// Get the distribution of transactions per slot as a histogram
+    SlotDensity {
+        period: Option<String>,
+    },
+    /// Get transaction counts and success rates by number of instructions
+    InstructionDist {
+        period: Option<String>,
+    },
+    /// Get CPI depth statistics, or the deepest transactions with --min-depth
+    CpiDepth {
+        period: Option<String>,
+        /// List transactions with at least this CPI depth instead
+        #[arg(long)]
+        min_depth: Option<u8>,
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
     /// Get failed transactions
     FailedTransactions {
         period: Option<String>,
         limit: Option<usize>,
     },
+    /// Get failures per bucket of the most frequent error messages
+    ErrorSeries {
+        period: Option<String>,
+        bucket: Option<String>,
+        /// Number of error messages to track
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+    },
+    /// Get recent transactions invoking a program
+    ByProgram {
+        program_id: String,
+        period: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Get recent transactions involving an account
+    ByAccount {
+        account_key: String,
+        period: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Export transactions as CSV, streaming so any number of rows fits in memory
+    Export {
+        period: Option<String>,
+        /// Only transactions of this DEX
+        #[arg(long)]
+        dex: Option<String>,
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Get the lamport balance of an account per time bucket
+    LamportHistory {
+        pubkey: String,
+        period: Option<String>,
+        bucket: Option<String>,
+    },
+    /// Get the lamport balance of an account after each recorded write
+    AccountHistory {
+        pubkey: String,
+        period: Option<String>,
+    },
+    /// Count and list non-vote transactions that paid no fee
+    ZeroFee {
+        period: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Search the log messages of transactions, ignoring case
+    SearchLogs {
+        pattern: String,
+        period: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Get the most frequently written accounts
+    HotAccounts {
+        period: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Get the net lamports flowing into a program's accounts over time
+    SolFlow {
+        program_id: String,
+        period: Option<String>,
+        bucket: Option<String>,
+    },
+    /// Get the most invoked programs
+    ProgramUsage {
+        period: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Get the programs invoked for the first time within the period
+    NewPrograms {
+        period: Option<String>,
+    },
+    /// Count distinct fee payers
+    UniqueTraders {
+        period: Option<String>,
+    },
+    /// Count distinct fee payers over time
+    UniqueTradersSeries {
+        period: Option<String>,
+        bucket: Option<String>,
+    },
+    /// Get the transaction count and volume of several fee payers
+    BatchTraderStats {
+        /// Fee payer addresses, at most 1000
+        #[arg(required = true)]
+        addresses: Vec<String>,
+        /// Time period (e.g., "1h", "24h", "7d", "30d")
+        #[arg(long)]
+        period: Option<String>,
+    },
+    /// Estimate the profit and loss of traders from their swaps, priced in USDC
+    TraderPnl {
+        period: Option<String>,
+        limit: Option<usize>,
+        /// Leave out traders with fewer priced swaps
+        #[arg(long, default_value_t = 5)]
+        min_swaps: u64,
+    },
+    /// Get the number and fees of Jito bundle transactions
+    JitoBundles {
+        period: Option<String>,
+    },
+    /// Get the most common instruction types by Anchor discriminator
+    InstructionTypes {
+        period: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Get possible sandwich attacks
+    Sandwiches {
+        period: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Count arbitrage transactions, or their share per bucket when a bucket is given
+    Arbitrage {
+        period: Option<String>,
+        bucket: Option<String>,
+    },
+    /// Get the share of slots containing indexed transactions
+    FillRate {
+        period: Option<String>,
+    },
+    /// Get transaction counts by day of week and hour of day (UTC)
+    Heatmap {
+        period: Option<String>,
+    },
+    /// Get a one row report of a UTC day
+    DailySummary {
+        /// Day as YYYY-MM-DD
+        date: String,
+    },
     /// Get transaction by signature
     Transaction {
         signature: Option<String>,
     },
+    /// Get the most traded tokens
+    TopTokens {
+        period: Option<String>,
+        /// Ranking metric ("volume", "swaps", "traders")
+        metric: Option<String>,
+        limit: Option<usize>,
+    },
+    /// Get the swap volume of each token, bought and sold
+    TokenVolume {
+        period: Option<String>,
+        limit: Option<usize>,
+        /// Ranking ("net_flow", "total_volume", "swap_count")
+        #[arg(long, default_value = "total_volume")]
+        sort_by: String,
+    },
+    /// Re-insert transaction batches from the dead letter file
+    ReplayDeadLetter {
+        /// Dead letter file, defaults to DEAD_LETTER_PATH
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Transactions per insert
+        #[arg(long, default_value_t = 500)]
+        batch_size: usize,
+    },
+    /// Check that ClickHouse is reachable and print the round-trip time
+    Ping,
+    /// Delete data older than `days` days, 0 keeps data forever
+    SetTtl {
+        days: u32,
+        /// Table to change, all tables with a timestamp if not given
+        table: Option<String>,
+    },
+    /// Row counts and disk usage per table
+    Stats,
+    /// Copy transactions into a table with the latest schema, stop the indexer first
+    RebuildTransactions,
+    /// Load token names from a CSV file with `mint,display_name,decimals` columns
+    ImportTokenMints {
+        file: PathBuf,
+    },
+    /// Run an ad-hoc read-only SQL query and print the rows as JSON
+    QueryRaw {
+        sql: String,
+    },
+    /// Refresh TPS, success rate and fee stats until Ctrl-C
+    Watch {
+        period: Option<String>,
+        /// Refresh interval in seconds
+        interval: Option<u64>,
+    },
 }
 
 #[tokio::main]
@@ -67,87 +362,485 @@ async fn main() -> Result<()> {
 
     dotenv::dotenv().ok();
 
-    let client = ClickhouseClient::new(
-        &std::env::var("CLICKHOUSE_URL")?,
-        &std::env::var("CLICKHOUSE_USER")?,
-        &std::env::var("CLICKHOUSE_PASSWORD")?,
-        &std::env::var("CLICKHOUSE_DB")?,
-    )
-    .await?;
+    let config = Config::load(cli.config)?;
+
+    let client = ClickhouseClient::builder()
+        .url(&config.clickhouse_url)
+        .user(&config.clickhouse_user)
+        .password(&config.clickhouse_password)
+        .database(&config.clickhouse_db)
+        .compression(config.clickhouse_compression)
+        .build()
+        .await?;
+
+    let output = cli.output;
+
+    // These work on the raw client rather than the query service
+    match cli.command {
+        Commands::ReplayDeadLetter { file, batch_size } => {
+            let file = file.unwrap_or_else(|| config.dead_letter_path.clone().into());
+            return replay_dead_letter(&client, file, batch_size).await;
+        }
+        Commands::SetTtl { days, table } => {
+            let tables = match &table {
+                Some(table) => vec![table.as_str()],
+                None => RETENTION_TABLES.to_vec(),
+            };
+            for table in tables {
+                client.set_ttl(table, days).await?;
+                println!("{}: {}", table, format_retention(days));
+            }
+            return Ok(());
+        }
+        Commands::RebuildTransactions => {
+            let rows = client.rebuild_transactions_table().await?;
+            println!("Rebuilt transactions, {} rows", rows);
+            return Ok(());
+        }
+        Commands::Ping => {
+            let round_trip = client.ping().await?;
+            output.print_one(&json!({
+                "url": config.clickhouse_url,
+                "round_trip_ms": round_trip.as_secs_f64() * 1000.0,
+            }))?;
+            return Ok(());
+        }
+        Commands::ImportTokenMints { file } => {
+            let mints = csv::Reader::from_path(&file)
+                .with_context(|| format!("Failed to open {}", file.display()))?
+                .deserialize()
+                .collect::<Result<Vec<ClickHouseTokenMint>, _>>()
+                .with_context(|| format!("Invalid token mint in {}", file.display()))?;
+            client.upsert_token_mints(&mints).await?;
+            println!("Imported {} token mints", mints.len());
+            return Ok(());
+        }
+        Commands::QueryRaw { sql } => {
+            let rows = client.execute_raw_query(&sql).await?;
+            println!("{}", serde_json::to_string_pretty(&rows)?);
+            return Ok(());
+        }
+        Commands::Stats => {
+            let stats = client.table_stats().await?;
+            match output {
+                OutputFormat::Text => {
+                    for table in stats {
+                        println!(
+                            "{}: {} rows, {} compressed, {} on disk",
+                            table.name,
+                            format_count(table.row_count),
+                            format_bytes(table.compressed_bytes),
+                            format_bytes(table.disk_bytes)
+                        );
+                    }
+                }
+                _ => output.print_rows(&stats)?,
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
 
-    let qs = QueryService::new(client);
+    let timeout = cli.timeout.unwrap_or(config.query_timeout_ms);
+    let qs = QueryService::new(client).with_timeout(Duration::from_millis(timeout));
 
     match cli.command {
-        Commands::Count { period } => {
+        Commands::Count {
+            period,
+            min_cu,
+            max_cu,
+        } => {
             let filters = TransactionFilters {
-                period: parse_period(period),
+                period: parse_period(period)?,
+                min_cu,
+                max_cu,
                 ..Default::default()
             };
 
             let count = qs.count_transactions(filters).await?;
-            println!("Total transactions: {}", count);
+            output.print_one(&json!({ "total_transactions": count }))?;
         }
         Commands::SuccessRate { period } => {
-            let p = parse_period(period).unwrap_or(TimePeriod::Last24Hours);
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
             let rate = qs.get_success_rate(p).await?;
-            println!("Success rate: {:.2}%", rate);
-        }
-        Commands::FeeStats { period } => {
-            let p = parse_period(period).unwrap_or(TimePeriod::Last24Hours);
-            let stats = qs.get_fee_stats(p).await?;
-            println!(
-                "Fees -> min: {:?}, max: {:?}, avg: {:?}, median: {:?}, total: {:?}, tx_count: {}",
-                stats.min,
-                stats.max,
-                stats.average,
-                stats.median,
-                stats.total,
-                stats.transaction_count
-            );
+            output.print_one(&json!({ "success_rate_percent": rate }))?;
+        }
+        Commands::SuccessRateTimeseries { period, bucket } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let bucket = parse_bucket(bucket)?.unwrap_or(TimeBucket::Hour);
+            let series = qs.get_success_rate_timeseries(p, bucket).await?;
+            output.print_rows(&series)?;
+        }
+        Commands::FeeStats { period, split_vote } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            if split_vote {
+                let stats = qs.get_fee_stats_by_type(p).await?;
+                output.print_one(&stats)?;
+            } else {
+                let stats = qs.get_fee_stats(p).await?;
+                output.print_one(&stats)?;
+            }
+        }
+        Commands::FeePercentiles { period, json } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let percentiles = qs.get_fee_percentiles(p).await?;
+            let output = if json { OutputFormat::Json } else { output };
+            output.print_one(&percentiles)?;
+        }
+        Commands::RetryRate { period, bucket } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let bucket = parse_bucket(bucket)?.unwrap_or(TimeBucket::FiveMinutes);
+            let retries = qs.get_retry_transaction_rate(p, bucket).await?;
+            output.print_rows(&retries)?;
+        }
+        Commands::FeeSpikes {
+            period,
+            bucket,
+            threshold,
+        } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let bucket = parse_bucket(bucket)?.unwrap_or(TimeBucket::Hour);
+            let spikes = qs
+                .get_fee_spike_events(p, bucket, threshold.unwrap_or(3.0))
+                .await?;
+            output.print_rows(&spikes)?;
+        }
+        Commands::CuCorrelation { period, buckets } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let correlation = qs
+                .get_cu_price_success_correlation(p, buckets.unwrap_or(10))
+                .await?;
+            output.print_rows(&correlation)?;
+        }
+        Commands::ComputeStats { period } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let stats = qs.get_compute_units_stats(p).await?;
+            output.print_one(&stats)?;
         }
         Commands::TotalFees { period } => {
-            let p = parse_period(period).unwrap_or(TimePeriod::Last24Hours);
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
             let total_fees = qs.get_total_fees(p).await?;
-            println!("total fees {}", total_fees)
+            output.print_one(&json!({ "total_fees": total_fees }))?;
         }
         Commands::Tps { period } => {
-            let p = parse_period(period).unwrap_or(TimePeriod::Last24Hours);
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
             let tps = qs.get_tps(p).await?;
-            println!("Tps: {} ", tps)
+            output.print_one(&json!({ "tps": tps }))?;
+        }
+        Commands::RollingTps { window_seconds } => {
+            let window_seconds = window_seconds.unwrap_or(60);
+            let tps = qs.get_rolling_tps(window_seconds).await?;
+            output.print_one(&json!({ "window_seconds": window_seconds, "tps": tps }))?;
         }
         Commands::TpsTimeseries { period, bucket } => {
-            let p = parse_period(period).unwrap_or(TimePeriod::Last24Hours);
-            let bucket = parse_bucket(bucket).unwrap_or(TimeBucket::Hour);
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let bucket = parse_bucket(bucket)?.unwrap_or(TimeBucket::Hour);
             let tps_timeseries = qs.get_tps_timeseries(p, bucket).await?;
-            println!("Tps in timeseries: {:?}", tps_timeseries);
+            output.print_rows(&tps_timeseries)?;
+        }
+        Commands::BlockTime { period } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::LastHour);
+            let block_time = qs.get_average_block_time(p).await?;
+            output.print_one(&block_time)?;
+        }
+        Commands::DexTps { period, bucket } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let bucket = parse_bucket(bucket)?.unwrap_or(TimeBucket::Hour);
+            let dex_tps = qs.get_tps_by_dex_timeseries(p, bucket).await?;
+            output.print_rows(&dex_tps)?;
+        }
+        Commands::DexRevertRate { period } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let rates = qs.get_revert_rate_by_dex(p).await?;
+            output.print_rows(&rates)?;
         }
         Commands::SlotStats { period } => {
-            let p = parse_period(period).unwrap_or(TimePeriod::Last24Hours);
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
             let slot_stats = qs.get_slot_stats(p).await?;
-            println!("slot stats: {:?}", slot_stats);
+            output.print_one(&slot_stats)?;
+        }
+        Commands::InstructionDist { period } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let buckets = qs.get_instruction_count_distribution(p).await?;
+
+            match output {
+                OutputFormat::Text => print_instruction_distribution(&buckets),
+                OutputFormat::Json | OutputFormat::Csv => output.print_rows(&buckets)?,
+            }
         }
-        Commands::Recent { limit, period } => {
+        Commands::CpiDepth {
+            period,
+            min_depth,
+            limit,
+        } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            match min_depth {
+                Some(min_depth) => {
+                    let transactions = qs.get_high_cpi_transactions(p, min_depth, limit).await?;
+                    output.print_rows(&transactions)?;
+                }
+                None => {
+                    let stats = qs.get_cpi_depth_stats(p).await?;
+                    output.print_one(&stats)?;
+                }
+            }
+        }
+        Commands::SlotDensity { period } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::LastHour);
+            let buckets = qs.get_slot_density_distribution(p).await?;
+
+            match output {
+                OutputFormat::Text => print_slot_density(&buckets),
+                OutputFormat::Json | OutputFormat::Csv => output.print_rows(&buckets)?,
+            }
+        }
+        Commands::Recent {
+            limit,
+            period,
+            min_cu,
+            max_cu,
+            cursor,
+        } => {
+            let filters = TransactionFilters {
+                period: parse_period(period)?,
+                min_cu,
+                max_cu,
+                ..Default::default()
+            };
+
+            let cursor = cursor
+                .as_deref()
+                .map(PaginationCursor::decode)
+                .transpose()?;
+
+            let (txs, next_cursor) = qs
+                .get_recent_transactions_paged(limit.unwrap_or(10), cursor, filters)
+                .await?;
+
+            output.print_rows(&txs)?;
+
+            // stderr so the hint does not end up in piped JSON/CSV
+            if let Some(next_cursor) = next_cursor {
+                eprintln!("Next page: --cursor {}", next_cursor.encode()?);
+            }
+        }
+        Commands::ByProgram {
+            program_id,
+            period,
+            limit,
+        } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let txs = qs
+                .get_transactions_by_program(&program_id, p, limit.unwrap_or(10))
+                .await?;
+            output.print_rows(&txs)?;
+        }
+        Commands::ByAccount {
+            account_key,
+            period,
+            limit,
+        } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let txs = qs
+                .get_transactions_by_account(&account_key, p, limit.unwrap_or(10))
+                .await?;
+            output.print_rows(&txs)?;
+        }
+        Commands::Export { period, dex, file } => {
             let filters = TransactionFilters {
-                period: parse_period(period),
+                period: parse_period(period)?,
+                dex,
                 ..Default::default()
             };
+            // Progress goes to stderr so it does not end up in the CSV on stdout
+            let progress = |rows| eprintln!("Exported {} transactions...", rows);
+
+            let sink: Box<dyn AsyncWrite + Unpin> = match &file {
+                Some(path) => Box::new(
+                    tokio::fs::File::create(path)
+                        .await
+                        .with_context(|| format!("Failed to create {}", path.display()))?,
+                ),
+                None => Box::new(tokio::io::stdout()),
+            };
+            let mut writer = BufWriter::new(sink);
+
+            let rows = qs
+                .export_transactions_csv_with_progress(&mut writer, filters, progress)
+                .await?;
+            writer.flush().await?;
 
+            eprintln!("Exported {} transactions", rows);
+        }
+        Commands::LamportHistory {
+            pubkey,
+            period,
+            bucket,
+        } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let bucket = parse_bucket(bucket)?.unwrap_or(TimeBucket::Hour);
+            let history = qs
+                .get_account_lamport_change_timeseries(&pubkey, p, bucket)
+                .await?;
+            output.print_rows(&history)?;
+        }
+        Commands::AccountHistory { pubkey, period } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let history = qs.get_account_history(&pubkey, p).await?;
+            output.print_rows(&history)?;
+        }
+        Commands::SearchLogs {
+            pattern,
+            period,
+            limit,
+        } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
             let txs = qs
-                .get_recent_transactions(limit.unwrap_or(10), Some(filters))
+                .search_transactions_by_log(&pattern, p, limit.unwrap_or(10))
                 .await?;
+            output.print_rows(&txs)?;
+        }
+        Commands::ZeroFee { period, limit } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let count = qs.count_zero_fee_transactions(p).await?;
+            let txs = qs.get_zero_fee_transactions(p, limit.unwrap_or(10)).await?;
 
-            for tx in txs {
-                println!(
-                    "{} | slot={} | success={} | fee={:?}",
-                    tx.signature, tx.slot, tx.success, tx.fee
-                );
+            match output {
+                OutputFormat::Text => {
+                    println!("Zero fee transactions: {}", count);
+                    output.print_rows(&txs)?;
+                }
+                OutputFormat::Json => output.print_one(&json!({
+                    "count": count,
+                    "transactions": txs,
+                }))?,
+                OutputFormat::Csv => output.print_rows(&txs)?,
+            }
+        }
+        Commands::HotAccounts { period, limit } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::LastHour);
+            let accounts = qs
+                .get_account_write_frequency(p, limit.unwrap_or(20))
+                .await?;
+            output.print_rows(&accounts)?;
+        }
+        Commands::SolFlow {
+            program_id,
+            period,
+            bucket,
+        } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let bucket = parse_bucket(bucket)?.unwrap_or(TimeBucket::Hour);
+            let flow = qs.get_net_sol_flow(&program_id, p, bucket).await?;
+            output.print_rows(&flow)?;
+        }
+        Commands::ProgramUsage { period, limit } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let stats = qs.get_program_usage_stats(p, limit.unwrap_or(10)).await?;
+            output.print_rows(&stats)?;
+        }
+        Commands::NewPrograms { period } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let programs = qs.get_new_programs(p).await?;
+            output.print_rows(&programs)?;
+        }
+        Commands::UniqueTraders { period } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let count = qs.count_unique_traders(p).await?;
+            output.print_one(&json!({ "unique_traders": count }))?;
+        }
+        Commands::UniqueTradersSeries { period, bucket } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let bucket = parse_bucket(bucket)?.unwrap_or(TimeBucket::Hour);
+            let series = qs.get_unique_traders_timeseries(p, bucket).await?;
+            output.print_rows(&series)?;
+        }
+        Commands::BatchTraderStats { addresses, period } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let mut stats = qs
+                .get_transactions_by_fee_payer_batch(&addresses, p)
+                .await?;
+            // In the order given, duplicates are printed once
+            let rows: Vec<_> = addresses
+                .iter()
+                .filter_map(|address| stats.remove(address))
+                .collect();
+            output.print_rows(&rows)?;
+        }
+        Commands::TraderPnl {
+            period,
+            limit,
+            min_swaps,
+        } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let pnl = qs
+                .get_trader_pnl_estimates(p, limit.unwrap_or(20), min_swaps)
+                .await?;
+            output.print_rows(&pnl)?;
+        }
+        Commands::JitoBundles { period } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let count = qs.count_jito_bundles(p).await?;
+            let fee_stats = qs.get_jito_bundle_fee_stats(p).await?;
+
+            match output {
+                OutputFormat::Text => {
+                    println!("Jito bundle transactions: {}", count);
+                    output.print_one(&fee_stats)?;
+                }
+                OutputFormat::Json => output.print_one(&json!({
+                    "count": count,
+                    "fee_stats": fee_stats,
+                }))?,
+                OutputFormat::Csv => output.print_one(&fee_stats)?,
+            }
+        }
+        Commands::InstructionTypes { period, limit } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let stats = qs
+                .get_instruction_type_stats(p, limit.unwrap_or(20))
+                .await?;
+            output.print_rows(&stats)?;
+        }
+        Commands::Sandwiches { period, limit } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::LastHour);
+            let candidates = qs
+                .get_sandwich_attack_candidates(p, limit.unwrap_or(20))
+                .await?;
+            output.print_rows(&candidates)?;
+        }
+        Commands::Arbitrage { period, bucket } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            match parse_bucket(bucket)? {
+                Some(bucket) => {
+                    let series = qs.get_arbitrage_timeseries(p, bucket).await?;
+                    output.print_rows(&series)?;
+                }
+                None => {
+                    let count = qs.count_arbitrage_transactions(p).await?;
+                    output.print_one(&json!({ "arbitrage_transactions": count }))?;
+                }
+            }
+        }
+        Commands::FillRate { period } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let fill_rate = qs.get_block_fill_rate(p).await?;
+            output.print_one(&fill_rate)?;
+        }
+        Commands::Heatmap { period } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last7Days);
+            let cells = qs.get_hourly_activity_heatmap(p).await?;
+
+            match output {
+                OutputFormat::Text => print_heatmap(&cells),
+                _ => output.print_rows(&cells)?,
             }
         }
         Commands::Transaction { signature } => {
             if let Some(sig) = signature {
                 let tx = qs.get_transaction(&sig).await?;
                 match tx {
-                    Some(t) => println!("Transaction details: {:?}", t),
+                    Some(t) => output.print_one(&t)?,
                     None => println!("invalid signature"),
                 }
             } else {
@@ -155,31 +848,353 @@ async fn main() -> Result<()> {
             }
         }
         Commands::FailedTransactions { period, limit } => {
-            let p = parse_period(period).unwrap_or(TimePeriod::Last24Hours);
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
             let failed_tx = qs.get_failed_transactions(p, limit).await?;
-            println!("failed transaction: {:?}", failed_tx);
+            output.print_rows(&failed_tx)?;
+        }
+        Commands::ErrorSeries {
+            period,
+            bucket,
+            top,
+        } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let bucket = parse_bucket(bucket)?.unwrap_or(TimeBucket::Hour);
+            let series = qs
+                .get_failed_transaction_error_timeseries(p, bucket, top)
+                .await?;
+            output.print_rows(&series)?;
+        }
+        Commands::TopTokens {
+            period,
+            metric,
+            limit,
+        } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let metric = parse_token_metric(metric).unwrap_or(TokenMetric::ByVolume);
+            let tokens = qs
+                .get_top_traded_tokens(p, metric, limit.unwrap_or(10))
+                .await?;
+            output.print_rows(&tokens)?;
+        }
+        Commands::TokenVolume {
+            period,
+            limit,
+            sort_by,
+        } => {
+            let p = parse_period(period)?.unwrap_or(TimePeriod::Last24Hours);
+            let sort: TokenVolumeSort = sort_by.parse()?;
+            let tokens = qs
+                .get_volume_by_token_sorted(p, sort, limit.unwrap_or(20))
+                .await?;
+            output.print_rows(&tokens)?;
+        }
+        Commands::DailySummary { date } => {
+            let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                .with_context(|| format!("invalid date '{}', expected YYYY-MM-DD", date))?;
+            let summary = qs.get_daily_summary(date).await?;
+            output.print_one(&summary)?;
+        }
+        Commands::Watch { period, interval } => {
+            // Validate once up front, relative periods are re-parsed on every tick
+            parse_period(period.clone())?;
+            watch(&qs, period, interval.unwrap_or(5)).await?;
+        }
+        Commands::ReplayDeadLetter { .. }
+        | Commands::SetTtl { .. }
+        | Commands::Ping
+        | Commands::RebuildTransactions
+        | Commands::ImportTokenMints { .. }
+        | Commands::QueryRaw { .. }
+        | Commands::Stats => {
+            unreachable!("handled before creating the query service")
         }
     }
 
     Ok(())
 }
 
-fn parse_period(p: Option<String>) -> Option<TimePeriod> {
-    match p.as_deref() {
-        Some("1h") => Some(TimePeriod::LastHour),
-        Some("24h") => Some(TimePeriod::Last24Hours),
-        Some("7d") => Some(TimePeriod::Last7Days),
-        Some("30d") => Some(TimePeriod::Last30Days),
-        _ => None,
+async fn watch(qs: &QueryService, period: Option<String>, interval_secs: u64) -> Result<()> {
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+    // One handler for the whole loop, so a Ctrl-C during the queries isn't missed
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = &mut ctrl_c => return Ok(()),
+        }
+
+        let p = parse_period(period.clone())?.unwrap_or(TimePeriod::Last24Hours);
+        let stats = tokio::select! {
+            stats = async {
+                tokio::try_join!(qs.get_tps(p), qs.get_success_rate(p), qs.get_fee_stats(p))
+            } => stats,
+            _ = &mut ctrl_c => return Ok(()),
+        };
+
+        // Clear the screen and move the cursor to the top left
+        print!("\x1B[2J\x1B[H");
+        println!(
+            "Last updated: {} (every {}s, Ctrl-C to exit)",
+            Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+            interval_secs
+        );
+        println!();
+
+        // A failed refresh, e.g. while ClickHouse restarts, is shown until the next one
+        let (tps, success_rate, fees) = match stats {
+            Ok(stats) => stats,
+            Err(e) => {
+                println!("Error: {:#}", e);
+                continue;
+            }
+        };
+        println!("{:<16} {:>16}", "TPS", format!("{:.2}", tps));
+        println!(
+            "{:<16} {:>16}",
+            "Success rate",
+            format!("{:.2}%", success_rate)
+        );
+        println!("{:<16} {:>16}", "Transactions", fees.transaction_count);
+        println!("{:<16} {:>16}", "Fee min", format_option(fees.min));
+        println!("{:<16} {:>16}", "Fee median", format_option(fees.median));
+        println!(
+            "{:<16} {:>16}",
+            "Fee avg",
+            format_option(fees.average.map(|avg| format!("{:.2}", avg)))
+        );
+        println!("{:<16} {:>16}", "Fee max", format_option(fees.max));
+        println!("{:<16} {:>16}", "Fees total", format_option(fees.total));
+    }
+}
+
+fn format_option<T: ToString>(value: Option<T>) -> String {
+    value.map_or("-".to_string(), |value| value.to_string())
+}
+
+async fn replay_dead_letter(
+    client: &ClickhouseClient,
+    path: PathBuf,
+    batch_size: usize,
+) -> Result<()> {
+    const PROGRESS_EVERY: usize = 1000;
+
+    anyhow::ensure!(batch_size > 0, "--batch-size must be greater than 0");
+
+    let dead_letter = DeadLetterQueue::new(path);
+
+    let (batches, read_len) = dead_letter.read_all()?;
+    if batches.is_empty() {
+        println!("No batches in {}", dead_letter.path().display());
+        return Ok(());
+    }
+
+    let total: usize = batches.iter().map(|batch| batch.transactions.len()).sum();
+    let mut failed_batches = Vec::new();
+    let (mut replayed, mut failed) = (0, 0);
+
+    for batch in batches {
+        for chunk in batch.transactions.chunks(batch_size) {
+            let processed_before = replayed + failed;
+
+            let retry = DeadLetterBatch {
+                timestamp: batch.timestamp,
+                error: batch.error.clone(),
+                transactions: chunk.to_vec(),
+                retry_count: batch.retry_count,
+            };
+
+            match client.batch_insert_transactions(chunk).await {
+                Ok(_) => {
+                    dead_letter.mark_done(&retry)?;
+                    replayed += chunk.len();
+                }
+                Err(e) => {
+                    println!("Failed to replay batch from {}: {}", batch.timestamp, e);
+                    failed += chunk.len();
+                    failed_batches.push(DeadLetterBatch {
+                        error: e.to_string(),
+                        retry_count: retry.retry_count + 1,
+                        ..retry
+                    });
+                }
+            }
+
+            if (replayed + failed) / PROGRESS_EVERY > processed_before / PROGRESS_EVERY {
+                println!("{}/{} rows processed", replayed + failed, total);
+            }
+        }
+    }
+
+    // Keep only what still failed so the next replay doesn't insert duplicates
+    dead_letter.rewrite(&failed_batches, read_len)?;
+
+    println!(
+        "Replayed {} transactions, {} failed ({} batches left in {}, replayed rows moved to {})",
+        replayed,
+        failed,
+        failed_batches.len(),
+        dead_letter.path().display(),
+        dead_letter.done_path().display()
+    );
+
+    Ok(())
+}
+
+fn format_retention(days: u32) -> String {
+    match days {
+        0 => "kept forever".to_string(),
+        days => format!("rows deleted after {} days", days),
+    }
+}
+
+/// Print a 7x24 grid of transaction counts, one row per day of week
+fn print_heatmap(cells: &[HeatmapCell]) {
+    const DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+    let mut grid = [[0u64; 24]; 7];
+    for cell in cells {
+        if let Some(row) = grid.get_mut((cell.day_of_week as usize).wrapping_sub(1))
+            && let Some(count) = row.get_mut(cell.hour_of_day as usize)
+        {
+            *count = cell.tx_count;
+        }
+    }
+
+    let width = grid
+        .iter()
+        .flatten()
+        .map(|count| count.to_string().len())
+        .max()
+        .unwrap_or(1)
+        .max(2);
+
+    print!("    ");
+    for hour in 0..24 {
+        print!(" {:>width$}", hour, width = width);
+    }
+    println!();
+
+    for (day, row) in DAYS.iter().zip(grid.iter()) {
+        print!("{} ", day);
+        for count in row {
+            print!(" {:>width$}", count, width = width);
+        }
+        println!();
+    }
+}
+
+/// Print one bar per bucket, scaled to the bucket with the most slots
+fn print_slot_density(buckets: &[SlotDensityBucket]) {
+    const BAR_WIDTH: u64 = 50;
+
+    if buckets.is_empty() {
+        println!("No slots in this period");
+        return;
     }
+
+    let labels: Vec<String> = buckets
+        .iter()
+        .map(|bucket| format!("{}-{}", bucket.bucket_start, bucket.bucket_end))
+        .collect();
+    let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+    let max_slots = buckets
+        .iter()
+        .map(|bucket| bucket.slot_count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    for (label, bucket) in labels.iter().zip(buckets) {
+        // Any non-empty bucket gets at least one block so it stands out from empty ones
+        let bar_len = (bucket.slot_count * BAR_WIDTH).div_ceil(max_slots);
+        println!(
+            "{:>width$} | {} {}",
+            label,
+            "█".repeat(bar_len as usize),
+            bucket.slot_count,
+            width = label_width
+        );
+    }
+}
+
+fn print_instruction_distribution(buckets: &[InstructionCountBucket]) {
+    const BAR_WIDTH: u64 = 50;
+
+    if buckets.is_empty() {
+        println!("No transactions in this period");
+        return;
+    }
+
+    let label_width = buckets
+        .iter()
+        .map(|bucket| bucket.instruction_count.to_string().len())
+        .max()
+        .unwrap_or(0);
+    let max_txs = buckets
+        .iter()
+        .map(|bucket| bucket.tx_count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    for bucket in buckets {
+        let bar_len = (bucket.tx_count * BAR_WIDTH).div_ceil(max_txs);
+        println!(
+            "{:>width$} | {:<bar_width$} {} ({:.1}% ok)",
+            bucket.instruction_count,
+            "█".repeat(bar_len as usize),
+            bucket.tx_count,
+            bucket.success_rate,
+            width = label_width,
+            bar_width = BAR_WIDTH as usize
+        );
+    }
+}
+
+/// 1234567 -> "1.2M"
+fn format_count(count: u64) -> String {
+    const UNITS: &[(u64, &str)] = &[(1_000_000_000, "B"), (1_000_000, "M"), (1_000, "K")];
+
+    UNITS
+        .iter()
+        .find(|(size, _)| count >= *size)
+        .map(|(size, unit)| format!("{:.1}{}", count as f64 / *size as f64, unit))
+        .unwrap_or_else(|| count.to_string())
+}
+
+/// 48318382080 -> "45.0 GB"
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn parse_period(p: Option<String>) -> Result<Option<TimePeriod>> {
+    p.as_deref().map(str::parse).transpose()
+}
+
+fn parse_bucket(b: Option<String>) -> Result<Option<TimeBucket>> {
+    b.as_deref().map(str::parse).transpose()
 }
 
-fn parse_bucket(b: Option<String>) -> Option<TimeBucket> {
-    match b.as_deref() {
-        Some("M") => Some(TimeBucket::Minute),
-        Some("H") => Some(TimeBucket::Hour),
-        Some("D") => Some(TimeBucket::Day),
-        Some("W") => Some(TimeBucket::Week),
+fn parse_token_metric(m: Option<String>) -> Option<TokenMetric> {
+    match m.as_deref() {
+        Some("volume") => Some(TokenMetric::ByVolume),
+        Some("swaps") => Some(TokenMetric::BySwapCount),
+        Some("traders") => Some(TokenMetric::ByUniqueTraders),
         _ => None,
     }
 }
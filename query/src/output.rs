@@ -0,0 +1,117 @@
+use std::io;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use comfy_table::{Table, presets::UTF8_FULL};
+use serde::Serialize;
+use serde_json::Value;
+
+/// How query results are printed, see the global `--output` flag
+#[derive(Debug, Clone, Copy, Default, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned human-readable tables
+    #[default]
+    Text,
+    /// Pretty printed JSON, e.g. to pipe into `jq`
+    Json,
+    /// CSV with a header row derived from the field names
+    Csv,
+}
+
+impl OutputFormat {
+    /// Print a single result, as a field/value table in text mode
+    pub fn print_one<T: Serialize>(self, value: &T) -> Result<()> {
+        match self {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+            OutputFormat::Csv => print_csv(&[serde_json::to_value(value)?])?,
+            OutputFormat::Text => {
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL).set_header(["field", "value"]);
+
+                match serde_json::to_value(value)? {
+                    Value::Object(fields) => {
+                        for (field, value) in fields {
+                            table.add_row([field, cell(&value)]);
+                        }
+                    }
+                    other => {
+                        table.add_row(["value".to_string(), cell(&other)]);
+                    }
+                }
+
+                println!("{table}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Print a list of results, one row per item
+    pub fn print_rows<T: Serialize>(self, rows: &[T]) -> Result<()> {
+        match self {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(rows)?),
+            OutputFormat::Csv => print_csv(&to_values(rows)?)?,
+            OutputFormat::Text => {
+                let values = to_values(rows)?;
+                if values.is_empty() {
+                    println!("No results");
+                    return Ok(());
+                }
+
+                let mut table = Table::new();
+                table.load_preset(UTF8_FULL).set_header(headers(&values));
+                for value in &values {
+                    table.add_row(row(value));
+                }
+
+                println!("{table}");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn to_values<T: Serialize>(rows: &[T]) -> Result<Vec<Value>> {
+    rows.iter()
+        .map(|row| Ok(serde_json::to_value(row)?))
+        .collect()
+}
+
+fn print_csv(values: &[Value]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(io::stdout());
+
+    if !values.is_empty() {
+        writer.write_record(headers(values))?;
+    }
+    for value in values {
+        writer.write_record(row(value))?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Field names of the first row, in declaration order
+fn headers(values: &[Value]) -> Vec<String> {
+    match values.first() {
+        Some(Value::Object(fields)) => fields.keys().cloned().collect(),
+        _ => vec!["value".to_string()],
+    }
+}
+
+fn row(value: &Value) -> Vec<String> {
+    match value {
+        Value::Object(fields) => fields.values().map(cell).collect(),
+        other => vec![cell(other)],
+    }
+}
+
+/// Strings unquoted, missing values empty, nested values as compact JSON
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}